@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use clap::{Args, Parser, Subcommand};
+
+/// Keep DNS records in sync with the public IP address of this machine.
+#[derive(Debug, Parser)]
+#[command(name = "app", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub overrides: Overrides,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Sync the records every refresh interval until stopped (default).
+    Run,
+    /// Sync the records once, exits non-zero when the sync failed.
+    Once,
+    /// Print the planned changes without applying them, exits with 2 when drift was found.
+    Plan,
+    /// Print the detected public IP addresses.
+    ShowIp,
+    /// Print the records currently managed at the DNS provider.
+    ListRecords,
+    /// Check the configuration and exit.
+    ValidateConfig,
+}
+
+impl Command {
+    /// The command to execute, `run` when none is given. Under dry-run the commands changing
+    /// records only print their plan.
+    pub fn resolve(command: Option<Command>, dry_run: bool) -> Command {
+        match command.unwrap_or(Command::Run) {
+            Command::Run | Command::Once if dry_run => Command::Plan,
+            command => command,
+        }
+    }
+}
+
+/// Flags overriding the environment variable of the same name.
+#[derive(Debug, Default, Args)]
+pub struct Overrides {
+    /// TOML or YAML configuration file, overrides CONFIG_FILE.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<String>,
+    /// Overrides RUST_LOG.
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+    /// Overrides ENVIRONMENT.
+    #[arg(long, global = true)]
+    pub environment: Option<String>,
+    /// Overrides DOMAIN.
+    #[arg(long, global = true)]
+    pub domain: Option<String>,
+    /// Overrides DNS_PROVIDER.
+    #[arg(long, global = true)]
+    pub dns_provider: Option<String>,
+    /// Overrides DNS_ENTRIES_TO_SYNC.
+    #[arg(long, global = true)]
+    pub dns_entries_to_sync: Option<String>,
+    /// Overrides DNS_RECORD_TYPES.
+    #[arg(long, global = true)]
+    pub dns_record_types: Option<String>,
+    /// Overrides REFRESH_INTERVAL_SECONDS.
+    #[arg(long, global = true)]
+    pub refresh_interval_seconds: Option<String>,
+    /// Overrides IP_SOURCES.
+    #[arg(long, global = true)]
+    pub ip_sources: Option<String>,
+    /// Overrides IP_SOURCES_QUORUM.
+    #[arg(long, global = true)]
+    pub ip_sources_quorum: Option<String>,
+    /// Overrides ALLOW_PRIVATE_IPS.
+    #[arg(long, global = true)]
+    pub allow_private_ips: Option<String>,
+    /// Overrides RETRY_MAX_ATTEMPTS.
+    #[arg(long, global = true)]
+    pub retry_max_attempts: Option<String>,
+    /// Overrides RETRY_MAX_DELAY_SECONDS.
+    #[arg(long, global = true)]
+    pub retry_max_delay_seconds: Option<String>,
+    /// Overrides RETRY_DEADLINE_SECONDS.
+    #[arg(long, global = true)]
+    pub retry_deadline_seconds: Option<String>,
+    /// Overrides HTTP_CONNECT_TIMEOUT_SECONDS.
+    #[arg(long, global = true)]
+    pub http_connect_timeout_seconds: Option<String>,
+    /// Overrides HTTP_REQUEST_TIMEOUT_SECONDS.
+    #[arg(long, global = true)]
+    pub http_request_timeout_seconds: Option<String>,
+    /// Overrides HTTP_USER_AGENT.
+    #[arg(long, global = true)]
+    pub http_user_agent: Option<String>,
+    /// Overrides HTTP_PROXY_URL.
+    #[arg(long, global = true)]
+    pub http_proxy_url: Option<String>,
+    /// Overrides CLOUDFLARE_API_URL.
+    #[arg(long, global = true)]
+    pub cloudflare_api_url: Option<String>,
+    /// Overrides CLOUDFLARE_ZONE_ID.
+    #[arg(long, global = true)]
+    pub cloudflare_zone_id: Option<String>,
+    /// Overrides CLOUDFLARE_API_KEY.
+    #[arg(long, global = true)]
+    pub cloudflare_api_key: Option<String>,
+    /// Overrides CLOUDFLARE_PROXY_ENABLED.
+    #[arg(long, global = true)]
+    pub cloudflare_proxy_enabled: Option<String>,
+    /// Overrides ROUTE53_API_URL.
+    #[arg(long, global = true)]
+    pub route53_api_url: Option<String>,
+    /// Overrides ROUTE53_HOSTED_ZONE_ID.
+    #[arg(long, global = true)]
+    pub route53_hosted_zone_id: Option<String>,
+    /// Overrides ROUTE53_TTL.
+    #[arg(long, global = true)]
+    pub route53_ttl: Option<String>,
+    /// Overrides AWS_PROFILE.
+    #[arg(long, global = true)]
+    pub aws_profile: Option<String>,
+    /// Overrides AWS_ACCESS_KEY_ID.
+    #[arg(long, global = true)]
+    pub aws_access_key_id: Option<String>,
+    /// Overrides AWS_SECRET_ACCESS_KEY.
+    #[arg(long, global = true)]
+    pub aws_secret_access_key: Option<String>,
+    /// Overrides AWS_SESSION_TOKEN.
+    #[arg(long, global = true)]
+    pub aws_session_token: Option<String>,
+    /// Overrides AWS_SHARED_CREDENTIALS_FILE.
+    #[arg(long, global = true)]
+    pub aws_shared_credentials_file: Option<String>,
+    /// Overrides RFC2136_SERVER.
+    #[arg(long, global = true)]
+    pub rfc2136_server: Option<String>,
+    /// Overrides RFC2136_ZONE.
+    #[arg(long, global = true)]
+    pub rfc2136_zone: Option<String>,
+    /// Overrides RFC2136_TTL.
+    #[arg(long, global = true)]
+    pub rfc2136_ttl: Option<String>,
+    /// Overrides RFC2136_TIMEOUT_SECONDS.
+    #[arg(long, global = true)]
+    pub rfc2136_timeout_seconds: Option<String>,
+    /// Overrides RFC2136_TSIG_KEY_NAME.
+    #[arg(long, global = true)]
+    pub rfc2136_tsig_key_name: Option<String>,
+    /// Overrides RFC2136_TSIG_SECRET.
+    #[arg(long, global = true)]
+    pub rfc2136_tsig_secret: Option<String>,
+    /// Overrides RFC2136_TSIG_ALGORITHM.
+    #[arg(long, global = true)]
+    pub rfc2136_tsig_algorithm: Option<String>,
+    /// Overrides DYNDNS2_URL.
+    #[arg(long, global = true)]
+    pub dyndns2_url: Option<String>,
+    /// Overrides DYNDNS2_USERNAME.
+    #[arg(long, global = true)]
+    pub dyndns2_username: Option<String>,
+    /// Overrides DYNDNS2_PASSWORD.
+    #[arg(long, global = true)]
+    pub dyndns2_password: Option<String>,
+    /// Overrides DIGITALOCEAN_API_URL.
+    #[arg(long, global = true)]
+    pub digitalocean_api_url: Option<String>,
+    /// Overrides DIGITALOCEAN_ZONE.
+    #[arg(long, global = true)]
+    pub digitalocean_zone: Option<String>,
+    /// Overrides DIGITALOCEAN_API_TOKEN.
+    #[arg(long, global = true)]
+    pub digitalocean_api_token: Option<String>,
+    /// Overrides HETZNER_API_URL.
+    #[arg(long, global = true)]
+    pub hetzner_api_url: Option<String>,
+    /// Overrides HETZNER_ZONE_ID.
+    #[arg(long, global = true)]
+    pub hetzner_zone_id: Option<String>,
+    /// Overrides HETZNER_API_TOKEN.
+    #[arg(long, global = true)]
+    pub hetzner_api_token: Option<String>,
+    /// Overrides POWERDNS_API_URL.
+    #[arg(long, global = true)]
+    pub powerdns_api_url: Option<String>,
+    /// Overrides POWERDNS_API_KEY.
+    #[arg(long, global = true)]
+    pub powerdns_api_key: Option<String>,
+    /// Overrides POWERDNS_SERVER_ID.
+    #[arg(long, global = true)]
+    pub powerdns_server_id: Option<String>,
+    /// Overrides POWERDNS_ZONE.
+    #[arg(long, global = true)]
+    pub powerdns_zone: Option<String>,
+    /// Overrides POWERDNS_TTL.
+    #[arg(long, global = true)]
+    pub powerdns_ttl: Option<String>,
+    /// Overrides PLAN_FORMAT.
+    #[arg(long, global = true)]
+    pub plan_format: Option<String>,
+    /// Overrides OWNER_ID.
+    #[arg(long, global = true)]
+    pub owner_id: Option<String>,
+    /// Overrides DUPLICATE_POLICY.
+    #[arg(long, global = true)]
+    pub duplicate_policy: Option<String>,
+    /// Print the plan as JSON, same as `--plan-format json`, `--json=false` prints text.
+    #[arg(long, global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub json: Option<bool>,
+    /// Only print the planned changes, overrides DRY_RUN. `--dry-run=false` turns it off.
+    #[arg(long, global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub dry_run: Option<bool>,
+    /// Delete owned records that are no longer configured, overrides PRUNE. `--prune=false` turns it off.
+    #[arg(long, global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub prune: Option<bool>,
+}
+
+impl Overrides {
+    /// The flags that were given, keyed by the environment variable they override.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let values = [
+            ("CONFIG_FILE", &self.config),
+            ("RUST_LOG", &self.log_level),
+            ("ENVIRONMENT", &self.environment),
+            ("DOMAIN", &self.domain),
+            ("DNS_PROVIDER", &self.dns_provider),
+            ("DNS_ENTRIES_TO_SYNC", &self.dns_entries_to_sync),
+            ("DNS_RECORD_TYPES", &self.dns_record_types),
+            ("REFRESH_INTERVAL_SECONDS", &self.refresh_interval_seconds),
+            ("IP_SOURCES", &self.ip_sources),
+            ("IP_SOURCES_QUORUM", &self.ip_sources_quorum),
+            ("ALLOW_PRIVATE_IPS", &self.allow_private_ips),
+            ("RETRY_MAX_ATTEMPTS", &self.retry_max_attempts),
+            ("RETRY_MAX_DELAY_SECONDS", &self.retry_max_delay_seconds),
+            ("RETRY_DEADLINE_SECONDS", &self.retry_deadline_seconds),
+            ("HTTP_CONNECT_TIMEOUT_SECONDS", &self.http_connect_timeout_seconds),
+            ("HTTP_REQUEST_TIMEOUT_SECONDS", &self.http_request_timeout_seconds),
+            ("HTTP_USER_AGENT", &self.http_user_agent),
+            ("HTTP_PROXY_URL", &self.http_proxy_url),
+            ("CLOUDFLARE_API_URL", &self.cloudflare_api_url),
+            ("CLOUDFLARE_ZONE_ID", &self.cloudflare_zone_id),
+            ("CLOUDFLARE_API_KEY", &self.cloudflare_api_key),
+            ("CLOUDFLARE_PROXY_ENABLED", &self.cloudflare_proxy_enabled),
+            ("ROUTE53_API_URL", &self.route53_api_url),
+            ("ROUTE53_HOSTED_ZONE_ID", &self.route53_hosted_zone_id),
+            ("ROUTE53_TTL", &self.route53_ttl),
+            ("AWS_PROFILE", &self.aws_profile),
+            ("AWS_ACCESS_KEY_ID", &self.aws_access_key_id),
+            ("AWS_SECRET_ACCESS_KEY", &self.aws_secret_access_key),
+            ("AWS_SESSION_TOKEN", &self.aws_session_token),
+            ("AWS_SHARED_CREDENTIALS_FILE", &self.aws_shared_credentials_file),
+            ("RFC2136_SERVER", &self.rfc2136_server),
+            ("RFC2136_ZONE", &self.rfc2136_zone),
+            ("RFC2136_TTL", &self.rfc2136_ttl),
+            ("RFC2136_TIMEOUT_SECONDS", &self.rfc2136_timeout_seconds),
+            ("RFC2136_TSIG_KEY_NAME", &self.rfc2136_tsig_key_name),
+            ("RFC2136_TSIG_SECRET", &self.rfc2136_tsig_secret),
+            ("RFC2136_TSIG_ALGORITHM", &self.rfc2136_tsig_algorithm),
+            ("DYNDNS2_URL", &self.dyndns2_url),
+            ("DYNDNS2_USERNAME", &self.dyndns2_username),
+            ("DYNDNS2_PASSWORD", &self.dyndns2_password),
+            ("DIGITALOCEAN_API_URL", &self.digitalocean_api_url),
+            ("DIGITALOCEAN_ZONE", &self.digitalocean_zone),
+            ("DIGITALOCEAN_API_TOKEN", &self.digitalocean_api_token),
+            ("HETZNER_API_URL", &self.hetzner_api_url),
+            ("HETZNER_ZONE_ID", &self.hetzner_zone_id),
+            ("HETZNER_API_TOKEN", &self.hetzner_api_token),
+            ("POWERDNS_API_URL", &self.powerdns_api_url),
+            ("POWERDNS_API_KEY", &self.powerdns_api_key),
+            ("POWERDNS_SERVER_ID", &self.powerdns_server_id),
+            ("POWERDNS_ZONE", &self.powerdns_zone),
+            ("POWERDNS_TTL", &self.powerdns_ttl),
+            ("PLAN_FORMAT", &self.plan_format),
+            ("OWNER_ID", &self.owner_id),
+            ("DUPLICATE_POLICY", &self.duplicate_policy),
+        ];
+
+        let mut overrides: HashMap<String, String> = values.into_iter()
+            .filter_map(|(key, value)| value.as_ref().map(|value| (key.to_string(), value.clone())))
+            .collect();
+
+        if let Some(json) = self.json {
+            overrides.insert("PLAN_FORMAT".to_string(), if json { "json" } else { "text" }.to_string());
+        }
+
+        if let Some(dry_run) = self.dry_run {
+            overrides.insert("DRY_RUN".to_string(), dry_run.to_string());
+        }
+
+        if let Some(prune) = self.prune {
+            overrides.insert("PRUNE".to_string(), prune.to_string());
+        }
+
+        overrides
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_flags_override_variables() {
+        let cli = Cli::parse_from([
+            "app", "once", "--domain", "example.com", "--ip-sources-quorum", "2", "--json",
+        ]);
+
+        assert_eq!(cli.command, Some(Command::Once));
+        assert_eq!(cli.overrides.to_map(), HashMap::from([
+            ("DOMAIN".to_string(), "example.com".to_string()),
+            ("IP_SOURCES_QUORUM".to_string(), "2".to_string()),
+            ("PLAN_FORMAT".to_string(), "json".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_every_credential_has_a_flag() {
+        let cli = Cli::parse_from([
+            "app", "--aws-secret-access-key", "aws", "--rfc2136-tsig-secret", "tsig", "--dyndns2-password", "dyndns2",
+            "--digitalocean-api-token", "do", "--hetzner-api-token", "hetzner", "--powerdns-api-key", "pdns",
+        ]);
+
+        assert_eq!(cli.overrides.to_map(), HashMap::from([
+            ("AWS_SECRET_ACCESS_KEY".to_string(), "aws".to_string()),
+            ("RFC2136_TSIG_SECRET".to_string(), "tsig".to_string()),
+            ("DYNDNS2_PASSWORD".to_string(), "dyndns2".to_string()),
+            ("DIGITALOCEAN_API_TOKEN".to_string(), "do".to_string()),
+            ("HETZNER_API_TOKEN".to_string(), "hetzner".to_string()),
+            ("POWERDNS_API_KEY".to_string(), "pdns".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_no_subcommand_runs_daemon() {
+        let cli = Cli::parse_from(["app", "--dry-run"]);
+
+        assert_eq!(cli.command, None);
+        assert_eq!(cli.overrides.to_map().get("DRY_RUN"), Some(&"true".to_string()));
+    }
+
+    #[test]
+    fn test_switches_turn_variables_off() {
+        let cli = Cli::parse_from(["app", "--dry-run=false", "--prune=false", "--json=false", "once"]);
+
+        assert_eq!(cli.command, Some(Command::Once));
+        assert_eq!(cli.overrides.to_map(), HashMap::from([
+            ("DRY_RUN".to_string(), "false".to_string()),
+            ("PRUNE".to_string(), "false".to_string()),
+            ("PLAN_FORMAT".to_string(), "text".to_string()),
+        ]));
+
+        let cli = Cli::parse_from(["app", "--prune", "once"]);
+        assert_eq!(cli.command, Some(Command::Once));
+        assert_eq!(cli.overrides.to_map().get("PRUNE"), Some(&"true".to_string()));
+    }
+
+    #[test]
+    fn test_dry_run_never_changes_records() {
+        assert_eq!(Command::resolve(None, true), Command::Plan);
+        assert_eq!(Command::resolve(Some(Command::Once), true), Command::Plan);
+        assert_eq!(Command::resolve(Some(Command::ListRecords), true), Command::ListRecords);
+        assert_eq!(Command::resolve(Some(Command::Once), false), Command::Once);
+        assert_eq!(Command::resolve(None, false), Command::Run);
+    }
+}
@@ -1,17 +1,23 @@
+use config::sync_entry::RecordType;
 use reqwest::{StatusCode};
 use crate::cloudflare::error::Error;
-use crate::cloudflare::types::{CloudflareZoneRecordsResponse, Record};
-use crate::exponential_backoff;
+use crate::cloudflare::types::{CloudflareZoneRecordsResponse, CloudflareZonesResponse, Record, RecordRequest, Zone};
+use crate::http::HttpClient;
 
 pub mod types;
 pub mod error;
 
+/// Page size requested when listing DNS records, the maximum Cloudflare allows is 5000 but
+/// smaller pages keep individual responses small.
+const RECORDS_PER_PAGE: i64 = 100;
+
 #[derive(Debug, Clone)]
 pub struct Client {
     pub(crate) api_key: String,
     pub(crate) zone_id: String,
     pub(crate) api_url: String,
     pub(crate) proxy_enabled: bool,
+    pub(crate) http: HttpClient,
 }
 
 impl Client {
@@ -21,18 +27,125 @@ impl Client {
             zone_id,
             api_url,
             proxy_enabled,
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Use the given zone instead of the one the client was created with.
+    pub fn with_zone_id(mut self, zone_id: String) -> Self {
+        self.zone_id = zone_id;
+        self
+    }
+
+    /// Find the id of the zone serving the domain. Cloudflare only matches zone names exactly,
+    /// so the parent domains are tried in turn when the domain is below the zone apex.
+    ///
+    /// # Errors
+    /// When no zone visible to the API token serves the domain, or several zones share its name.
+    pub async fn find_zone_id(&self, domain: &str) -> Result<String, Error> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let mut name = domain.as_str();
+
+        loop {
+            let mut zones = self.get_zones(name).await?;
+
+            match zones.len() {
+                0 => {},
+                1 => return Ok(zones.remove(0).id),
+                _ => {
+                    return Err(Error::new(format!(
+                        "{} Cloudflare zones named {} are visible to the API token, set CLOUDFLARE_ZONE_ID to pick one",
+                        zones.len(),
+                        name
+                    )));
+                }
+            }
+
+            match name.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => name = parent,
+                _ => {
+                    return Err(Error::new(format!(
+                        "No Cloudflare zone for {} is visible to the API token, grant it Zone Read access or set CLOUDFLARE_ZONE_ID",
+                        domain
+                    )));
+                }
+            }
+        }
+    }
+
+    async fn get_zones(&self, name: &str) -> Result<Vec<Zone>, Error> {
+        let api_url = format!("{}/zones", self.api_url);
+
+        let request_builder = self.http
+            .get(api_url)
+            .query(&[("name", name)])
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.api_key));
+
+        let response = self.http.send(request_builder).await.map_err(|error| {
+            tracing::error!("Failed to fetch zones: {}", error);
+            Error::new("Failed to fetch zones".to_string())
+        })?;
+
+        let status: StatusCode = response.status();
+        let body: String = response.text().await.unwrap_or_else(|_| "Empty response body".to_string());
+
+        tracing::debug!("Received response {}, {}", status, body);
+
+        if !status.is_success() {
+            return Err(Error::new(format!("Failed to fetch zones named {}, Cloudflare responded with {}", name, status)));
         }
+
+        let response: CloudflareZonesResponse = serde_json::from_str(&body).map_err(|error| {
+            tracing::error!("Failed to parse response body: {}", error);
+            Error::new("Failed to parse response body".to_string())
+        })?;
+
+        Ok(response.result)
     }
 
+    /// Fetch every A and AAAA record in the zone, following Cloudflare's pagination until the
+    /// last page.
     pub async fn get_zone_records(&self) -> Result<Vec<Record>, Error> {
+        let mut address_records: Vec<Record> = vec![];
+        let mut page: i64 = 1;
+
+        loop {
+            let response = self.get_zone_records_page(page).await?;
+
+            address_records.extend(
+                response.result.into_iter().filter(|record| record.type_field.parse::<RecordType>().is_ok())
+            );
+
+            let result_info = response.result_info;
+            tracing::debug!("Fetched page {} of {} for zone {}", result_info.page, result_info.total_pages, self.zone_id);
+
+            if result_info.page >= result_info.total_pages || result_info.count == 0 {
+                break;
+            }
+
+            page = result_info.page + 1;
+        }
+
+        Ok(address_records)
+    }
+
+    async fn get_zone_records_page(&self, page: i64) -> Result<CloudflareZoneRecordsResponse, Error> {
         let api_url = format!("{}/zones/{}/dns_records", self.api_url, self.zone_id);
 
-        let request_builder = reqwest::Client::new()
+        let request_builder = self.http
             .get(api_url)
+            .query(&[("page", page), ("per_page", RECORDS_PER_PAGE)])
             .header("Content-Type", "application/json")
             .header("Authorization", format!("Bearer {}", self.api_key));
 
-        let domain_records = exponential_backoff::request(request_builder).await.map_err(|error| {
+        let domain_records = self.http.send(request_builder).await.map_err(|error| {
             tracing::error!("Failed to fetch domain records: {}", error);
             Error::new("Failed to fetch domain records".to_string())
         })?;
@@ -48,17 +161,26 @@ impl Client {
                 Error::new("Failed to parse response body".to_string())
             })?;
 
-            let all_records = response.result;
-
-            let a_records: Vec<Record> = all_records.into_iter().filter(|record| record.type_field == "A").collect();
-
-            Ok(a_records)
+            Ok(response)
         } else {
             Err(Error::new("Failed to fetch domain records".to_string()))
         }
     }
 
-    pub async fn update_zone_record(&self, id: &str, name: &str, content: &str) -> Result<(), Error> {
+    /// Whether records are proxied unless they set otherwise, `CLOUDFLARE_PROXY_ENABLED`.
+    pub fn proxy_enabled(&self) -> bool {
+        self.proxy_enabled
+    }
+
+    /// The request with the zone wide proxy setting applied when the record does not set its own.
+    fn with_default_proxied(&self, record: &RecordRequest) -> RecordRequest {
+        RecordRequest {
+            proxied: record.proxied.or(Some(self.proxy_enabled)),
+            ..record.clone()
+        }
+    }
+
+    pub async fn update_zone_record(&self, id: &str, record: &RecordRequest) -> Result<(), Error> {
         let url = format!(
             "{}/zones/{}/dns_records/{}",
             self.api_url,
@@ -66,20 +188,15 @@ impl Client {
             id
         );
 
-        let body = serde_json::json!({
-            "type": "A",
-            "name": name,
-            "content": content,
-            "proxied": self.proxy_enabled,
-        });
+        let body = self.with_default_proxied(record);
 
-        let request_builder = reqwest::Client::new()
+        let request_builder = self.http
             .patch(&url)
             .header("Content-Type", "application/json")
             .header("Authorization", format!("Bearer {}", self.api_key))
             .json(&body);
 
-        let response = exponential_backoff::request(request_builder).await.map_err(|error| {
+        let response = self.http.send(request_builder).await.map_err(|error| {
             tracing::error!("Failed to update record: {}", error);
             Error::new("Failed to update record".to_string())
         })?;
@@ -97,27 +214,22 @@ impl Client {
 
     }
 
-    pub async fn create_zone_record(&self, name: &str, content: &str) -> Result<(), Error> {
+    pub async fn create_zone_record(&self, record: &RecordRequest) -> Result<(), Error> {
         let url = format!(
             "{}/zones/{}/dns_records",
             self.api_url,
             self.zone_id,
         );
 
-        let body = serde_json::json!({
-            "type": "A",
-            "name": name,
-            "content": content,
-            "proxied": self.proxy_enabled,
-        });
+        let body = self.with_default_proxied(record);
 
-        let request_builder = reqwest::Client::new()
+        let request_builder = self.http
             .post(&url)
             .header("Content-Type", "application/json")
             .header("Authorization", format!("Bearer {}", self.api_key))
             .json(&body);
 
-        let response = exponential_backoff::request(request_builder).await.map_err(|error| {
+        let response = self.http.send(request_builder).await.map_err(|error| {
             tracing::error!("Failed to create record: {}", error);
             Error::new("Failed to create record".to_string())
         })?;
@@ -134,4 +246,302 @@ impl Client {
             Err(Error::new("Failed to create record".to_string()))
         }
     }
+
+    pub async fn delete_zone_record(&self, id: &str) -> Result<(), Error> {
+        let url = format!(
+            "{}/zones/{}/dns_records/{}",
+            self.api_url,
+            self.zone_id,
+            id
+        );
+
+        let request_builder = self.http
+            .delete(&url)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.api_key));
+
+        let response = self.http.send(request_builder).await.map_err(|error| {
+            tracing::error!("Failed to delete record: {}", error);
+            Error::new("Failed to delete record".to_string())
+        })?;
+
+        let status = response.status();
+        let message = response.text().await.unwrap_or_else(|_| "Empty response body".to_string());
+
+        tracing::debug!("Received response {}, {}", status, message);
+
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(Error::new("Failed to delete record".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn page_body(records: serde_json::Value, page: i64, total_pages: i64) -> serde_json::Value {
+        let count = records.as_array().map(|records| records.len()).unwrap_or_default();
+
+        json!({
+            "result": records,
+            "success": true,
+            "errors": [],
+            "messages": [],
+            "result_info": {
+                "page": page,
+                "per_page": RECORDS_PER_PAGE,
+                "count": count,
+                "total_count": 3,
+                "total_pages": total_pages,
+            }
+        })
+    }
+
+    fn request(type_field: &str, content: &str) -> RecordRequest {
+        RecordRequest {
+            name: "www".to_string(),
+            type_field: type_field.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn record(id: &str, name: &str, type_field: &str, content: &str) -> serde_json::Value {
+        json!({ "id": id, "name": name, "type": type_field, "content": content, "proxied": false, "ttl": 1 })
+    }
+
+    #[tokio::test]
+    async fn test_get_zone_records_follows_pagination() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones/zone/dns_records"))
+            .and(query_param("page", "1"))
+            .and(header("Authorization", "Bearer key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page_body(json!([
+                record("1", "www.example.com", "A", "1.1.1.1"),
+                record("2", "example.com", "MX", "mail.example.com"),
+                record("4", "www.example.com", "AAAA", "2001:db8::1"),
+            ]), 1, 2)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones/zone/dns_records"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page_body(json!([
+                record("3", "api.example.com", "A", "1.1.1.1"),
+            ]), 2, 2)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), "zone".to_string(), false);
+
+        let records = client.get_zone_records().await.unwrap();
+        let names: Vec<&str> = records.iter().map(|record| record.name.as_str()).collect();
+
+        assert_eq!(names, vec!["www.example.com", "www.example.com", "api.example.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_zone_records_single_page() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones/zone/dns_records"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page_body(json!([
+                record("1", "www.example.com", "A", "1.1.1.1"),
+            ]), 1, 1)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), "zone".to_string(), false);
+
+        let records = client.get_zone_records().await.unwrap();
+
+        assert_eq!(records.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_zone_records_error_status() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones/zone/dns_records"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), "zone".to_string(), false);
+
+        assert!(client.get_zone_records().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_zone_record_sends_record_type() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/zones/zone/dns_records"))
+            .and(body_partial_json(json!({ "type": "AAAA", "name": "www", "content": "2001:db8::1" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), "zone".to_string(), false);
+
+        assert!(client.create_zone_record(&request("AAAA", "2001:db8::1")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_update_zone_record_sends_settings() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/zones/zone/dns_records/1"))
+            .and(body_partial_json(json!({ "proxied": true, "ttl": 300, "comment": "home", "tags": ["ddns"] })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), "zone".to_string(), true);
+        let record = RecordRequest {
+            ttl: Some(300),
+            comment: Some("home".to_string()),
+            tags: Some(vec!["ddns".to_string()]),
+            ..request("A", "192.0.2.1")
+        };
+
+        assert!(client.update_zone_record("1", &record).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_zone_record() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/zones/zone/dns_records/1"))
+            .and(header("Authorization", "Bearer key"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/zones/zone/dns_records/2"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), "zone".to_string(), false);
+
+        assert!(client.delete_zone_record("1").await.is_ok());
+        assert!(client.delete_zone_record("2").await.is_err());
+    }
+
+    fn zones_body(ids: &[&str], name: &str) -> serde_json::Value {
+        let zones: Vec<serde_json::Value> = ids.iter().map(|id| json!({ "id": id, "name": name, "status": "active" })).collect();
+
+        json!({ "result": zones, "success": true, "errors": [], "messages": [] })
+    }
+
+    #[tokio::test]
+    async fn test_find_zone_id_exact_match() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones"))
+            .and(query_param("name", "example.com"))
+            .and(header("Authorization", "Bearer key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(zones_body(&["zone"], "example.com")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), String::new(), false);
+
+        assert_eq!(client.find_zone_id("Example.com.").await.unwrap(), "zone");
+    }
+
+    #[tokio::test]
+    async fn test_find_zone_id_of_subdomain() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones"))
+            .and(query_param("name", "home.example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(zones_body(&[], "home.example.com")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones"))
+            .and(query_param("name", "example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(zones_body(&["zone"], "example.com")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), String::new(), false);
+
+        assert_eq!(client.find_zone_id("home.example.com").await.unwrap(), "zone");
+    }
+
+    #[tokio::test]
+    async fn test_find_zone_id_errors() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones"))
+            .and(query_param("name", "example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(zones_body(&[], "example.com")))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones"))
+            .and(query_param("name", "example.org"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(zones_body(&["one", "two"], "example.org")))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "key".to_string(), String::new(), false);
+
+        assert_eq!(
+            client.find_zone_id("example.com").await.unwrap_err().message,
+            "No Cloudflare zone for example.com is visible to the API token, grant it Zone Read access or set CLOUDFLARE_ZONE_ID"
+        );
+        assert_eq!(
+            client.find_zone_id("example.org").await.unwrap_err().message,
+            "2 Cloudflare zones named example.org are visible to the API token, set CLOUDFLARE_ZONE_ID to pick one"
+        );
+    }
+
+    #[test]
+    fn test_record_proxied_overrides_zone_default() {
+        let client = Client::new("http://localhost".to_string(), "key".to_string(), "zone".to_string(), true);
+        let record = RecordRequest {
+            proxied: Some(false),
+            ..request("A", "192.0.2.1")
+        };
+
+        assert_eq!(client.with_default_proxied(&record).proxied, Some(false));
+        assert_eq!(client.with_default_proxied(&request("A", "192.0.2.1")).proxied, Some(true));
+
+        let body = serde_json::to_value(request("A", "192.0.2.1")).unwrap();
+        assert!(body.get("ttl").is_none() && body.get("comment").is_none());
+    }
 }
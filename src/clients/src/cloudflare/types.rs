@@ -12,6 +12,19 @@ pub struct CloudflareZoneRecordsResponse {
     pub result_info: ResultInfo,
 }
 
+/// Response of the zone listing, only the fields needed to find the zone id are kept.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudflareZonesResponse {
+    pub result: Vec<Zone>,
+    pub success: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Record {
@@ -22,6 +35,27 @@ pub struct Record {
     pub content: String,
     pub proxied: bool,
     pub ttl: Option<u64>,
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Body of a create or update request. Unset fields are left out, so an update keeps their
+/// current value and a create uses Cloudflare's default.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordRequest {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -0,0 +1,320 @@
+use config::sync_entry::RecordType;
+use reqwest::{RequestBuilder, StatusCode};
+use crate::digitalocean::error::Error;
+use crate::digitalocean::types::{DomainRecordResponse, DomainRecordsResponse, DomainResponse, ErrorResponse, Record, RecordRequest};
+use crate::http::HttpClient;
+
+pub mod error;
+pub mod types;
+
+/// Page size requested when listing domain records, the maximum DigitalOcean allows.
+const RECORDS_PER_PAGE: i64 = 200;
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub(crate) api_url: String,
+    pub(crate) api_token: String,
+    pub(crate) zone: String,
+    pub(crate) http: HttpClient,
+}
+
+impl Client {
+    /// # Arguments
+    /// * `api_url` - The API endpoint, `https://api.digitalocean.com/v2`.
+    /// * `api_token` - A personal access token with read and write access to domains.
+    /// * `zone` - The DigitalOcean domain the records belong to.
+    pub fn new(api_url: String, api_token: String, zone: String) -> Self {
+        Self {
+            api_url,
+            api_token,
+            zone,
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Use the given domain instead of the one the client was created with.
+    pub fn with_zone(mut self, zone: String) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// The DigitalOcean domain the records belong to, record names are relative to it.
+    pub fn zone(&self) -> &str {
+        &self.zone
+    }
+
+    /// Find the DigitalOcean domain serving the domain, trying the parent domains in turn when
+    /// the domain is below the zone apex.
+    ///
+    /// # Errors
+    /// When no domain of the account serves the domain.
+    pub async fn find_zone(&self, domain: &str) -> Result<String, Error> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let mut name = domain.as_str();
+
+        loop {
+            let url = format!("{}/domains/{}", self.api_url, name);
+            let (status, body) = self.send(self.http.get(url), "fetch domain").await?;
+
+            match status {
+                StatusCode::NOT_FOUND => {},
+                status if status.is_success() => {
+                    let response: DomainResponse = parse(&body)?;
+                    return Ok(response.domain.name);
+                },
+                status => return Err(error_of("fetch domain", status, &body)),
+            }
+
+            match name.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => name = parent,
+                _ => {
+                    return Err(Error::new(format!(
+                        "No DigitalOcean domain for {} is visible to the API token, add the domain or set DIGITALOCEAN_ZONE",
+                        domain
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Fetch every A and AAAA record of the domain, following the pagination until the last
+    /// page.
+    pub async fn get_domain_records(&self) -> Result<Vec<Record>, Error> {
+        let url = format!("{}/domains/{}/records", self.api_url, self.zone);
+        let mut address_records: Vec<Record> = vec![];
+        let mut page: i64 = 1;
+
+        loop {
+            let request_builder = self.http.get(&url).query(&[("page", page), ("per_page", RECORDS_PER_PAGE)]);
+            let (status, body) = self.send(request_builder, "fetch domain records").await?;
+
+            if !status.is_success() {
+                return Err(error_of("fetch domain records", status, &body));
+            }
+
+            let response: DomainRecordsResponse = parse(&body)?;
+
+            address_records.extend(
+                response.domain_records.into_iter().filter(|record| record.type_field.parse::<RecordType>().is_ok())
+            );
+
+            tracing::debug!("Fetched page {} of domain {}", page, self.zone);
+
+            if response.links.pages.and_then(|pages| pages.next).is_none() {
+                break;
+            }
+
+            page += 1;
+        }
+
+        Ok(address_records)
+    }
+
+    pub async fn create_domain_record(&self, record: &RecordRequest) -> Result<Record, Error> {
+        let url = format!("{}/domains/{}/records", self.api_url, self.zone);
+        let (status, body) = self.send(self.http.post(url).json(record), "create record").await?;
+
+        if !status.is_success() {
+            return Err(error_of("create record", status, &body));
+        }
+
+        let response: DomainRecordResponse = parse(&body)?;
+        Ok(response.domain_record)
+    }
+
+    pub async fn update_domain_record(&self, id: &str, record: &RecordRequest) -> Result<Record, Error> {
+        let url = format!("{}/domains/{}/records/{}", self.api_url, self.zone, id);
+        let (status, body) = self.send(self.http.patch(url).json(record), "update record").await?;
+
+        if !status.is_success() {
+            return Err(error_of("update record", status, &body));
+        }
+
+        let response: DomainRecordResponse = parse(&body)?;
+        Ok(response.domain_record)
+    }
+
+    pub async fn delete_domain_record(&self, id: &str) -> Result<(), Error> {
+        let url = format!("{}/domains/{}/records/{}", self.api_url, self.zone, id);
+        let (status, body) = self.send(self.http.delete(url), "delete record").await?;
+
+        if !status.is_success() {
+            return Err(error_of("delete record", status, &body));
+        }
+
+        Ok(())
+    }
+
+    /// Send the authenticated request.
+    ///
+    /// # Returns
+    /// The status and body of the response, whatever the status.
+    async fn send(&self, request_builder: RequestBuilder, action: &str) -> Result<(StatusCode, String), Error> {
+        let request_builder = request_builder
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.api_token));
+
+        let response = self.http.send(request_builder).await.map_err(|error| {
+            tracing::error!("Failed to {}: {}", action, error);
+            Error::new(format!("Failed to {}", action))
+        })?;
+
+        let status: StatusCode = response.status();
+        let body: String = response.text().await.unwrap_or_else(|_| "Empty response body".to_string());
+
+        tracing::debug!("Received response {}, {}", status, body);
+
+        Ok((status, body))
+    }
+}
+
+fn parse<'a, T: serde::Deserialize<'a>>(body: &'a str) -> Result<T, Error> {
+    serde_json::from_str(body).map_err(|error| {
+        tracing::error!("Failed to parse response body: {}", error);
+        Error::new("Failed to parse response body".to_string())
+    })
+}
+
+/// The error of a failed request, with the message DigitalOcean gave when there is one.
+fn error_of(action: &str, status: StatusCode, body: &str) -> Error {
+    let reason = serde_json::from_str::<ErrorResponse>(body)
+        .map(|response| format!("{}: {}", response.id, response.message))
+        .unwrap_or_else(|_| status.to_string());
+
+    Error::new(format!("Failed to {}, DigitalOcean responded with {}", action, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> Client {
+        Client::new(server.uri(), "token".to_string(), "example.com".to_string())
+    }
+
+    fn fixture(status: u16, body: &str) -> ResponseTemplate {
+        ResponseTemplate::new(status).set_body_raw(body, "application/json")
+    }
+
+    #[tokio::test]
+    async fn test_get_domain_records_follows_pagination() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/domains/example.com/records"))
+            .and(query_param("page", "1"))
+            .and(header("Authorization", "Bearer token"))
+            .respond_with(fixture(200, include_str!("../../fixtures/digitalocean/records_page_1.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/domains/example.com/records"))
+            .and(query_param("page", "2"))
+            .respond_with(fixture(200, include_str!("../../fixtures/digitalocean/records_page_2.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let records = client(&server).get_domain_records().await.unwrap();
+        let records: Vec<(u64, &str, &str)> = records.iter().map(|record| (record.id, record.name.as_str(), record.data.as_str())).collect();
+
+        assert_eq!(records, vec![(3352896, "www", "192.0.2.1"), (3352897, "@", "2001:db8::1")]);
+    }
+
+    #[tokio::test]
+    async fn test_get_domain_records_error_message() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/domains/example.com/records"))
+            .respond_with(fixture(401, include_str!("../../fixtures/digitalocean/unauthorized.json")))
+            .mount(&server)
+            .await;
+
+        assert_eq!(
+            client(&server).get_domain_records().await.unwrap_err().message,
+            "Failed to fetch domain records, DigitalOcean responded with Unauthorized: Unable to authenticate you"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_update_and_delete_record() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/domains/example.com/records"))
+            .and(body_json(json!({ "type": "A", "name": "home", "data": "192.0.2.9", "ttl": 300 })))
+            .respond_with(fixture(201, include_str!("../../fixtures/digitalocean/record.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/domains/example.com/records/3352899"))
+            .and(body_json(json!({ "type": "A", "name": "home", "data": "192.0.2.9" })))
+            .respond_with(fixture(200, include_str!("../../fixtures/digitalocean/record.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/domains/example.com/records/3352899"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        let request = RecordRequest {
+            type_field: "A".to_string(),
+            name: "home".to_string(),
+            data: "192.0.2.9".to_string(),
+            ttl: Some(300),
+        };
+
+        let created = client.create_domain_record(&request).await.unwrap();
+        assert_eq!((created.id, created.ttl), (3352899, Some(300)));
+
+        let request = RecordRequest { ttl: None, ..request };
+        assert!(client.update_domain_record("3352899", &request).await.is_ok());
+        assert!(client.delete_domain_record("3352899").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_find_zone_of_subdomain() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/domains/home.example.com"))
+            .respond_with(fixture(404, include_str!("../../fixtures/digitalocean/not_found.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/domains/example.com"))
+            .respond_with(fixture(200, include_str!("../../fixtures/digitalocean/domain.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+
+        assert_eq!(client.find_zone("Home.Example.com.").await.unwrap(), "example.com");
+        assert_eq!(
+            client.find_zone("example.org").await.unwrap_err().message,
+            "No DigitalOcean domain for example.org is visible to the API token, add the domain or set DIGITALOCEAN_ZONE"
+        );
+    }
+}
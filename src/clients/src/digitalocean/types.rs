@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+/// Response of a domain lookup, only the name is kept.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainResponse {
+    pub domain: Domain,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Domain {
+    pub name: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainRecordsResponse {
+    pub domain_records: Vec<Record>,
+    #[serde(default)]
+    pub links: Links,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainRecordResponse {
+    pub domain_record: Record,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Links {
+    pub pages: Option<Pages>,
+}
+
+/// Links to the neighbouring pages, `next` is left out on the last page.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pages {
+    pub next: Option<String>,
+    pub last: Option<String>,
+}
+
+/// A domain record. The name is relative to the domain, `@` is the domain itself.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub name: String,
+    pub data: String,
+    pub ttl: Option<u64>,
+}
+
+/// Body of a create or update request. A create without TTL uses the TTL of the domain, an
+/// update without TTL keeps the current one.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordRequest {
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub name: String,
+    pub data: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+}
+
+/// Body of every error response.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub id: String,
+    pub message: String,
+}
@@ -0,0 +1,173 @@
+use std::fmt::Debug;
+use reqwest::StatusCode;
+use crate::dyndns2::error::Error;
+use crate::dyndns2::types::UpdateResponse;
+use crate::exponential_backoff::RetryPolicy;
+use crate::http::HttpClient;
+
+pub mod error;
+pub mod types;
+
+/// Client of the dyndns2 update protocol spoken by No-IP, Dynu, Google Domains style and many
+/// self-hosted update servers.
+#[derive(Clone)]
+pub struct Client {
+    pub(crate) url: String,
+    pub(crate) username: String,
+    pub(crate) password: String,
+    pub(crate) http: HttpClient,
+}
+
+impl Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Client {
+    /// # Arguments
+    /// * `url` - The full update URL, e.g. `https://dynupdate.no-ip.com/nic/update`.
+    /// * `username` - The basic auth user name.
+    /// * `password` - The basic auth password, or the update token of the host.
+    pub fn new(url: String, username: String, password: String) -> Self {
+        Self {
+            url,
+            username,
+            password,
+            http: without_status_retries(HttpClient::default()),
+        }
+    }
+
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = without_status_retries(http);
+        self
+    }
+
+    /// Point the hostname at the address.
+    ///
+    /// # Returns
+    /// The answer of the server, which may refuse the update. A `401` response is a `badauth`
+    /// answer.
+    ///
+    /// # Errors
+    /// When the request fails, or the server answers with an error status and no known code.
+    pub async fn update(&self, hostname: &str, ip: &str) -> Result<UpdateResponse, Error> {
+        let request_builder = self.http.get(&self.url)
+            .query(&[("hostname", hostname), ("myip", ip)])
+            .basic_auth(&self.username, Some(&self.password));
+
+        let response = self.http.send(request_builder).await.map_err(|error| {
+            tracing::error!("Failed to send dyndns2 update of {}: {}", hostname, error);
+            Error::new(format!("Failed to send dyndns2 update of {}", hostname))
+        })?;
+
+        let status: StatusCode = response.status();
+        let body: String = response.text().await.unwrap_or_default();
+
+        tracing::debug!("Received response {}, {}", status, body);
+
+        if status == StatusCode::UNAUTHORIZED {
+            return Ok(UpdateResponse::BadAuth);
+        }
+
+        match UpdateResponse::parse(&body) {
+            UpdateResponse::Unknown(_) if !status.is_success() => {
+                Err(Error::new(format!("dyndns2 update of {} failed, {}", hostname, status)))
+            },
+            response => Ok(response),
+        }
+    }
+}
+
+/// The answer codes tell when an update may be sent again, and servers treat quick repeats as
+/// abuse, so only transport errors are retried.
+fn without_status_retries(http: HttpClient) -> HttpClient {
+    let retry_policy = RetryPolicy {
+        retryable_statuses: vec![],
+        ..http.retry_policy().clone()
+    };
+
+    http.with_retry_policy(retry_policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> Client {
+        Client::new(format!("{}/nic/update", server.uri()), "user".to_string(), "pass".to_string())
+    }
+
+    async fn respond(status: u16, body: &str) -> Result<UpdateResponse, Error> {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/nic/update"))
+            .respond_with(ResponseTemplate::new(status).set_body_string(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client(&server).update("home.example.com", "192.0.2.1").await
+    }
+
+    #[tokio::test]
+    async fn test_update_sends_hostname_address_and_credentials() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/nic/update"))
+            .and(query_param("hostname", "home.example.com"))
+            .and(query_param("myip", "192.0.2.1"))
+            // "user:pass" in base64
+            .and(header("Authorization", "Basic dXNlcjpwYXNz"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("good 192.0.2.1"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = client(&server).update("home.example.com", "192.0.2.1").await.unwrap();
+
+        assert_eq!(response, UpdateResponse::Good);
+    }
+
+    #[tokio::test]
+    async fn test_update_parses_answer_codes() {
+        let cases = [
+            ("nochg 192.0.2.1", UpdateResponse::NoChange),
+            ("badauth", UpdateResponse::BadAuth),
+            ("!donator", UpdateResponse::NotDonator),
+            ("notfqdn", UpdateResponse::NotFqdn),
+            ("nohost", UpdateResponse::NoHost),
+            ("numhost", UpdateResponse::NumHost),
+            ("abuse", UpdateResponse::Abuse),
+            ("badagent", UpdateResponse::BadAgent),
+            ("dnserr", UpdateResponse::DnsError),
+            ("911", UpdateResponse::ServerError),
+            ("teapot", UpdateResponse::Unknown("teapot".to_string())),
+        ];
+
+        for (body, expected) in cases {
+            assert_eq!(respond(200, body).await.unwrap(), expected, "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_unauthorized_is_badauth() {
+        assert_eq!(respond(401, "").await.unwrap(), UpdateResponse::BadAuth);
+    }
+
+    #[tokio::test]
+    async fn test_update_server_error_is_not_retried() {
+        // `expect(1)` fails the test when the 911 is retried.
+        assert_eq!(respond(503, "911").await.unwrap(), UpdateResponse::ServerError);
+        assert!(respond(500, "Internal Server Error").await.is_err());
+    }
+}
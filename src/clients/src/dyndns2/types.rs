@@ -0,0 +1,76 @@
+use std::fmt::Display;
+
+/// The answer of the server to an update, the first word of the response body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateResponse {
+    /// The update was applied, followed by the address.
+    Good,
+    /// The hostname already had the address.
+    NoChange,
+    /// The username or password is wrong.
+    BadAuth,
+    /// The account may not use the requested feature.
+    NotDonator,
+    /// The hostname is not a fully qualified domain name.
+    NotFqdn,
+    /// The hostname does not exist in the account.
+    NoHost,
+    /// Too many hostnames in one request.
+    NumHost,
+    /// The hostname is blocked for update abuse.
+    Abuse,
+    /// The user agent is blocked or the request is malformed.
+    BadAgent,
+    /// A DNS error on the server side.
+    DnsError,
+    /// The service is down for maintenance, `911`.
+    ServerError,
+    /// Any other answer, with the response body.
+    Unknown(String),
+}
+
+impl UpdateResponse {
+    /// Parse the answer from the response body.
+    pub fn parse(body: &str) -> Self {
+        let code = body.split_whitespace().next().unwrap_or_default();
+
+        match code {
+            "good" => UpdateResponse::Good,
+            "nochg" => UpdateResponse::NoChange,
+            "badauth" => UpdateResponse::BadAuth,
+            "!donator" => UpdateResponse::NotDonator,
+            "notfqdn" => UpdateResponse::NotFqdn,
+            "nohost" => UpdateResponse::NoHost,
+            "numhost" => UpdateResponse::NumHost,
+            "abuse" => UpdateResponse::Abuse,
+            "badagent" => UpdateResponse::BadAgent,
+            "dnserr" => UpdateResponse::DnsError,
+            "911" => UpdateResponse::ServerError,
+            _ => UpdateResponse::Unknown(body.trim().to_string()),
+        }
+    }
+
+    /// Whether the hostname now has the address.
+    pub fn is_success(&self) -> bool {
+        matches!(self, UpdateResponse::Good | UpdateResponse::NoChange)
+    }
+}
+
+impl Display for UpdateResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UpdateResponse::Good => write!(f, "good"),
+            UpdateResponse::NoChange => write!(f, "nochg"),
+            UpdateResponse::BadAuth => write!(f, "badauth"),
+            UpdateResponse::NotDonator => write!(f, "!donator"),
+            UpdateResponse::NotFqdn => write!(f, "notfqdn"),
+            UpdateResponse::NoHost => write!(f, "nohost"),
+            UpdateResponse::NumHost => write!(f, "numhost"),
+            UpdateResponse::Abuse => write!(f, "abuse"),
+            UpdateResponse::BadAgent => write!(f, "badagent"),
+            UpdateResponse::DnsError => write!(f, "dnserr"),
+            UpdateResponse::ServerError => write!(f, "911"),
+            UpdateResponse::Unknown(body) => write!(f, "{:?}", body),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt::Display;
+
+/// Maximum number of characters of a response body kept on an error, enough to recognise an
+/// HTML error page without flooding the logs.
+const BODY_SNIPPET_LENGTH: usize = 256;
+
+/// The broad category of a client failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The request never got a response, e.g. DNS, connection or timeout failures.
+    Network,
+    /// The server responded with a non success status code.
+    Http,
+    /// The response could not be parsed into the expected value.
+    Parse,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Network => write!(f, "network"),
+            ErrorKind::Http => write!(f, "http"),
+            ErrorKind::Parse => write!(f, "parse"),
+        }
+    }
+}
+
+/// Error returned by the clients, carrying the status code and a snippet of the response body
+/// when there was a response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub status: Option<u16>,
+    pub body: Option<String>,
+}
+
+impl Error {
+    pub fn network(message: String) -> Self {
+        Self {
+            kind: ErrorKind::Network,
+            message,
+            status: None,
+            body: None,
+        }
+    }
+
+    pub fn http(message: String, status: u16, body: &str) -> Self {
+        Self {
+            kind: ErrorKind::Http,
+            message,
+            status: Some(status),
+            body: snippet(body),
+        }
+    }
+
+    pub fn parse(message: String, body: &str) -> Self {
+        Self {
+            kind: ErrorKind::Parse,
+            message,
+            status: None,
+            body: snippet(body),
+        }
+    }
+}
+
+fn snippet(body: &str) -> Option<String> {
+    let body = body.trim();
+
+    if body.is_empty() {
+        return None;
+    }
+
+    Some(body.chars().take(BODY_SNIPPET_LENGTH).collect())
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} error: {}", self.kind, self.message)?;
+
+        if let Some(status) = self.status {
+            write!(f, " (status {})", status)?;
+        }
+
+        if let Some(body) = &self.body {
+            write!(f, ": {}", body)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_includes_status_and_snippet() {
+        let error = Error::http("Request failed".to_string(), 503, &"x".repeat(1000));
+
+        assert_eq!(error.body.as_ref().map(|body| body.len()), Some(BODY_SNIPPET_LENGTH));
+        assert!(error.to_string().starts_with("http error: Request failed (status 503): xxx"));
+    }
+}
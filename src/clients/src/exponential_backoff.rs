@@ -1,21 +1,440 @@
-use reqwest::RequestBuilder;
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Request, RequestBuilder, Response, StatusCode};
+use tokio::time::Instant;
+use crate::error::Error;
 
-pub(crate) async fn request(built_request: RequestBuilder) -> Result<reqwest::Response, reqwest::Error> {
-    let mut backoff = 1;
+/// Controls how a request is retried: how often, how long to wait between attempts and which
+/// responses are worth retrying at all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts including the first one, `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on every following retry.
+    pub initial_delay: Duration,
+    /// Upper bound of a single delay. A `Retry-After` asking for longer stops the retries.
+    pub max_delay: Duration,
+    /// Upper bound of the time spent retrying, a retry that would end past it is not attempted.
+    pub deadline: Option<Duration>,
+    /// Randomize each delay between zero and the exponential delay ("full jitter").
+    pub jitter: bool,
+    /// Response status codes that are retried, transport errors are always retried.
+    pub retryable_statuses: Vec<StatusCode>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            deadline: Some(Duration::from_secs(60)),
+            jitter: true,
+            retryable_statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+}
+
+impl From<&config::RetrySettings> for RetryPolicy {
+    fn from(settings: &config::RetrySettings) -> Self {
+        Self {
+            max_attempts: settings.max_attempts.max(1),
+            max_delay: Duration::from_secs(settings.max_delay_seconds),
+            deadline: settings.deadline_seconds.map(Duration::from_secs),
+            ..Default::default()
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy sending the request exactly once.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The delay before the retry following the given attempt, starting at one.
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self.initial_delay.saturating_mul(1 << exponent).min(self.max_delay);
+
+        if self.jitter {
+            let millis = delay.as_millis() as u64;
+            Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+        } else {
+            delay
+        }
+    }
+}
+
+/// What to do with the result of an attempt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Outcome {
+    Done,
+    Retry { retry_after: Option<Duration> },
+}
+
+/// Run the operation until the classifier is satisfied or the policy runs out, returning the
+/// last result either way.
+pub(crate) async fn retry<T, E, F, Fut, C>(policy: &RetryPolicy, mut operation: F, classify: C) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    C: Fn(&Result<T, E>) -> Outcome,
+{
+    let started = Instant::now();
+    let mut attempt: u32 = 1;
 
     loop {
-        let request_clone = built_request.try_clone().expect("Failed to clone request");
-        let response = request_clone.send().await;
+        let result = operation().await;
 
-        match response {
-            Ok(response) => {
-                return Ok(response);
-            },
-            Err(error) => {
-                tracing::error!("Failed to send request {:?}", error);
-                tokio::time::sleep(std::time::Duration::from_secs(backoff)).await;
-                backoff *= 2;
+        let retry_after = match classify(&result) {
+            Outcome::Done => return result,
+            Outcome::Retry { retry_after } => retry_after,
+        };
+
+        if attempt >= policy.max_attempts {
+            tracing::warn!("Giving up after {} attempts", attempt);
+            return result;
+        }
+
+        let delay = retry_after.unwrap_or_else(|| policy.backoff_delay(attempt));
+
+        if delay > policy.max_delay {
+            tracing::warn!("Server asked to retry in {:?}, more than the maximum delay, giving up", delay);
+            return result;
+        }
+
+        if let Some(deadline) = policy.deadline {
+            if started.elapsed() + delay > deadline {
+                tracing::warn!("Retrying in {:?} would exceed the {:?} deadline, giving up", delay, deadline);
+                return result;
+            }
+        }
+
+        tracing::debug!("Attempt {} failed, retrying in {:?}", attempt, delay);
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Parse a `Retry-After` header value, either delay seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value.trim()).ok()?;
+
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Why an attempt got no response.
+#[derive(Debug)]
+enum Failure {
+    Send(reqwest::Error),
+    /// The body is a stream which was consumed by the previous attempt.
+    NotReplayable,
+}
+
+/// Whether the result is worth another attempt. A request that is not idempotent, such as a POST
+/// creating a record, is only retried when it never reached the server or was refused with 429,
+/// so a retry can never apply it twice.
+fn classify_response(policy: &RetryPolicy, idempotent: bool, result: &Result<Response, Failure>) -> Outcome {
+    match result {
+        Ok(response) if policy.retryable_statuses.contains(&response.status()) => {
+            tracing::warn!("Request to {} failed with status {}", response.url(), response.status());
+
+            if !idempotent && response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Outcome::Done;
+            }
+
+            let retry_after = response.headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
+
+            Outcome::Retry { retry_after }
+        },
+        Ok(_) => Outcome::Done,
+        Err(Failure::Send(error)) => {
+            tracing::error!("Failed to send request {:?}", error);
+
+            match idempotent || error.is_connect() {
+                true => Outcome::Retry { retry_after: None },
+                false => Outcome::Done,
+            }
+        },
+        Err(Failure::NotReplayable) => Outcome::Done,
+    }
+}
+
+/// Send the request according to the retry policy. Responses with a non retryable status are
+/// returned as is, as is the last response once the policy gives up. A request with a streaming
+/// body can only be sent once and is never retried.
+pub(crate) async fn request(built_request: RequestBuilder, policy: &RetryPolicy) -> Result<Response, Error> {
+    let (client, request) = built_request.build_split();
+    let request = request.map_err(|error| Error::network(format!("Invalid request: {}", error)))?;
+
+    let description = format!("{} {}", request.method(), request.url());
+    let idempotent = request.method().is_idempotent();
+    let replay: Option<Request> = request.try_clone();
+    let mut first: Option<Request> = Some(request);
+
+    let result = retry(
+        policy,
+        || {
+            let attempt = first.take().or_else(|| replay.as_ref().and_then(Request::try_clone));
+            let client = &client;
+
+            async move {
+                match attempt {
+                    Some(request) => client.execute(request).await.map_err(Failure::Send),
+                    None => Err(Failure::NotReplayable),
+                }
             }
+        },
+        |result| match replay {
+            Some(_) => classify_response(policy, idempotent, result),
+            None => Outcome::Done,
+        },
+    ).await;
+
+    result.map_err(|failure| match failure {
+        Failure::Send(error) if error.is_timeout() => Error::network(format!("{} timed out: {}", description, error)),
+        Failure::Send(error) => Error::network(format!("{} failed: {}", description, error)),
+        Failure::NotReplayable => Error::network(format!("{} cannot be sent again, its body is a stream", description)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+            deadline: None,
+            jitter: false,
+            ..Default::default()
         }
     }
-}
\ No newline at end of file
+
+    /// Operation failing until the given attempt, counting how often it ran.
+    async fn run(policy: &RetryPolicy, succeed_on: u32, retry_after: Option<Duration>) -> (Result<u32, u32>, u32) {
+        let attempts = AtomicU32::new(0);
+
+        let result = retry(
+            policy,
+            || async {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                if attempt >= succeed_on { Ok(attempt) } else { Err(attempt) }
+            },
+            |result| match result {
+                Ok(_) => Outcome::Done,
+                Err(_) => Outcome::Retry { retry_after },
+            },
+        ).await;
+
+        (result, attempts.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_backs_off_exponentially() {
+        let started = Instant::now();
+
+        let (result, attempts) = run(&policy(), 3, None).await;
+
+        assert_eq!(result, Ok(3));
+        assert_eq!(attempts, 3);
+        assert_eq!(started.elapsed(), Duration::from_secs(1 + 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_stops_at_max_attempts() {
+        let started = Instant::now();
+
+        let (result, attempts) = run(&policy(), 100, None).await;
+
+        assert_eq!(result, Err(4));
+        assert_eq!(attempts, 4);
+        assert_eq!(started.elapsed(), Duration::from_secs(1 + 2 + 4));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_caps_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 6,
+            max_delay: Duration::from_secs(3),
+            ..policy()
+        };
+        let started = Instant::now();
+
+        let (result, _) = run(&policy, 100, None).await;
+
+        assert_eq!(result, Err(6));
+        assert_eq!(started.elapsed(), Duration::from_secs(1 + 2 + 3 + 3 + 3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_respects_deadline() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            deadline: Some(Duration::from_secs(5)),
+            ..policy()
+        };
+        let started = Instant::now();
+
+        let (result, attempts) = run(&policy, 100, None).await;
+
+        assert_eq!(result, Err(3));
+        assert_eq!(attempts, 3);
+        assert_eq!(started.elapsed(), Duration::from_secs(1 + 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_honours_retry_after() {
+        let started = Instant::now();
+
+        let (result, _) = run(&policy(), 2, Some(Duration::from_secs(7))).await;
+
+        assert_eq!(result, Ok(2));
+        assert_eq!(started.elapsed(), Duration::from_secs(7));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_gives_up_on_long_retry_after() {
+        let started = Instant::now();
+
+        let (result, attempts) = run(&policy(), 2, Some(Duration::from_secs(3600))).await;
+
+        assert_eq!(result, Err(1));
+        assert_eq!(attempts, 1);
+        assert_eq!(started.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_jitter_stays_below_delay() {
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy()
+        };
+
+        for attempt in 1..10 {
+            assert!(policy.backoff_delay(attempt) <= Duration::from_secs(10));
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_request_retries_retryable_status() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "0"))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let response = request(reqwest::Client::new().get(server.uri()), &policy()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_request_does_not_retry_client_errors() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let response = request(reqwest::Client::new().get(server.uri()), &policy()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_does_not_retry_post_on_server_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "0"))
+            .mount(&server)
+            .await;
+
+        let response = request(reqwest::Client::new().post(server.uri()).body("{}"), &policy()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_retries_post_on_too_many_requests() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&server)
+            .await;
+
+        let response = request(reqwest::Client::new().post(server.uri()).body("{}"), &policy()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_sends_streaming_body_once() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "0"))
+            .mount(&server)
+            .await;
+
+        let stream = futures_util::stream::iter(vec![Ok::<_, std::io::Error>("{}")]);
+        let built_request = reqwest::Client::new().put(server.uri()).body(reqwest::Body::wrap_stream(stream));
+
+        let response = request(built_request, &policy()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,369 @@
+use config::sync_entry::RecordType;
+use reqwest::{RequestBuilder, StatusCode};
+use crate::hetzner::error::Error;
+use crate::hetzner::types::{ErrorResponse, Record, RecordRequest, RecordResponse, RecordsResponse, Zone, ZoneResponse, ZonesResponse};
+use crate::http::HttpClient;
+
+pub mod error;
+pub mod types;
+
+/// Page size requested when listing records.
+const RECORDS_PER_PAGE: i64 = 100;
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub(crate) api_url: String,
+    pub(crate) api_token: String,
+    pub(crate) zone: Zone,
+    pub(crate) http: HttpClient,
+}
+
+impl Client {
+    /// # Arguments
+    /// * `api_url` - The API endpoint, `https://dns.hetzner.com/api/v1`.
+    /// * `api_token` - A DNS API token.
+    /// * `zone` - The zone the records belong to.
+    pub fn new(api_url: String, api_token: String, zone: Zone) -> Self {
+        Self {
+            api_url,
+            api_token,
+            zone,
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Use the given zone instead of the one the client was created with.
+    pub fn with_zone(mut self, zone: Zone) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// The zone the records belong to, record names are relative to it.
+    pub fn zone(&self) -> &Zone {
+        &self.zone
+    }
+
+    /// Fetch the zone with the id, to learn its name.
+    pub async fn get_zone(&self, id: &str) -> Result<Zone, Error> {
+        let url = format!("{}/zones/{}", self.api_url, id);
+        let (status, body) = self.send(self.http.get(url), "fetch zone").await?;
+
+        if !status.is_success() {
+            return Err(error_of("fetch zone", status, &body));
+        }
+
+        let response: ZoneResponse = parse(&body)?;
+        Ok(response.zone)
+    }
+
+    /// Find the zone serving the domain, trying the parent domains in turn when the domain is
+    /// below the zone apex.
+    ///
+    /// # Errors
+    /// When no zone visible to the API token serves the domain.
+    pub async fn find_zone(&self, domain: &str) -> Result<Zone, Error> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let mut name = domain.as_str();
+
+        loop {
+            let url = format!("{}/zones", self.api_url);
+            let (status, body) = self.send(self.http.get(url).query(&[("name", name)]), "fetch zones").await?;
+
+            match status {
+                StatusCode::NOT_FOUND => {},
+                status if status.is_success() => {
+                    let response: ZonesResponse = parse(&body)?;
+
+                    if let Some(zone) = response.zones.into_iter().find(|zone| zone.name.eq_ignore_ascii_case(name)) {
+                        return Ok(zone);
+                    }
+                },
+                status => return Err(error_of("fetch zones", status, &body)),
+            }
+
+            match name.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => name = parent,
+                _ => {
+                    return Err(Error::new(format!(
+                        "No Hetzner zone for {} is visible to the API token, set HETZNER_ZONE_ID to pick one",
+                        domain
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Fetch every A and AAAA record of the zone, following the pagination until the last
+    /// page.
+    pub async fn get_zone_records(&self) -> Result<Vec<Record>, Error> {
+        let url = format!("{}/records", self.api_url);
+        let mut address_records: Vec<Record> = vec![];
+        let mut page: i64 = 1;
+
+        loop {
+            let query = [("zone_id", self.zone.id.clone()), ("page", page.to_string()), ("per_page", RECORDS_PER_PAGE.to_string())];
+            let (status, body) = self.send(self.http.get(&url).query(&query), "fetch records").await?;
+
+            if !status.is_success() {
+                return Err(error_of("fetch records", status, &body));
+            }
+
+            let response: RecordsResponse = parse(&body)?;
+
+            address_records.extend(
+                response.records.into_iter().filter(|record| record.type_field.parse::<RecordType>().is_ok())
+            );
+
+            let last_page = response.meta.pagination.map(|pagination| pagination.last_page).unwrap_or(page);
+            tracing::debug!("Fetched page {} of {} for zone {}", page, last_page, self.zone.name);
+
+            if page >= last_page {
+                break;
+            }
+
+            page += 1;
+        }
+
+        Ok(address_records)
+    }
+
+    pub async fn create_zone_record(&self, record: &RecordRequest) -> Result<Record, Error> {
+        let url = format!("{}/records", self.api_url);
+        let (status, body) = self.send(self.http.post(url).json(record), "create record").await?;
+
+        if !status.is_success() {
+            return Err(error_of("create record", status, &body));
+        }
+
+        let response: RecordResponse = parse(&body)?;
+        Ok(response.record)
+    }
+
+    pub async fn update_zone_record(&self, id: &str, record: &RecordRequest) -> Result<Record, Error> {
+        let url = format!("{}/records/{}", self.api_url, id);
+        let (status, body) = self.send(self.http.put(url).json(record), "update record").await?;
+
+        if !status.is_success() {
+            return Err(error_of("update record", status, &body));
+        }
+
+        let response: RecordResponse = parse(&body)?;
+        Ok(response.record)
+    }
+
+    pub async fn delete_zone_record(&self, id: &str) -> Result<(), Error> {
+        let url = format!("{}/records/{}", self.api_url, id);
+        let (status, body) = self.send(self.http.delete(url), "delete record").await?;
+
+        if !status.is_success() {
+            return Err(error_of("delete record", status, &body));
+        }
+
+        Ok(())
+    }
+
+    /// Send the authenticated request.
+    ///
+    /// # Returns
+    /// The status and body of the response, whatever the status.
+    async fn send(&self, request_builder: RequestBuilder, action: &str) -> Result<(StatusCode, String), Error> {
+        let request_builder = request_builder
+            .header("Content-Type", "application/json")
+            .header("Auth-API-Token", &self.api_token);
+
+        let response = self.http.send(request_builder).await.map_err(|error| {
+            tracing::error!("Failed to {}: {}", action, error);
+            Error::new(format!("Failed to {}", action))
+        })?;
+
+        let status: StatusCode = response.status();
+        let body: String = response.text().await.unwrap_or_else(|_| "Empty response body".to_string());
+
+        tracing::debug!("Received response {}, {}", status, body);
+
+        Ok((status, body))
+    }
+}
+
+fn parse<'a, T: serde::Deserialize<'a>>(body: &'a str) -> Result<T, Error> {
+    serde_json::from_str(body).map_err(|error| {
+        tracing::error!("Failed to parse response body: {}", error);
+        Error::new("Failed to parse response body".to_string())
+    })
+}
+
+/// The error of a failed request, with the message Hetzner gave when there is one.
+fn error_of(action: &str, status: StatusCode, body: &str) -> Error {
+    let reason = serde_json::from_str::<ErrorResponse>(body)
+        .ok()
+        .and_then(|response| response.error.map(|error| error.message).or(response.message))
+        .unwrap_or_else(|| status.to_string());
+
+    Error::new(format!("Failed to {}, Hetzner responded with {}", action, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn zone() -> Zone {
+        Zone {
+            id: "HBhuAj8UXBkCPbfwk7E7Zn".to_string(),
+            name: "example.com".to_string(),
+        }
+    }
+
+    fn client(server: &MockServer) -> Client {
+        Client::new(server.uri(), "token".to_string(), zone())
+    }
+
+    fn fixture(status: u16, body: &str) -> ResponseTemplate {
+        ResponseTemplate::new(status).set_body_raw(body, "application/json")
+    }
+
+    #[tokio::test]
+    async fn test_get_zone_records_follows_pagination() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("zone_id", "HBhuAj8UXBkCPbfwk7E7Zn"))
+            .and(query_param("page", "1"))
+            .and(header("Auth-API-Token", "token"))
+            .respond_with(fixture(200, include_str!("../../fixtures/hetzner/records_page_1.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("page", "2"))
+            .respond_with(fixture(200, include_str!("../../fixtures/hetzner/records_page_2.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let records = client(&server).get_zone_records().await.unwrap();
+        let records: Vec<(&str, &str, Option<u64>)> = records.iter().map(|record| (record.name.as_str(), record.value.as_str(), record.ttl)).collect();
+
+        assert_eq!(records, vec![("www", "192.0.2.1", Some(3600)), ("@", "2001:db8::1", None)]);
+    }
+
+    #[tokio::test]
+    async fn test_error_messages() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .respond_with(fixture(401, include_str!("../../fixtures/hetzner/unauthorized.json")))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/records"))
+            .respond_with(fixture(422, include_str!("../../fixtures/hetzner/invalid_record.json")))
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+
+        assert_eq!(
+            client.get_zone_records().await.unwrap_err().message,
+            "Failed to fetch records, Hetzner responded with Invalid authentication credentials"
+        );
+        assert_eq!(
+            client.create_zone_record(&RecordRequest::default()).await.unwrap_err().message,
+            "Failed to create record, Hetzner responded with 422 Unprocessable Entity: invalid A record"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_update_and_delete_record() {
+        let server = MockServer::start().await;
+        let body = json!({ "zone_id": "HBhuAj8UXBkCPbfwk7E7Zn", "type": "A", "name": "home", "value": "192.0.2.9", "ttl": 300 });
+
+        Mock::given(method("POST"))
+            .and(path("/records"))
+            .and(body_json(&body))
+            .respond_with(fixture(200, include_str!("../../fixtures/hetzner/record.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("PUT"))
+            .and(path("/records/5d7f9b1c3e5a7c9e1b3d5f7a9c1e3b5d"))
+            .and(body_json(&body))
+            .respond_with(fixture(200, include_str!("../../fixtures/hetzner/record.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/records/5d7f9b1c3e5a7c9e1b3d5f7a9c1e3b5d"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        let request = RecordRequest {
+            zone_id: "HBhuAj8UXBkCPbfwk7E7Zn".to_string(),
+            type_field: "A".to_string(),
+            name: "home".to_string(),
+            value: "192.0.2.9".to_string(),
+            ttl: Some(300),
+        };
+
+        let created = client.create_zone_record(&request).await.unwrap();
+        assert_eq!(created.id, "5d7f9b1c3e5a7c9e1b3d5f7a9c1e3b5d");
+
+        assert!(client.update_zone_record(&created.id, &request).await.is_ok());
+        assert!(client.delete_zone_record(&created.id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_find_zone_of_subdomain() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones"))
+            .and(query_param("name", "home.example.com"))
+            .respond_with(fixture(404, include_str!("../../fixtures/hetzner/zone_not_found.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones"))
+            .and(query_param("name", "example.com"))
+            .respond_with(fixture(200, include_str!("../../fixtures/hetzner/zones.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/zones/HBhuAj8UXBkCPbfwk7E7Zn"))
+            .respond_with(fixture(200, include_str!("../../fixtures/hetzner/zone.json")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri(), "token".to_string(), Zone::default());
+
+        assert_eq!(client.find_zone("home.example.com").await.unwrap(), zone());
+        assert_eq!(client.get_zone("HBhuAj8UXBkCPbfwk7E7Zn").await.unwrap(), zone());
+        assert_eq!(
+            client.find_zone("example.org").await.unwrap_err().message,
+            "No Hetzner zone for example.org is visible to the API token, set HETZNER_ZONE_ID to pick one"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZonesResponse {
+    pub zones: Vec<Zone>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneResponse {
+    pub zone: Zone,
+}
+
+/// A DNS zone, only the fields needed to address its records are kept.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordsResponse {
+    pub records: Vec<Record>,
+    #[serde(default)]
+    pub meta: Meta,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordResponse {
+    pub record: Record,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+    pub pagination: Option<Pagination>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pagination {
+    pub page: i64,
+    pub per_page: i64,
+    pub last_page: i64,
+    pub total_entries: i64,
+}
+
+/// A record. The name is relative to the zone, `@` is the zone itself, and records without
+/// TTL use the TTL of the zone.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub name: String,
+    pub value: String,
+    pub ttl: Option<u64>,
+    pub zone_id: String,
+}
+
+/// Body of a create or update request, a record without TTL uses the TTL of the zone.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordRequest {
+    pub zone_id: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub name: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+}
+
+/// Body of an error response. Authentication errors only carry a message, the other errors
+/// an error object.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub message: Option<String>,
+    pub error: Option<ErrorDetail>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    pub message: String,
+    pub code: i64,
+}
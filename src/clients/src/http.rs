@@ -0,0 +1,213 @@
+use std::net::IpAddr;
+use std::time::Duration;
+use reqwest::{ClientBuilder, IntoUrl, Proxy, RequestBuilder, Response};
+use crate::error::Error;
+use crate::exponential_backoff;
+use crate::exponential_backoff::RetryPolicy;
+
+/// Timeouts of requests to devices on the local network, which answer quickly or not at all.
+const LOCAL_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const LOCAL_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// HTTP client shared by every API client, holding a single connection pool configured with
+/// timeouts, user agent and proxy, together with the retry policy applied to every request.
+///
+/// Cloning is cheap and shares the underlying connection pool.
+#[derive(Debug, Clone, Default)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    /// The settings the client was built from, unset for the default client.
+    settings: Option<config::HttpSettings>,
+}
+
+impl HttpClient {
+    /// Build the shared client from the configured HTTP settings.
+    pub fn new(settings: &config::HttpSettings, retry_policy: RetryPolicy) -> Result<Self, Error> {
+        let client = build(builder(Some(settings))?)?;
+
+        Ok(Self {
+            client,
+            retry_policy,
+            settings: Some(settings.clone()),
+        })
+    }
+
+    /// A client with the same settings whose connections only use the IP version of the local
+    /// address, `0.0.0.0` for IPv4 and `::` for IPv6. It has a connection pool of its own.
+    pub fn with_local_address(&self, local_address: IpAddr) -> Result<Self, Error> {
+        let client = build(builder(self.settings.as_ref())?.local_address(local_address))?;
+
+        Ok(Self {
+            client,
+            retry_policy: self.retry_policy.clone(),
+            settings: self.settings.clone(),
+        })
+    }
+
+    /// A client for devices on the local network such as the router, with the same user agent but
+    /// without proxy, short timeouts and no retries. It has a connection pool of its own.
+    pub fn for_local_network(&self) -> Result<Self, Error> {
+        let mut builder = reqwest::Client::builder()
+            .no_proxy()
+            .connect_timeout(LOCAL_CONNECT_TIMEOUT)
+            .timeout(LOCAL_REQUEST_TIMEOUT);
+
+        if let Some(settings) = &self.settings {
+            builder = builder.user_agent(settings.user_agent.clone());
+        }
+
+        Ok(Self {
+            client: build(builder)?,
+            retry_policy: RetryPolicy::none(),
+            settings: self.settings.clone(),
+        })
+    }
+
+    /// Replace the retry policy, keeping the connection pool.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// The retry policy applied to every request.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.put(url)
+    }
+
+    pub fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.patch(url)
+    }
+
+    pub fn delete<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.delete(url)
+    }
+
+    /// Send a request built from this client according to the retry policy.
+    pub async fn send(&self, request_builder: RequestBuilder) -> Result<Response, Error> {
+        exponential_backoff::request(request_builder, &self.retry_policy).await
+    }
+}
+
+/// A builder applying the timeouts, user agent and proxy of the settings, reqwest defaults
+/// without settings.
+fn builder(settings: Option<&config::HttpSettings>) -> Result<ClientBuilder, Error> {
+    let mut builder = reqwest::Client::builder();
+
+    let Some(settings) = settings else {
+        return Ok(builder);
+    };
+
+    builder = builder
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_seconds))
+        .timeout(Duration::from_secs(settings.request_timeout_seconds))
+        .user_agent(settings.user_agent.clone());
+
+    if let Some(proxy_url) = &settings.proxy_url {
+        let proxy = Proxy::all(proxy_url).map_err(|error| {
+            Error::network(format!("Invalid proxy URL {}: {}", proxy_url, error))
+        })?;
+
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder)
+}
+
+fn build(builder: ClientBuilder) -> Result<reqwest::Client, Error> {
+    builder.build().map_err(|error| {
+        Error::network(format!("Failed to build HTTP client: {}", error))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn settings() -> config::HttpSettings {
+        config::HttpSettings {
+            connect_timeout_seconds: 1,
+            request_timeout_seconds: 1,
+            user_agent: "dynamic-dns-test".to_string(),
+            proxy_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sends_user_agent() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(header("User-Agent", "dynamic-dns-test"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::new(&settings(), RetryPolicy::none()).unwrap();
+        let response = client.send(client.get(server.uri())).await.unwrap();
+
+        assert!(response.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::new(&settings(), RetryPolicy::none()).unwrap();
+        let error = client.send(client.get(server.uri())).await.unwrap_err();
+
+        assert!(error.message.contains("timed out"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_local_network_client_skips_proxy() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let settings = config::HttpSettings {
+            proxy_url: Some("http://127.0.0.1:9".to_string()),
+            ..settings()
+        };
+
+        let client = HttpClient::new(&settings, RetryPolicy::default()).unwrap().for_local_network().unwrap();
+        let response = client.send(client.get(server.uri())).await.unwrap();
+
+        assert!(response.status().is_success());
+        assert_eq!(client.retry_policy(), &RetryPolicy::none());
+    }
+
+    #[test]
+    fn test_invalid_proxy() {
+        let settings = config::HttpSettings {
+            proxy_url: Some("not a proxy".to_string()),
+            ..settings()
+        };
+
+        assert!(HttpClient::new(&settings, RetryPolicy::none()).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use async_trait::async_trait;
+use hickory_resolver::config::{LookupIpStrategy, NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use crate::error::Error;
+use crate::ip::{IpSource, IpVersion};
+
+/// A source resolving a special name against a specific resolver that answers with the address
+/// the query came from, such as OpenDNS' `myip.opendns.com`.
+#[derive(Debug, Clone)]
+pub struct DnsSource {
+    name: String,
+    query_name: String,
+    v4_server: IpAddr,
+    v6_server: IpAddr,
+}
+
+impl DnsSource {
+    pub fn new(name: String, query_name: String, v4_server: IpAddr, v6_server: IpAddr) -> Self {
+        Self {
+            name,
+            query_name,
+            v4_server,
+            v6_server,
+        }
+    }
+
+    /// The OpenDNS `myip.opendns.com` lookup against resolver1.opendns.com.
+    pub fn opendns() -> Self {
+        Self::new(
+            "opendns".to_string(),
+            "myip.opendns.com.".to_string(),
+            IpAddr::V4(Ipv4Addr::new(208, 67, 222, 222)),
+            IpAddr::V6(Ipv6Addr::new(0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35)),
+        )
+    }
+}
+
+#[async_trait]
+impl IpSource for DnsSource {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn lookup(&self, version: IpVersion) -> Result<IpAddr, Error> {
+        // The query has to reach the resolver over the requested IP version, as it answers with
+        // the address it sees the query coming from.
+        let (server, ip_strategy) = match version {
+            IpVersion::V4 => (self.v4_server, LookupIpStrategy::Ipv4Only),
+            IpVersion::V6 => (self.v6_server, LookupIpStrategy::Ipv6Only),
+        };
+
+        let name_servers = NameServerConfigGroup::from_ips_clear(&[server], 53, true);
+        let config = ResolverConfig::from_parts(None, vec![], name_servers);
+
+        let mut options = ResolverOpts::default();
+        options.ip_strategy = ip_strategy;
+        options.use_hosts_file = false;
+        options.cache_size = 0;
+
+        tracing::debug!("Resolving {} against {}", self.query_name, server);
+
+        let resolver = TokioAsyncResolver::tokio(config, options);
+
+        let response = resolver.lookup_ip(self.query_name.as_str()).await.map_err(|error| {
+            Error::network(format!("Failed to resolve {} against {}: {}", self.query_name, server, error))
+        })?;
+
+        response.iter()
+            .find(|ip| version.matches(ip))
+            .ok_or_else(|| Error::parse(format!("No {} address returned for {}", version, self.query_name), ""))
+    }
+}
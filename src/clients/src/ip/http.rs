@@ -0,0 +1,211 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use serde_json::Value;
+use crate::error::Error;
+use crate::http::HttpClient;
+use crate::ip::{parse_ip, IpSource, IpVersion};
+
+/// Send a GET request and return the body of a successful response.
+async fn fetch_body(http: &HttpClient, url: &str) -> Result<String, Error> {
+    tracing::debug!("Fetching public IP address from: {}", url);
+
+    let request_builder = http
+        .get(url)
+        .header("Accept", "text/plain, application/json");
+
+    let response = http.send(request_builder)
+        .await
+        .map_err(|error| Error::network(format!("Failed to reach {}: {}", url, error.message)))?;
+
+    let status = response.status();
+
+    let body = response.text().await.map_err(|error| {
+        Error::network(format!("Failed to read response from {}: {}", url, error))
+    })?;
+
+    if !status.is_success() {
+        return Err(Error::http(format!("{} responded with an error", url), status.as_u16(), &body));
+    }
+
+    Ok(body)
+}
+
+/// A source responding with the address as plain text, optionally with separate endpoints per
+/// IP version. Without dedicated endpoints the request is sent over the requested IP version, so
+/// a dual-stack host gets the address of that version back.
+#[derive(Debug, Clone)]
+pub struct PlainTextSource {
+    name: String,
+    v4_url: String,
+    v6_url: String,
+    http: HttpClient,
+}
+
+impl PlainTextSource {
+    /// A source using the same URL for both IP versions.
+    pub fn new(url: String) -> Self {
+        Self {
+            name: url.clone(),
+            v4_url: url.clone(),
+            v6_url: url,
+            http: HttpClient::default(),
+        }
+    }
+
+    /// The icanhazip.com service, which has dedicated IPv4 and IPv6 hosts.
+    pub fn icanhazip() -> Self {
+        Self {
+            name: "icanhazip".to_string(),
+            v4_url: "https://ipv4.icanhazip.com".to_string(),
+            v6_url: "https://ipv6.icanhazip.com".to_string(),
+            http: HttpClient::default(),
+        }
+    }
+
+    /// The ifconfig.co service, which answers with the address the request arrived from on the
+    /// same host for both IP versions.
+    pub fn ifconfig_co() -> Self {
+        Self {
+            name: "ifconfig.co".to_string(),
+            v4_url: "https://ifconfig.co/ip".to_string(),
+            v6_url: "https://ifconfig.co/ip".to_string(),
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+}
+
+#[async_trait]
+impl IpSource for PlainTextSource {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn lookup(&self, version: IpVersion) -> Result<IpAddr, Error> {
+        let url = match version {
+            IpVersion::V4 => &self.v4_url,
+            IpVersion::V6 => &self.v6_url,
+        };
+
+        let body = match self.v4_url == self.v6_url {
+            true => {
+                let http = self.http.with_local_address(version.unspecified())?;
+                fetch_body(&http, url).await?
+            },
+            false => fetch_body(&self.http, url).await?,
+        };
+
+        parse_ip(&body, version)
+    }
+}
+
+/// A source responding with JSON, the address is read from a dot separated field path such as
+/// `data.ip`.
+#[derive(Debug, Clone)]
+pub struct JsonSource {
+    url: String,
+    field_path: String,
+    http: HttpClient,
+}
+
+impl JsonSource {
+    pub fn new(url: String, field_path: String) -> Self {
+        Self {
+            url,
+            field_path,
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+}
+
+#[async_trait]
+impl IpSource for JsonSource {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn lookup(&self, version: IpVersion) -> Result<IpAddr, Error> {
+        let body = fetch_body(&self.http, &self.url).await?;
+
+        let json: Value = serde_json::from_str(&body).map_err(|error| {
+            Error::parse(format!("Failed to parse JSON response from {}: {}", self.url, error), &body)
+        })?;
+
+        let field = self.field_path
+            .split('.')
+            .try_fold(&json, |value, key| value.get(key))
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| Error::parse(format!("Field {} missing from response of {}", self.field_path, self.url), &body))?;
+
+        parse_ip(field, version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exponential_backoff::RetryPolicy;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_plain_text_source() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/ip"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("203.0.113.7\n"))
+            .mount(&server)
+            .await;
+
+        let source = PlainTextSource::new(format!("{}/ip", server.uri()))
+            .with_http_client(HttpClient::default().with_retry_policy(RetryPolicy::none()));
+
+        // The mock server only listens on IPv4, so the IPv6 request cannot connect.
+        assert_eq!(source.lookup(IpVersion::V4).await.unwrap(), "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert!(source.lookup(IpVersion::V6).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plain_text_source_error_status() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let source = PlainTextSource::new(server.uri())
+            .with_http_client(HttpClient::default().with_retry_policy(RetryPolicy::none()));
+
+        assert!(source.lookup(IpVersion::V4).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_json_source_field_path() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/ip"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "data": { "ip": "2001:db8::1" } })))
+            .mount(&server)
+            .await;
+
+        let source = JsonSource::new(format!("{}/ip", server.uri()), "data.ip".to_string());
+        let missing_field = JsonSource::new(format!("{}/ip", server.uri()), "data.address".to_string());
+
+        assert_eq!(source.lookup(IpVersion::V6).await.unwrap(), "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert!(missing_field.lookup(IpVersion::V6).await.is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use crate::error::Error;
+use crate::ip::{is_public, IpSource, IpVersion};
+
+/// A source reading the public address straight from a local network interface, for hosts that
+/// have their public address assigned directly (e.g. a PPPoE link or a routed IPv6 prefix).
+#[derive(Debug, Clone)]
+pub struct InterfaceSource {
+    interface: String,
+}
+
+impl InterfaceSource {
+    pub fn new(interface: String) -> Self {
+        Self {
+            interface,
+        }
+    }
+}
+
+/// Pick the first public address of the requested version, skipping private, link-local, CGNAT
+/// and other reserved addresses an interface usually carries alongside its public one.
+pub(crate) fn select_public_address(addresses: impl IntoIterator<Item = IpAddr>, version: IpVersion) -> Option<IpAddr> {
+    addresses.into_iter().find(|ip| version.matches(ip) && is_public(ip))
+}
+
+#[async_trait]
+impl IpSource for InterfaceSource {
+    fn name(&self) -> String {
+        format!("interface:{}", self.interface)
+    }
+
+    async fn lookup(&self, version: IpVersion) -> Result<IpAddr, Error> {
+        let interfaces = if_addrs::get_if_addrs().map_err(|error| {
+            Error::network(format!("Failed to list network interfaces: {}", error))
+        })?;
+
+        let addresses: Vec<IpAddr> = interfaces.into_iter()
+            .filter(|interface| interface.name == self.interface)
+            .map(|interface| interface.ip())
+            .collect();
+
+        if addresses.is_empty() {
+            return Err(Error::network(format!("Interface {} not found or has no addresses", self.interface)));
+        }
+
+        tracing::debug!("Interface {} has addresses {:?}", self.interface, addresses);
+
+        select_public_address(addresses.clone(), version).ok_or_else(|| {
+            Error::parse(format!("Interface {} has no public {} address", self.interface, version), &format!("{:?}", addresses))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_public_address() {
+        let addresses: Vec<IpAddr> = ["192.168.1.2", "100.64.1.2", "fe80::1", "1.1.1.1", "2606:4700::1"]
+            .iter()
+            .map(|ip| ip.parse().unwrap())
+            .collect();
+
+        assert_eq!(select_public_address(addresses.clone(), IpVersion::V4), Some("1.1.1.1".parse().unwrap()));
+        assert_eq!(select_public_address(addresses, IpVersion::V6), Some("2606:4700::1".parse().unwrap()));
+        assert_eq!(select_public_address(vec!["10.0.0.1".parse().unwrap()], IpVersion::V4), None);
+    }
+
+    #[tokio::test]
+    async fn test_loopback_interface_has_no_public_address() {
+        let source = InterfaceSource::new("lo".to_string());
+
+        assert!(source.lookup(IpVersion::V4).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_interface() {
+        let source = InterfaceSource::new("does-not-exist0".to_string());
+
+        assert!(source.lookup(IpVersion::V4).await.is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt::Display;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use async_trait::async_trait;
+use crate::error::Error;
+
+pub mod http;
+pub mod dns;
+pub mod interface;
+pub mod upnp;
+
+/// The IP protocol version of a public address lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    /// Whether the address belongs to this IP version.
+    pub fn matches(&self, ip: &IpAddr) -> bool {
+        match self {
+            IpVersion::V4 => ip.is_ipv4(),
+            IpVersion::V6 => ip.is_ipv6(),
+        }
+    }
+
+    /// The unspecified address of this IP version, binding to it restricts a connection to the
+    /// version.
+    pub fn unspecified(&self) -> IpAddr {
+        match self {
+            IpVersion::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpVersion::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+}
+
+impl Display for IpVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpVersion::V4 => write!(f, "IPv4"),
+            IpVersion::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// A service able to tell the public IP address of the current machine.
+#[async_trait]
+pub trait IpSource: Send + Sync {
+    /// Human readable name of the source, used for logging.
+    fn name(&self) -> String;
+
+    /// Look up the public address of the given IP version.
+    async fn lookup(&self, version: IpVersion) -> Result<IpAddr, Error>;
+}
+
+/// Whether the address is globally routable, rejecting private, loopback, link-local, CGNAT,
+/// documentation, multicast and other reserved ranges.
+pub fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+fn is_public_v4(ip: &Ipv4Addr) -> bool {
+    let octets = ip.octets();
+
+    let is_shared = octets[0] == 100 && (octets[1] & 0b1100_0000) == 64;
+    let is_benchmarking = octets[0] == 198 && (octets[1] & 0xfe) == 18;
+    let is_reserved = octets[0] >= 240 || octets[0] == 0;
+
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || is_shared
+        || is_benchmarking
+        || is_reserved)
+}
+
+fn is_public_v6(ip: &Ipv6Addr) -> bool {
+    if let Some(mapped) = ip.to_ipv4_mapped() {
+        return is_public_v4(&mapped);
+    }
+
+    let segments = ip.segments();
+
+    let is_unique_local = (segments[0] & 0xfe00) == 0xfc00;
+    let is_link_local = (segments[0] & 0xffc0) == 0xfe80;
+    let is_documentation = segments[0] == 0x2001 && segments[1] == 0x0db8;
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || is_unique_local
+        || is_link_local
+        || is_documentation)
+}
+
+/// Parse the raw address returned by a source, making sure it belongs to the requested version.
+pub(crate) fn parse_ip(raw_ip: &str, version: IpVersion) -> Result<IpAddr, Error> {
+    let ip: IpAddr = raw_ip.trim().parse().map_err(|_| {
+        Error::parse("Response is not an IP address".to_string(), raw_ip)
+    })?;
+
+    if !version.matches(&ip) {
+        return Err(Error::parse(format!("Expected an {} address", version), raw_ip));
+    }
+
+    Ok(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ip() {
+        assert_eq!(parse_ip("203.0.113.7\n", IpVersion::V4).unwrap(), "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert!(parse_ip("2001:db8::1", IpVersion::V4).is_err());
+        assert!(parse_ip("<html>captive portal</html>", IpVersion::V4).is_err());
+    }
+
+    #[test]
+    fn test_is_public() {
+        let public = ["1.1.1.1", "2606:4700:4700::1111"];
+        let reserved = [
+            "10.0.0.1", "172.16.0.1", "192.168.1.1", "127.0.0.1", "169.254.1.1", "100.64.0.1",
+            "100.127.255.255", "203.0.113.7", "0.0.0.0", "255.255.255.255", "224.0.0.1",
+            "::1", "fe80::1", "fd00::1", "2001:db8::1", "::ffff:192.168.1.1",
+        ];
+
+        for ip in public {
+            assert!(is_public(&ip.parse().unwrap()), "{} should be public", ip);
+        }
+
+        for ip in reserved {
+            assert!(!is_public(&ip.parse().unwrap()), "{} should not be public", ip);
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::net::IpAddr;
+use std::time::Duration;
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::Deserialize;
+use tokio::net::UdpSocket;
+use crate::error::Error;
+use crate::http::HttpClient;
+use crate::ip::{parse_ip, IpSource, IpVersion};
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+const SSDP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The WAN connection services able to answer `GetExternalIPAddress`, in order of preference.
+const WAN_SERVICE_TYPES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// The parts of a UPnP device description needed to find the WAN connection service.
+#[derive(Debug, Deserialize)]
+struct Description {
+    #[serde(rename = "URLBase")]
+    url_base: Option<String>,
+    device: Device,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Device {
+    #[serde(rename = "serviceList", default)]
+    service_list: ServiceList,
+    /// Embedded devices, the WAN connection service usually lives two levels down.
+    #[serde(rename = "deviceList", default)]
+    device_list: DeviceList,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ServiceList {
+    #[serde(default)]
+    service: Vec<Service>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DeviceList {
+    #[serde(default)]
+    device: Vec<Device>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Service {
+    #[serde(rename = "serviceType")]
+    service_type: String,
+    #[serde(rename = "controlURL")]
+    control_url: String,
+}
+
+impl Device {
+    /// The services of the device and of its embedded devices.
+    fn services(&self) -> Vec<&Service> {
+        self.service_list.service.iter()
+            .chain(self.device_list.device.iter().flat_map(Device::services))
+            .collect()
+    }
+}
+
+/// The SOAP answer to `GetExternalIPAddress`, element names are matched without their namespace
+/// prefix.
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    #[serde(rename = "Body")]
+    body: EnvelopeBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnvelopeBody {
+    #[serde(rename = "GetExternalIPAddressResponse")]
+    response: ExternalIpAddressResponse,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalIpAddressResponse {
+    #[serde(rename = "NewExternalIPAddress")]
+    external_ip: String,
+}
+
+/// A source asking the router for its external address through UPnP IGD, either discovering
+/// the gateway with SSDP or using a known device description URL.
+#[derive(Debug, Clone, Default)]
+pub struct UpnpSource {
+    description_url: Option<String>,
+    http: HttpClient,
+}
+
+impl UpnpSource {
+    /// Discover the gateway on the local network with an SSDP search on every lookup.
+    pub fn discover() -> Self {
+        Self {
+            description_url: None,
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the gateway at the given device description URL, e.g. `http://192.168.1.1:5000/rootDesc.xml`.
+    pub fn new(description_url: String) -> Self {
+        Self {
+            description_url: Some(description_url),
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the given HTTP client instead of a dedicated default one. The router is on the local
+    /// network, so the client should come from [`HttpClient::for_local_network`].
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Send an SSDP M-SEARCH for an internet gateway device and return its description URL.
+    async fn discover_description_url(&self) -> Result<String, Error> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(|error| {
+            Error::network(format!("Failed to bind SSDP socket: {}", error))
+        })?;
+
+        let search = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\n\r\n",
+            SSDP_ADDRESS
+        );
+
+        socket.send_to(search.as_bytes(), SSDP_ADDRESS).await.map_err(|error| {
+            Error::network(format!("Failed to send SSDP search: {}", error))
+        })?;
+
+        let mut buffer = [0u8; 2048];
+        let (length, gateway) = tokio::time::timeout(SSDP_TIMEOUT, socket.recv_from(&mut buffer)).await
+            .map_err(|_| Error::network("No UPnP gateway answered the SSDP search".to_string()))?
+            .map_err(|error| Error::network(format!("Failed to receive SSDP response: {}", error)))?;
+
+        let response = String::from_utf8_lossy(&buffer[..length]);
+
+        tracing::debug!("SSDP response from {}: {}", gateway, response);
+
+        response.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(header, _)| header.trim().eq_ignore_ascii_case("location"))
+            .map(|(_, location)| location.trim().to_string())
+            .ok_or_else(|| Error::parse(format!("SSDP response from {} has no location", gateway), &response))
+    }
+
+    /// Fetch the device description and find the control URL of its WAN connection service.
+    async fn find_wan_service(&self, description_url: &str) -> Result<(String, &'static str), Error> {
+        let request_builder = self.http
+            .get(description_url);
+
+        let description = self.http.send(request_builder)
+            .await
+            .map_err(|error| Error::network(format!("Failed to fetch UPnP description {}: {}", description_url, error.message)))?
+            .text()
+            .await
+            .map_err(|error| Error::network(format!("Failed to read UPnP description {}: {}", description_url, error)))?;
+
+        let parsed: Description = quick_xml::de::from_str(&description).map_err(|error| {
+            Error::parse(format!("Invalid UPnP description {}: {}", description_url, error), &description)
+        })?;
+
+        let base_url = parsed.url_base.as_deref().map(str::trim).unwrap_or(description_url);
+        let base_url = Url::parse(base_url).map_err(|error| {
+            Error::parse(format!("Invalid UPnP base URL {}: {}", base_url, error), &description)
+        })?;
+
+        let services = parsed.device.services();
+
+        for service_type in WAN_SERVICE_TYPES {
+            if let Some(service) = services.iter().find(|service| service.service_type.trim() == service_type) {
+                let control_url = base_url.join(service.control_url.trim()).map_err(|error| {
+                    Error::parse(format!("Invalid UPnP control URL {}: {}", service.control_url, error), &description)
+                })?;
+
+                return Ok((control_url.to_string(), service_type));
+            }
+        }
+
+        Err(Error::parse(format!("UPnP device {} has no WAN connection service", description_url), &description))
+    }
+}
+
+#[async_trait]
+impl IpSource for UpnpSource {
+    fn name(&self) -> String {
+        match &self.description_url {
+            Some(description_url) => format!("upnp:{}", description_url),
+            None => "upnp".to_string(),
+        }
+    }
+
+    async fn lookup(&self, version: IpVersion) -> Result<IpAddr, Error> {
+        if version == IpVersion::V6 {
+            return Err(Error::network("UPnP gateways only report an IPv4 external address".to_string()));
+        }
+
+        let description_url = match &self.description_url {
+            Some(description_url) => description_url.clone(),
+            None => self.discover_description_url().await?,
+        };
+
+        let (control_url, service_type) = self.find_wan_service(&description_url).await?;
+
+        tracing::debug!("Querying external address from {} ({})", control_url, service_type);
+
+        let envelope = format!(
+            "<?xml version=\"1.0\"?>\
+            <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+            <s:Body><u:GetExternalIPAddress xmlns:u=\"{}\"></u:GetExternalIPAddress></s:Body>\
+            </s:Envelope>",
+            service_type
+        );
+
+        let request_builder = self.http
+            .post(&control_url)
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header("SOAPAction", format!("\"{}#GetExternalIPAddress\"", service_type))
+            .body(envelope);
+
+        let response = self.http.send(request_builder)
+            .await
+            .map_err(|error| Error::network(format!("Failed to query UPnP gateway {}: {}", control_url, error.message)))?;
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_else(|_| "Empty response body".to_string());
+
+        if !status.is_success() {
+            return Err(Error::http(format!("UPnP gateway {} responded with an error", control_url), status.as_u16(), &body));
+        }
+
+        let envelope: Envelope = quick_xml::de::from_str(&body).map_err(|_| {
+            Error::parse(format!("UPnP gateway {} did not return an external address", control_url), &body)
+        })?;
+
+        parse_ip(&envelope.body.response.external_ip, version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exponential_backoff::RetryPolicy;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const DESCRIPTION: &str = "<?xml version=\"1.0\"?>
+        <root xmlns=\"urn:schemas-upnp-org:device-1-0\"><device><serviceList>
+        <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType><controlURL>/ctl/L3F</controlURL></service>
+        <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType><controlURL>/ctl/IPConn</controlURL></service>
+        </serviceList></device></root>";
+
+    async fn mock_gateway(external_ip_response: ResponseTemplate) -> MockServer {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/rootDesc.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(DESCRIPTION))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/ctl/IPConn"))
+            .and(header("SOAPAction", "\"urn:schemas-upnp-org:service:WANIPConnection:1#GetExternalIPAddress\""))
+            .respond_with(external_ip_response)
+            .mount(&server)
+            .await;
+
+        server
+    }
+
+    #[tokio::test]
+    async fn test_upnp_external_address() {
+        let server = mock_gateway(ResponseTemplate::new(200).set_body_string(
+            "<s:Envelope><s:Body><u:GetExternalIPAddressResponse>\
+            <NewExternalIPAddress>198.51.100.20</NewExternalIPAddress>\
+            </u:GetExternalIPAddressResponse></s:Body></s:Envelope>"
+        )).await;
+
+        let source = UpnpSource::new(format!("{}/rootDesc.xml", server.uri()));
+
+        assert_eq!(source.lookup(IpVersion::V4).await.unwrap(), "198.51.100.20".parse::<IpAddr>().unwrap());
+        assert!(source.lookup(IpVersion::V6).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upnp_gateway_fault() {
+        let server = mock_gateway(ResponseTemplate::new(500).set_body_string("<s:Fault></s:Fault>")).await;
+
+        let source = UpnpSource::new(format!("{}/rootDesc.xml", server.uri()))
+            .with_http_client(HttpClient::default().with_retry_policy(RetryPolicy::none()));
+
+        assert!(source.lookup(IpVersion::V4).await.is_err());
+    }
+
+    #[test]
+    fn test_services_of_embedded_devices() {
+        let description: Description = quick_xml::de::from_str("<?xml version=\"1.0\"?>
+            <root xmlns=\"urn:schemas-upnp-org:device-1-0\">
+            <URLBase>http://192.168.1.1:5000/</URLBase>
+            <device><deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+            <serviceList><service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType><controlURL>/ctl/L3F</controlURL></service></serviceList>
+            <deviceList><device><deviceType>urn:schemas-upnp-org:device:WANDevice:1</deviceType>
+            <deviceList><device><deviceType>urn:schemas-upnp-org:device:WANConnectionDevice:1</deviceType>
+            <serviceList><service><serviceType>urn:schemas-upnp-org:service:WANPPPConnection:1</serviceType><controlURL>/ctl/PPPConn</controlURL></service></serviceList>
+            </device></deviceList></device></deviceList></device></root>").unwrap();
+
+        let services: Vec<(&str, &str)> = description.device.services().iter()
+            .map(|service| (service.service_type.as_str(), service.control_url.as_str()))
+            .collect();
+
+        assert_eq!(description.url_base.as_deref(), Some("http://192.168.1.1:5000/"));
+        assert_eq!(services, vec![
+            ("urn:schemas-upnp-org:service:Layer3Forwarding:1", "/ctl/L3F"),
+            ("urn:schemas-upnp-org:service:WANPPPConnection:1", "/ctl/PPPConn"),
+        ]);
+    }
+}
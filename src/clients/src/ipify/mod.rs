@@ -1,6 +1,12 @@
 use std::fmt::Display;
+use std::net::IpAddr;
+use async_trait::async_trait;
 use serde::Deserialize;
-use crate::exponential_backoff;
+use crate::http::HttpClient;
+#[cfg(test)]
+use crate::exponential_backoff::RetryPolicy;
+use crate::error::Error;
+use crate::ip::{parse_ip, IpSource, IpVersion};
 
 #[derive(Debug, Clone)]
 pub enum Format {
@@ -10,8 +16,9 @@ pub enum Format {
 
 #[derive(Debug, Clone)]
 pub struct Ipify {
-    url: &'static str,
+    url: String,
     format: Format,
+    http: HttpClient,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -20,42 +27,82 @@ struct IpifyResponse {
 }
 
 impl Ipify {
+    /// Build an Ipify client resolving the public IPv4 address.
     pub fn new(format: Format) -> Self {
-        let url = match format {
-            Format::Json => {
+        Self::for_version(format, IpVersion::V4)
+    }
+
+    /// Build an Ipify client resolving the public address of the given IP version, IPv6 lookups
+    /// go through the IPv6 only `api6.ipify.org` endpoint.
+    pub fn for_version(format: Format, version: IpVersion) -> Self {
+        let url = match (format.clone(), version) {
+            (Format::Json, IpVersion::V4) => {
                 "https://api.ipify.org?format=json"
             }
-            Format::Text => {
+            (Format::Text, IpVersion::V4) => {
                 "https://api.ipify.org"
             }
+            (Format::Json, IpVersion::V6) => {
+                "https://api6.ipify.org?format=json"
+            }
+            (Format::Text, IpVersion::V6) => {
+                "https://api6.ipify.org"
+            }
         };
 
+        Self {
+            format,
+            url: url.to_string(),
+            http: HttpClient::default(),
+        }
+    }
+
+    #[cfg(test)]
+    fn with_url(url: String, format: Format) -> Self {
         Self {
             format,
             url,
+            http: HttpClient::default().with_retry_policy(RetryPolicy::none()),
         }
     }
 
-    pub async fn get_ip(&self) -> Result<String, reqwest::Error> {
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub async fn get_ip(&self) -> Result<String, Error> {
         tracing::debug!("Fetching public IP address from: {}", self.url);
 
-        let fetch_ip_request = reqwest::Client::new()
-            .get(self.url);
+        let fetch_ip_request = self.http
+            .get(&self.url);
+
+        let response = self.http.send(fetch_ip_request).await.map_err(|error| {
+            Error::network(format!("Failed to fetch IP address from {}: {}", self.url, error.message))
+        })?;
 
-        let fetch_ip = exponential_backoff::request(fetch_ip_request).await;
+        let status = response.status();
+        let body = response.text().await.map_err(|error| {
+            Error::network(format!("Failed to read response from {}: {}", self.url, error))
+        })?;
 
-        let ip_address = fetch_ip.expect("Failed to fetch IP address");
+        if !status.is_success() {
+            return Err(Error::http(format!("{} responded with an error", self.url), status.as_u16(), &body));
+        }
 
         match self.format {
             Format::Json => {
-                let ipify_response: IpifyResponse = ip_address.json().await.expect("Failed to parse JSON response");
+                let ipify_response: IpifyResponse = serde_json::from_str(&body).map_err(|error| {
+                    Error::parse(format!("Failed to parse JSON response: {}", error), &body)
+                })?;
 
                 tracing::debug!("Fetched public IP address: {}", ipify_response.ip);
 
                 Ok(ipify_response.ip)
             }
             Format::Text => {
-                let ip_address = ip_address.text().await.expect("Failed to parse text response");
+                let ip_address = body.trim().to_string();
 
                 tracing::debug!("Fetched public IP address: {}", ip_address);
 
@@ -65,6 +112,22 @@ impl Ipify {
     }
 }
 
+#[async_trait]
+impl IpSource for Ipify {
+    fn name(&self) -> String {
+        "ipify".to_string()
+    }
+
+    async fn lookup(&self, version: IpVersion) -> Result<IpAddr, Error> {
+        let ip_address = Ipify::for_version(self.format.clone(), version)
+            .with_http_client(self.http.clone())
+            .get_ip()
+            .await?;
+
+        parse_ip(&ip_address, version)
+    }
+}
+
 impl Display for Ipify {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "url: {}", self.url)
@@ -75,6 +138,63 @@ impl Display for Ipify {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ErrorKind;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock_ipify(response: ResponseTemplate) -> MockServer {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+
+        server
+    }
+
+    #[tokio::test]
+    async fn test_get_ip_mock_text() {
+        let server = mock_ipify(ResponseTemplate::new(200).set_body_string("198.51.100.20\n")).await;
+
+        let result = Ipify::with_url(server.uri(), Format::Text).get_ip().await;
+
+        assert_eq!(result.unwrap(), "198.51.100.20");
+    }
+
+    #[tokio::test]
+    async fn test_get_ip_http_error() {
+        let server = mock_ipify(ResponseTemplate::new(503).set_body_string("<html>Service Unavailable</html>")).await;
+
+        let error = Ipify::with_url(server.uri(), Format::Text).get_ip().await.unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Http);
+        assert_eq!(error.status, Some(503));
+        assert_eq!(error.body.as_deref(), Some("<html>Service Unavailable</html>"));
+    }
+
+    #[tokio::test]
+    async fn test_get_ip_network_error() {
+        // Bind and release a port so nothing is listening on it.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let error = Ipify::with_url(url, Format::Text).get_ip().await.unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Network);
+        assert_eq!(error.status, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_ip_parse_error() {
+        let server = mock_ipify(ResponseTemplate::new(200).set_body_string("<html>captive portal</html>")).await;
+
+        let error = Ipify::with_url(server.uri(), Format::Json).get_ip().await.unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.status, None);
+    }
 
     #[tokio::test]
     async fn test_get_ip_text() {
@@ -1,3 +1,12 @@
 pub mod cloudflare;
+pub mod digitalocean;
+pub mod dyndns2;
+pub mod error;
+pub mod hetzner;
+pub mod http;
+pub mod ip;
 pub mod ipify;
-pub(crate) mod exponential_backoff;
+pub mod powerdns;
+pub mod rfc2136;
+pub mod route53;
+pub mod exponential_backoff;
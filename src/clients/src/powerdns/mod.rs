@@ -0,0 +1,290 @@
+use config::sync_entry::RecordType;
+use reqwest::{RequestBuilder, StatusCode};
+use crate::http::HttpClient;
+use crate::powerdns::error::Error;
+use crate::powerdns::types::{ErrorResponse, PatchRequest, RRset, RRsetChange, Zone, ZoneSummary};
+
+pub mod error;
+pub mod types;
+
+/// Client of the PowerDNS Authoritative HTTP API. PowerDNS has no record ids, records are read
+/// and written as whole record sets.
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub(crate) api_url: String,
+    pub(crate) api_key: String,
+    pub(crate) server_id: String,
+    pub(crate) zone_id: String,
+    pub(crate) default_ttl: u64,
+    pub(crate) http: HttpClient,
+}
+
+impl Client {
+    /// # Arguments
+    /// * `api_url` - The webserver of PowerDNS, e.g. `http://127.0.0.1:8081`.
+    /// * `api_key` - The `api-key` of the PowerDNS configuration.
+    /// * `server_id` - The server the zone belongs to, `localhost` on a single server.
+    /// * `zone_id` - The id of the zone, its name with the trailing dot.
+    /// * `default_ttl` - TTL of records that do not set their own.
+    pub fn new(api_url: String, api_key: String, server_id: String, zone_id: String, default_ttl: u64) -> Self {
+        Self {
+            api_url,
+            api_key,
+            server_id,
+            zone_id,
+            default_ttl,
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Use the given zone instead of the one the client was created with.
+    pub fn with_zone_id(mut self, zone_id: String) -> Self {
+        self.zone_id = zone_id;
+        self
+    }
+
+    /// TTL of records that do not set their own, PowerDNS requires one on every record set.
+    pub fn default_ttl(&self) -> u64 {
+        self.default_ttl
+    }
+
+    /// Find the id of the zone serving the domain, trying the parent domains in turn when the
+    /// domain is below the zone apex.
+    ///
+    /// # Errors
+    /// When the server has no zone for the domain.
+    pub async fn find_zone_id(&self, domain: &str) -> Result<String, Error> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let mut name = domain.as_str();
+
+        loop {
+            let zone = format!("{}.", name);
+            let request_builder = self.http.get(self.url("zones")).query(&[("zone", zone.as_str())]);
+            let (status, body) = self.send(request_builder, "fetch zones").await?;
+
+            if !status.is_success() {
+                return Err(error_of("fetch zones", status, &body));
+            }
+
+            let zones: Vec<ZoneSummary> = parse(&body)?;
+
+            if let Some(zone) = zones.into_iter().find(|summary| summary.name.eq_ignore_ascii_case(&zone)) {
+                return Ok(zone.id);
+            }
+
+            match name.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => name = parent,
+                _ => {
+                    return Err(Error::new(format!(
+                        "No PowerDNS zone for {} on server {}, create the zone or set POWERDNS_ZONE",
+                        domain,
+                        self.server_id
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Fetch the A and AAAA record sets of the zone, disabled records included.
+    pub async fn get_rrsets(&self) -> Result<Vec<RRset>, Error> {
+        let url = self.url(&format!("zones/{}", self.zone_id));
+        let (status, body) = self.send(self.http.get(url), "fetch zone").await?;
+
+        if !status.is_success() {
+            return Err(error_of("fetch zone", status, &body));
+        }
+
+        let zone: Zone = parse(&body)?;
+
+        Ok(zone.rrsets.into_iter()
+            .filter(|rrset| rrset.type_field.parse::<RecordType>().is_ok())
+            .collect())
+    }
+
+    /// Apply the record set changes in one request, PowerDNS applies them all or none.
+    pub async fn patch_rrsets(&self, rrsets: &[RRsetChange]) -> Result<(), Error> {
+        let url = self.url(&format!("zones/{}", self.zone_id));
+        let body = PatchRequest {
+            rrsets: rrsets.to_vec(),
+        };
+
+        let (status, body) = self.send(self.http.patch(url).json(&body), "change record sets").await?;
+
+        if !status.is_success() {
+            return Err(error_of("change record sets", status, &body));
+        }
+
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/v1/servers/{}/{}", self.api_url.trim_end_matches('/'), self.server_id, path)
+    }
+
+    /// Send the authenticated request.
+    ///
+    /// # Returns
+    /// The status and body of the response, whatever the status.
+    async fn send(&self, request_builder: RequestBuilder, action: &str) -> Result<(StatusCode, String), Error> {
+        let request_builder = request_builder.header("X-API-Key", &self.api_key);
+
+        let response = self.http.send(request_builder).await.map_err(|error| {
+            tracing::error!("Failed to {}: {}", action, error);
+            Error::new(format!("Failed to {}", action))
+        })?;
+
+        let status: StatusCode = response.status();
+        let body: String = response.text().await.unwrap_or_else(|_| "Empty response body".to_string());
+
+        tracing::debug!("Received response {}, {}", status, body);
+
+        Ok((status, body))
+    }
+}
+
+fn parse<'a, T: serde::Deserialize<'a>>(body: &'a str) -> Result<T, Error> {
+    serde_json::from_str(body).map_err(|error| {
+        tracing::error!("Failed to parse response body: {}", error);
+        Error::new("Failed to parse response body".to_string())
+    })
+}
+
+/// The error of a failed request, with the message PowerDNS gave when there is one.
+fn error_of(action: &str, status: StatusCode, body: &str) -> Error {
+    let reason = serde_json::from_str::<ErrorResponse>(body)
+        .map(|response| response.error)
+        .unwrap_or_else(|_| status.to_string());
+
+    Error::new(format!("Failed to {}, PowerDNS responded with {}", action, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::powerdns::types::RRsetRecord;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> Client {
+        Client::new(server.uri(), "key".to_string(), "localhost".to_string(), "example.com.".to_string(), 300)
+    }
+
+    fn rrset(name: &str, type_field: &str, records: &[(&str, bool)]) -> serde_json::Value {
+        let records: Vec<serde_json::Value> = records.iter().map(|(content, disabled)| json!({ "content": content, "disabled": disabled })).collect();
+
+        json!({ "name": name, "type": type_field, "ttl": 3600, "records": records, "comments": [] })
+    }
+
+    #[tokio::test]
+    async fn test_get_rrsets_keeps_address_records() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/servers/localhost/zones/example.com."))
+            .and(header("X-API-Key", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "example.com.",
+                "name": "example.com.",
+                "kind": "Native",
+                "serial": 2024030501,
+                "rrsets": [
+                    rrset("example.com.", "SOA", &[("a.misconfigured.dns.server.invalid. hostmaster.example.com. 2024030501 10800 3600 604800 3600", false)]),
+                    rrset("www.example.com.", "A", &[("192.0.2.1", false), ("192.0.2.2", true)]),
+                    rrset("old.example.com.", "A", &[("192.0.2.3", true)]),
+                    rrset("example.com.", "AAAA", &[("2001:db8::1", false)]),
+                ]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let rrsets = client(&server).get_rrsets().await.unwrap();
+        let rrsets: Vec<(&str, &str, &[RRsetRecord])> = rrsets.iter()
+            .map(|rrset| (rrset.name.as_str(), rrset.type_field.as_str(), rrset.records.as_slice()))
+            .collect();
+        let record = |content: &str, disabled: bool| RRsetRecord { content: content.to_string(), disabled };
+
+        assert_eq!(rrsets, vec![
+            ("www.example.com.", "A", [record("192.0.2.1", false), record("192.0.2.2", true)].as_slice()),
+            ("old.example.com.", "A", [record("192.0.2.3", true)].as_slice()),
+            ("example.com.", "AAAA", [record("2001:db8::1", false)].as_slice()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_patch_rrsets_replaces_and_deletes() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/servers/localhost/zones/example.com."))
+            .and(body_json(json!({ "rrsets": [
+                { "name": "www.example.com.", "type": "A", "changetype": "REPLACE", "ttl": 300, "records": [{ "content": "192.0.2.9", "disabled": false }] },
+                { "name": "old.example.com.", "type": "A", "changetype": "DELETE" },
+            ] })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let changes = vec![
+            RRsetChange::new("www.example.com.".to_string(), "A".to_string(), 300, &["192.0.2.9".to_string()], &[]),
+            RRsetChange::new("old.example.com.".to_string(), "A".to_string(), 300, &[], &[]),
+        ];
+
+        assert!(client(&server).patch_rrsets(&changes).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_patch_rrsets_error_message() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/servers/localhost/zones/example.com."))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "error": "RRset www.example.com. IN A: Conflicts with pre-existing RRset"
+            })))
+            .mount(&server)
+            .await;
+
+        let changes = vec![RRsetChange::new("www.example.com.".to_string(), "A".to_string(), 300, &["192.0.2.9".to_string()], &[])];
+
+        assert_eq!(
+            client(&server).patch_rrsets(&changes).await.unwrap_err().message,
+            "Failed to change record sets, PowerDNS responded with RRset www.example.com. IN A: Conflicts with pre-existing RRset"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_zone_id_of_subdomain() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/servers/localhost/zones"))
+            .and(query_param("zone", "home.example.com."))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/servers/localhost/zones"))
+            .and(query_param("zone", "example.com."))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "id": "example.com.", "name": "example.com.", "kind": "Native", "url": "/api/v1/servers/localhost/zones/example.com." }
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = client(&server).with_zone_id(String::new());
+
+        assert_eq!(client.find_zone_id("home.example.com").await.unwrap(), "example.com.");
+    }
+}
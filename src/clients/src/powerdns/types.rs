@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+/// A zone as listed, without its record sets.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneSummary {
+    pub id: String,
+    pub name: String,
+}
+
+/// A zone with its record sets.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub rrsets: Vec<RRset>,
+}
+
+/// A record set, every record of a name and type. Names are absolute with a trailing dot.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RRset {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub ttl: u64,
+    pub records: Vec<RRsetRecord>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RRsetRecord {
+    pub content: String,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChangeType {
+    /// Replace every record of the record set, creating it when it does not exist.
+    Replace,
+    /// Delete the record set.
+    Delete,
+}
+
+impl ChangeType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeType::Replace => "REPLACE",
+            ChangeType::Delete => "DELETE",
+        }
+    }
+}
+
+/// A record set change of a PATCH request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RRsetChange {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub changetype: ChangeType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<RRsetRecord>,
+}
+
+impl RRsetChange {
+    /// Replace the record set with the values and the disabled values, or delete it when there
+    /// are none. Disabled records have to be sent along, a replacement drops any record it lacks.
+    pub fn new(name: String, type_field: String, ttl: u64, values: &[String], disabled: &[String]) -> Self {
+        let records: Vec<RRsetRecord> = values.iter()
+            .map(|value| RRsetRecord { content: value.clone(), disabled: false })
+            .chain(disabled.iter().map(|value| RRsetRecord { content: value.clone(), disabled: true }))
+            .collect();
+
+        if records.is_empty() {
+            return Self {
+                name,
+                type_field,
+                changetype: ChangeType::Delete,
+                ttl: None,
+                records,
+            };
+        }
+
+        Self {
+            name,
+            type_field,
+            changetype: ChangeType::Replace,
+            ttl: Some(ttl),
+            records,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchRequest {
+    pub rrsets: Vec<RRsetChange>,
+}
+
+/// Body of every error response.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
@@ -0,0 +1,488 @@
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use hickory_proto::op::{update_message, Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{DNSClass, Name, RData, Record as DnsRecord, RecordSet, RecordType};
+use hickory_proto::rr::dnssec::rdata::tsig::TsigAlgorithm as HickoryTsigAlgorithm;
+use hickory_proto::rr::dnssec::tsig::TSigner;
+use hickory_proto::rr::rdata::{A, AAAA};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::rfc2136::error::Error;
+use crate::rfc2136::types::{Record, TsigAlgorithm, TsigKey, Update};
+
+pub mod error;
+pub mod types;
+
+/// Allowed difference in seconds between the clocks of the client and the server, the value
+/// recommended by RFC 8945.
+const TSIG_FUDGE: u16 = 300;
+
+/// Client for a primary name server accepting RFC 2136 dynamic updates. Every message is sent
+/// over TCP and signed with the TSIG key when one is set.
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub(crate) server: String,
+    pub(crate) zone: String,
+    pub(crate) tsig_key: Option<TsigKey>,
+    pub(crate) default_ttl: u32,
+    pub(crate) timeout: Duration,
+}
+
+impl Client {
+    pub fn new(server: String, zone: String, tsig_key: Option<TsigKey>, default_ttl: u32, timeout: Duration) -> Self {
+        Self {
+            server,
+            zone,
+            tsig_key,
+            default_ttl,
+            timeout,
+        }
+    }
+
+    /// Use the given zone instead of the one the client was created with.
+    pub fn with_zone(mut self, zone: String) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// TTL of records that do not set their own.
+    pub fn default_ttl(&self) -> u32 {
+        self.default_ttl
+    }
+
+    /// Find the zone the server is authoritative for that contains the domain, from the SOA
+    /// record the server answers a SOA query for the domain with.
+    ///
+    /// # Errors
+    /// When the server is not authoritative for the domain.
+    pub async fn find_zone(&self, domain: &str) -> Result<String, Error> {
+        let name = fqdn(domain)?;
+
+        let mut message = Message::new();
+        message
+            .set_id(rand::random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(false);
+        message.add_query(Query::query(name, RecordType::SOA));
+
+        let response = self.exchange(message, false).await?.remove(0);
+
+        let soa = response.answers().iter()
+            .chain(response.name_servers())
+            .find(|record| record.record_type() == RecordType::SOA);
+
+        match (response.response_code(), soa) {
+            (ResponseCode::NoError | ResponseCode::NXDomain, Some(soa)) => Ok(plain_name(soa.name())),
+            (response_code, _) => Err(Error::new(format!(
+                "DNS server {} is not authoritative for {} ({}), set RFC2136_ZONE",
+                self.server,
+                domain,
+                response_code
+            ))),
+        }
+    }
+
+    /// Transfer the zone with AXFR and return its A and AAAA records.
+    pub async fn transfer_zone(&self) -> Result<Vec<Record>, Error> {
+        let message = update_message::zone_transfer(fqdn(&self.zone)?, None);
+
+        let responses = self.exchange(message, true).await?;
+
+        let mut records: Vec<Record> = vec![];
+        for response in responses {
+            if response.response_code() != ResponseCode::NoError {
+                return Err(Error::new(format!(
+                    "Zone transfer of {} from {} failed: {}, the server must allow transfers to the TSIG key",
+                    self.zone,
+                    self.server,
+                    response.response_code()
+                )));
+            }
+
+            records.extend(response.answers().iter().filter_map(|record| {
+                let value = match record.data()? {
+                    RData::A(address) => address.0.to_string(),
+                    RData::AAAA(address) => address.0.to_string(),
+                    _ => return None,
+                };
+
+                Some(Record {
+                    name: plain_name(record.name()),
+                    type_field: record.record_type().to_string(),
+                    ttl: record.ttl(),
+                    value,
+                })
+            }));
+        }
+
+        Ok(records)
+    }
+
+    /// Replace the record set of a name and type in a single UPDATE message, with the current
+    /// record set as prerequisite so a record changed by someone else since it was read is
+    /// never overwritten.
+    ///
+    /// # Errors
+    /// When the record set no longer holds the current values, or the server refuses the update.
+    pub async fn update(&self, update: &Update) -> Result<(), Error> {
+        let zone = fqdn(&self.zone)?;
+        let name = fqdn(&update.name)?;
+
+        if !zone.zone_of(&name) {
+            return Err(Error::new(format!("Record {} is outside of zone {}", update.name, self.zone)));
+        }
+
+        let record_type = RecordType::from_str(&update.type_field).map_err(|error| {
+            Error::new(format!("Unsupported record type {}: {}", update.type_field, error))
+        })?;
+
+        let current = record_set(&name, record_type, 0, &update.current)?;
+        let desired = record_set(&name, record_type, update.ttl, &update.desired)?;
+
+        let message = match (update.current.is_empty(), update.desired.is_empty()) {
+            (true, true) => return Ok(()),
+            (true, false) => update_message::create(desired, zone, false),
+            // The desired record set is empty when deleting, nothing is added after the delete.
+            (false, _) => update_message::compare_and_swap(current, desired, zone, false),
+        };
+
+        let response = self.exchange(message, false).await?.remove(0);
+
+        match response.response_code() {
+            ResponseCode::NoError => Ok(()),
+            ResponseCode::YXRRSet | ResponseCode::NXRRSet => Err(Error::new(format!(
+                "{} record set {} changed since it was read, the update was not applied",
+                update.type_field,
+                update.name
+            ))),
+            response_code => Err(Error::new(format!(
+                "DNS server {} refused the update of {} record set {}: {}",
+                self.server,
+                update.type_field,
+                update.name,
+                response_code
+            ))),
+        }
+    }
+
+    /// Sign and send the message, and read the responses. A zone transfer is read until the
+    /// closing SOA record, any other message has a single response.
+    async fn exchange(&self, mut message: Message, transfer: bool) -> Result<Vec<Message>, Error> {
+        let mut verifier = match &self.tsig_key {
+            Some(key) => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default();
+
+                message.finalize(&signer(key)?, now as u32).map_err(|error| {
+                    Error::new(format!("Failed to sign DNS message: {}", error))
+                })?
+            },
+            None => None,
+        };
+
+        let id = message.id();
+        let request = message.to_vec().map_err(|error| {
+            Error::new(format!("Failed to encode DNS message: {}", error))
+        })?;
+
+        let exchange = async {
+            let mut stream = TcpStream::connect(&self.server).await?;
+            stream.write_all(&(request.len() as u16).to_be_bytes()).await?;
+            stream.write_all(&request).await?;
+
+            let mut responses: Vec<Vec<u8>> = vec![];
+            let mut soa_records: usize = 0;
+            loop {
+                let mut length = [0u8; 2];
+                stream.read_exact(&mut length).await?;
+                let mut response = vec![0u8; u16::from_be_bytes(length) as usize];
+                stream.read_exact(&mut response).await?;
+
+                let (answers, complete) = match Message::from_vec(&response) {
+                    Ok(parsed) => (
+                        parsed.answers().iter().filter(|record| record.record_type() == RecordType::SOA).count(),
+                        parsed.response_code() != ResponseCode::NoError,
+                    ),
+                    Err(_) => (0, true),
+                };
+                soa_records += answers;
+                responses.push(response);
+
+                if !transfer || complete || soa_records >= 2 {
+                    return Ok::<_, std::io::Error>(responses);
+                }
+            }
+        };
+
+        let responses = tokio::time::timeout(self.timeout, exchange).await
+            .map_err(|_| Error::new(format!("DNS server {} did not answer within {} seconds", self.server, self.timeout.as_secs())))?
+            .map_err(|error| Error::new(format!("Failed to reach DNS server {}: {}", self.server, error)))?;
+
+        responses.iter()
+            .map(|response| {
+                let parsed = Message::from_vec(response).map_err(|error| {
+                    Error::new(format!("Invalid response from DNS server {}: {}", self.server, error))
+                })?;
+
+                if parsed.id() != id {
+                    return Err(Error::new(format!("DNS server {} answered a different message", self.server)));
+                }
+
+                let Some(verify) = verifier.as_mut() else {
+                    return Ok(parsed);
+                };
+
+                // Errors such as an unknown key are answered without a signature.
+                verify(response).map(|response| response.into_message()).map_err(|error| match parsed.response_code() {
+                    ResponseCode::NoError => Error::new(format!("Invalid TSIG signature of the response from {}: {}", self.server, error)),
+                    response_code => Error::new(format!("DNS server {} rejected the TSIG key: {}", self.server, response_code)),
+                })
+            })
+            .collect()
+    }
+}
+
+fn signer(key: &TsigKey) -> Result<TSigner, Error> {
+    let algorithm = match key.algorithm {
+        TsigAlgorithm::HmacSha256 => HickoryTsigAlgorithm::HmacSha256,
+        TsigAlgorithm::HmacSha512 => HickoryTsigAlgorithm::HmacSha512,
+    };
+
+    TSigner::new(key.secret.clone(), algorithm, fqdn(&key.name)?, TSIG_FUDGE).map_err(|error| {
+        Error::new(format!("Invalid TSIG key {}: {}", key.name, error))
+    })
+}
+
+/// The fully qualified name, with the trailing dot.
+fn fqdn(name: &str) -> Result<Name, Error> {
+    Name::from_ascii(format!("{}.", name.trim_end_matches('.'))).map_err(|error| {
+        Error::new(format!("Invalid domain name {}: {}", name, error))
+    })
+}
+
+/// The name in lower case without the trailing dot.
+fn plain_name(name: &Name) -> String {
+    name.to_lowercase().to_ascii().trim_end_matches('.').to_string()
+}
+
+fn record_set(name: &Name, record_type: RecordType, ttl: u32, values: &[String]) -> Result<RecordSet, Error> {
+    let mut record_set = RecordSet::with_ttl(name.clone(), record_type, ttl);
+
+    for value in values {
+        let rdata = match record_type {
+            RecordType::A => value.parse().map(|address| RData::A(A(address))).ok(),
+            RecordType::AAAA => value.parse().map(|address| RData::AAAA(AAAA(address))).ok(),
+            _ => None,
+        };
+        let rdata = rdata.ok_or_else(|| Error::new(format!("Invalid {} record value {}", record_type, value)))?;
+
+        record_set.insert(DnsRecord::from_rdata(name.clone(), ttl, rdata).set_dns_class(DNSClass::IN).clone(), 0);
+    }
+
+    Ok(record_set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use hickory_proto::rr::dnssec::rdata::tsig::{make_tsig_record, message_tbs, TSIG};
+    use hickory_proto::rr::rdata::SOA;
+    use tokio::net::TcpListener;
+
+    const KEY_NAME: &str = "ddns-key";
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn key() -> TsigKey {
+        TsigKey::new(KEY_NAME.to_string(), TsigAlgorithm::HmacSha256, SECRET.to_vec())
+    }
+
+    fn soa(zone: &str) -> DnsRecord {
+        let zone = fqdn(zone).unwrap();
+        let rdata = SOA::new(fqdn("ns1.example.com").unwrap(), fqdn("hostmaster.example.com").unwrap(), 1, 3600, 600, 86400, 300);
+
+        DnsRecord::from_rdata(zone, 300, RData::SOA(rdata))
+    }
+
+    fn address_record(name: &str, value: &str) -> DnsRecord {
+        let rdata = match value.parse().unwrap() {
+            std::net::IpAddr::V4(address) => RData::A(A(address)),
+            std::net::IpAddr::V6(address) => RData::AAAA(AAAA(address)),
+        };
+
+        DnsRecord::from_rdata(fqdn(name).unwrap(), 300, rdata)
+    }
+
+    /// A name server answering every request with the response built by `respond`, signed with
+    /// the test key. Requests without a valid signature are answered with NOTAUTH. The
+    /// received requests are kept for the assertions.
+    async fn server<F>(respond: F) -> (String, Arc<Mutex<Vec<Message>>>)
+    where
+        F: Fn(&Message) -> Message + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let requests: Arc<Mutex<Vec<Message>>> = Arc::new(Mutex::new(vec![]));
+        let received = requests.clone();
+
+        tokio::spawn(async move {
+            let signer = signer(&key()).unwrap();
+
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+
+                let mut length = [0u8; 2];
+                stream.read_exact(&mut length).await.unwrap();
+                let mut request = vec![0u8; u16::from_be_bytes(length) as usize];
+                stream.read_exact(&mut request).await.unwrap();
+
+                let message = Message::from_vec(&request).unwrap();
+                received.lock().unwrap().push(message.clone());
+
+                let response = match signer.verify_message_byte(None, &request, true) {
+                    Ok((request_mac, _, time)) => {
+                        let mut response = respond(&message);
+                        response.set_id(message.id()).set_message_type(MessageType::Response).set_op_code(message.op_code());
+
+                        let pre_tsig = TSIG::new(signer.algorithm().clone(), time, TSIG_FUDGE, vec![], message.id(), 0, vec![]);
+                        // The request MAC is prepended separately, encoding it in the same buffer
+                        // would shift the offsets of compressed names.
+                        let mut tbs = (request_mac.len() as u16).to_be_bytes().to_vec();
+                        tbs.extend(&request_mac);
+                        tbs.extend(message_tbs(None, &response, &pre_tsig, signer.signer_name()).unwrap());
+                        let mac = signer.sign(&tbs).unwrap();
+                        response.add_tsig(make_tsig_record(signer.signer_name().clone(), pre_tsig.set_mac(mac)));
+                        response
+                    },
+                    Err(_) => Message::error_msg(message.id(), message.op_code(), ResponseCode::NotAuth),
+                };
+
+                let bytes = response.to_vec().unwrap();
+                stream.write_all(&(bytes.len() as u16).to_be_bytes()).await.unwrap();
+                stream.write_all(&bytes).await.unwrap();
+            }
+        });
+
+        (address, requests)
+    }
+
+    fn client(server: String, tsig_key: Option<TsigKey>) -> Client {
+        Client::new(server, "example.com".to_string(), tsig_key, 300, Duration::from_secs(5))
+    }
+
+    #[tokio::test]
+    async fn test_transfer_zone() {
+        let (address, _) = server(|_| {
+            let mut response = Message::new();
+            response.add_answers(vec![
+                soa("example.com"),
+                address_record("www.example.com", "192.0.2.1"),
+                address_record("*.dev.example.com", "2001:db8::1"),
+                soa("example.com"),
+            ]);
+            response
+        }).await;
+
+        let records = client(address, Some(key())).transfer_zone().await.unwrap();
+
+        assert_eq!(records, vec![
+            Record {
+                name: "www.example.com".to_string(),
+                type_field: "A".to_string(),
+                ttl: 300,
+                value: "192.0.2.1".to_string(),
+            },
+            Record {
+                name: "*.dev.example.com".to_string(),
+                type_field: "AAAA".to_string(),
+                ttl: 300,
+                value: "2001:db8::1".to_string(),
+            },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_update_sends_prerequisites() {
+        let (address, requests) = server(|_| Message::new()).await;
+        let client = client(address, Some(key()));
+
+        client.update(&Update {
+            name: "www.example.com".to_string(),
+            type_field: "A".to_string(),
+            ttl: 60,
+            current: vec!["192.0.2.1".to_string()],
+            desired: vec!["192.0.2.9".to_string()],
+        }).await.unwrap();
+
+        client.update(&Update {
+            name: "new.example.com".to_string(),
+            type_field: "AAAA".to_string(),
+            ttl: 60,
+            current: vec![],
+            desired: vec!["2001:db8::9".to_string()],
+        }).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+
+        let swap = &requests[0];
+        assert_eq!(swap.op_code(), OpCode::Update);
+        assert_eq!(swap.queries()[0].name(), &fqdn("example.com").unwrap());
+        // The current record set must still exist with exactly these values.
+        assert_eq!(swap.answers(), &[address_record("www.example.com", "192.0.2.1").set_ttl(0).clone()]);
+        assert_eq!(swap.name_servers().iter().map(|record| (record.dns_class(), record.ttl())).collect::<Vec<_>>(), vec![(DNSClass::NONE, 0), (DNSClass::IN, 60)]);
+        assert_eq!(swap.signature().len(), 1);
+
+        let create = &requests[1];
+        // The name must not have a record of the type yet.
+        assert_eq!(create.answers()[0].dns_class(), DNSClass::NONE);
+        assert_eq!(create.answers()[0].data(), None);
+        assert_eq!(create.name_servers()[0].data(), Some(&RData::AAAA(AAAA("2001:db8::9".parse().unwrap()))));
+    }
+
+    #[tokio::test]
+    async fn test_update_reports_changed_record_set() {
+        let (address, _) = server(|_| {
+            let mut response = Message::new();
+            response.set_response_code(ResponseCode::NXRRSet);
+            response
+        }).await;
+
+        let error = client(address, Some(key())).update(&Update {
+            name: "www.example.com".to_string(),
+            type_field: "A".to_string(),
+            ttl: 60,
+            current: vec!["192.0.2.1".to_string()],
+            desired: vec![],
+        }).await.unwrap_err();
+
+        assert_eq!(error.message, "A record set www.example.com changed since it was read, the update was not applied");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_key_is_rejected() {
+        let (address, _) = server(|_| Message::new()).await;
+        let wrong_key = TsigKey::new(KEY_NAME.to_string(), TsigAlgorithm::HmacSha256, b"wrong".to_vec());
+
+        let error = client(address.clone(), Some(wrong_key)).transfer_zone().await.unwrap_err();
+
+        assert_eq!(error.message, format!("DNS server {} rejected the TSIG key: Not authorized", address));
+    }
+
+    #[tokio::test]
+    async fn test_find_zone() {
+        let (address, _) = server(|request| {
+            let mut response = Message::new();
+            if request.queries()[0].name() == &fqdn("example.com").unwrap() {
+                response.add_answer(soa("example.com"));
+            } else {
+                response.add_name_server(soa("example.com"));
+            }
+            response
+        }).await;
+
+        let client = client(address, Some(key()));
+
+        assert_eq!(client.find_zone("Home.example.com").await.unwrap(), "example.com");
+        assert_eq!(client.find_zone("example.com.").await.unwrap(), "example.com");
+    }
+}
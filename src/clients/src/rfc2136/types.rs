@@ -0,0 +1,56 @@
+/// An address record of the zone. Names are fully qualified without the trailing dot.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub type_field: String,
+    pub ttl: u32,
+    pub value: String,
+}
+
+/// The replacement of the record set of a name and type. The update only applies while the
+/// record set still holds exactly the current values, an empty current record set requires
+/// the name to have no record of the type.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Update {
+    pub name: String,
+    pub type_field: String,
+    /// TTL of the desired record set.
+    pub ttl: u32,
+    pub current: Vec<String>,
+    pub desired: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsigAlgorithm {
+    HmacSha256,
+    HmacSha512,
+}
+
+/// The TSIG key every message is signed with, it must be known to the server under the
+/// same name.
+#[derive(Clone, PartialEq)]
+pub struct TsigKey {
+    pub name: String,
+    pub algorithm: TsigAlgorithm,
+    pub secret: Vec<u8>,
+}
+
+impl TsigKey {
+    pub fn new(name: String, algorithm: TsigAlgorithm, secret: Vec<u8>) -> Self {
+        Self {
+            name,
+            algorithm,
+            secret,
+        }
+    }
+}
+
+impl std::fmt::Debug for TsigKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TsigKey")
+            .field("name", &self.name)
+            .field("algorithm", &self.algorithm)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
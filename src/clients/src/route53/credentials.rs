@@ -0,0 +1,120 @@
+use std::path::Path;
+use crate::route53::error::Error;
+
+/// AWS credentials used to sign the Route 53 requests.
+#[derive(Clone, PartialEq)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Token of temporary credentials, e.g. from an assumed role.
+    pub session_token: Option<String>,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    pub fn new(access_key_id: String, secret_access_key: String, session_token: Option<String>) -> Self {
+        Self {
+            access_key_id,
+            secret_access_key,
+            session_token,
+        }
+    }
+
+    /// Read the credentials of a profile from a shared credentials file, the
+    /// `~/.aws/credentials` file written by the AWS CLI.
+    pub fn from_profile(path: &Path, profile: &str) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|error| {
+            Error::new(format!("Failed to read AWS credentials file {}: {}", path.display(), error))
+        })?;
+
+        Self::parse_profile(&contents, profile).map_err(|error| {
+            Error::new(format!("Invalid AWS credentials file {}: {}", path.display(), error))
+        })
+    }
+
+    fn parse_profile(contents: &str, profile: &str) -> Result<Self, String> {
+        let mut section: Option<&str> = None;
+        let mut found = false;
+        let mut access_key_id: Option<String> = None;
+        let mut secret_access_key: Option<String> = None;
+        let mut session_token: Option<String> = None;
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                section = Some(name.trim());
+                found |= section == Some(profile);
+                continue;
+            }
+
+            if section != Some(profile) {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                let value = Some(value.trim().to_string());
+
+                match key.trim() {
+                    "aws_access_key_id" => access_key_id = value,
+                    "aws_secret_access_key" => secret_access_key = value,
+                    "aws_session_token" => session_token = value,
+                    _ => {},
+                }
+            }
+        }
+
+        if !found {
+            return Err(format!("profile {} not found", profile));
+        }
+
+        match (access_key_id, secret_access_key) {
+            (Some(access_key_id), Some(secret_access_key)) => Ok(Self::new(access_key_id, secret_access_key, session_token)),
+            _ => Err(format!("profile {} has no aws_access_key_id and aws_secret_access_key", profile)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CREDENTIALS: &str = "
+[default]
+aws_access_key_id = AKIDDEFAULT
+aws_secret_access_key = default-secret
+
+# Temporary credentials
+[dns]
+aws_access_key_id=AKIDDNS
+aws_secret_access_key=dns-secret
+aws_session_token=token
+";
+
+    #[test]
+    fn test_parse_profile() {
+        let default = Credentials::parse_profile(CREDENTIALS, "default").unwrap();
+        assert_eq!(default, Credentials::new("AKIDDEFAULT".to_string(), "default-secret".to_string(), None));
+
+        let dns = Credentials::parse_profile(CREDENTIALS, "dns").unwrap();
+        assert_eq!(dns.session_token.as_deref(), Some("token"));
+
+        assert_eq!(Credentials::parse_profile(CREDENTIALS, "prod").unwrap_err(), "profile prod not found");
+    }
+
+    #[test]
+    fn test_debug_hides_secret() {
+        let credentials = Credentials::new("AKID".to_string(), "secret".to_string(), Some("token".to_string()));
+
+        assert!(!format!("{:?}", credentials).contains("secret"));
+    }
+}
@@ -0,0 +1,481 @@
+use std::time::SystemTime;
+use quick_xml::escape::escape;
+use reqwest::{StatusCode, Url};
+use crate::http::HttpClient;
+use crate::route53::credentials::Credentials;
+use crate::route53::error::Error;
+use crate::route53::types::{Change, ErrorResponse, ListHostedZonesByNameResponse, ListResourceRecordSetsResponse, RecordSet};
+
+pub mod credentials;
+pub mod error;
+mod sigv4;
+pub mod types;
+
+/// Route 53 is a global service, its requests are always signed for this region.
+const SIGNING_REGION: &str = "us-east-1";
+
+const SIGNING_SERVICE: &str = "route53";
+
+const API_VERSION: &str = "2013-04-01";
+
+/// Page size requested when listing record sets, the maximum Route 53 allows.
+const RECORD_SETS_PER_PAGE: &str = "300";
+
+/// Number of changes sent in a single `ChangeResourceRecordSets` request. Route 53 allows up
+/// to 1000 values per request, keeping batches smaller leaves room for multi value sets.
+pub const MAX_CHANGES_PER_BATCH: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub(crate) api_url: String,
+    pub(crate) hosted_zone_id: String,
+    pub(crate) credentials: Credentials,
+    pub(crate) default_ttl: u64,
+    pub(crate) http: HttpClient,
+}
+
+impl Client {
+    pub fn new(api_url: String, hosted_zone_id: String, credentials: Credentials, default_ttl: u64) -> Self {
+        Self {
+            api_url,
+            hosted_zone_id,
+            credentials,
+            default_ttl,
+            http: HttpClient::default(),
+        }
+    }
+
+    /// Use the shared HTTP client instead of a dedicated default one.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Use the given hosted zone instead of the one the client was created with.
+    pub fn with_hosted_zone_id(mut self, hosted_zone_id: String) -> Self {
+        self.hosted_zone_id = hosted_zone_id;
+        self
+    }
+
+    /// TTL of records that do not set their own, Route 53 requires one on every record.
+    pub fn default_ttl(&self) -> u64 {
+        self.default_ttl
+    }
+
+    /// Find the id of the public hosted zone serving the domain, trying the parent domains in
+    /// turn when the domain is below the zone apex.
+    ///
+    /// # Errors
+    /// When no public hosted zone serves the domain, or several public zones share its name.
+    pub async fn find_hosted_zone_id(&self, domain: &str) -> Result<String, Error> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let mut name = domain.as_str();
+
+        loop {
+            let url = self.url("hostedzonesbyname", &[("dnsname", name), ("maxitems", "10")])?;
+            let body = self.send("GET", url, String::new()).await?;
+
+            let response: ListHostedZonesByNameResponse = quick_xml::de::from_str(&body).map_err(|error| {
+                tracing::error!("Failed to parse response body: {}", error);
+                Error::new("Failed to parse response body".to_string())
+            })?;
+
+            let zones: Vec<String> = response.hosted_zones.hosted_zone.into_iter()
+                .filter(|zone| zone.name.trim_end_matches('.').eq_ignore_ascii_case(name))
+                .filter(|zone| !zone.config.as_ref().is_some_and(|config| config.private_zone))
+                .map(|zone| zone.id.trim_start_matches("/hostedzone/").to_string())
+                .collect();
+
+            match zones.as_slice() {
+                [] => {},
+                [zone_id] => return Ok(zone_id.clone()),
+                _ => {
+                    return Err(Error::new(format!(
+                        "{} public Route 53 hosted zones are named {}, set ROUTE53_HOSTED_ZONE_ID to pick one",
+                        zones.len(),
+                        name
+                    )));
+                }
+            }
+
+            match name.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => name = parent,
+                _ => {
+                    return Err(Error::new(format!(
+                        "No public Route 53 hosted zone for {} is visible to the credentials, grant them route53:ListHostedZonesByName or set ROUTE53_HOSTED_ZONE_ID",
+                        domain
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Fetch every simple A and AAAA record set of the zone, following the pagination until
+    /// the last page. Alias records and records with a routing policy are left out.
+    pub async fn get_record_sets(&self) -> Result<Vec<RecordSet>, Error> {
+        let mut record_sets: Vec<RecordSet> = vec![];
+        let mut start: Option<(String, String, Option<String>)> = None;
+
+        loop {
+            let response = self.get_record_sets_page(start.as_ref(), RECORD_SETS_PER_PAGE).await?;
+
+            record_sets.extend(simple_record_sets(&response));
+
+            match (response.is_truncated, response.next_record_name, response.next_record_type) {
+                (true, Some(name), Some(type_field)) => {
+                    start = Some((name, type_field, response.next_record_identifier));
+                },
+                _ => break,
+            }
+        }
+
+        Ok(record_sets)
+    }
+
+    /// Fetch the simple record set of a name and type, if there is one.
+    pub async fn get_record_set(&self, name: &str, type_field: &str) -> Result<Option<RecordSet>, Error> {
+        let start = (name.to_string(), type_field.to_string(), None);
+        let response = self.get_record_sets_page(Some(&start), "1").await?;
+
+        Ok(simple_record_sets(&response).into_iter().find(|record_set| {
+            record_set.name.eq_ignore_ascii_case(name.trim_end_matches('.')) && record_set.type_field == type_field
+        }))
+    }
+
+    async fn get_record_sets_page(
+        &self,
+        start: Option<&(String, String, Option<String>)>,
+        max_items: &str
+    ) -> Result<ListResourceRecordSetsResponse, Error> {
+        let mut query: Vec<(&str, &str)> = vec![("maxitems", max_items)];
+        if let Some((name, type_field, identifier)) = start {
+            query.push(("name", name));
+            query.push(("type", type_field));
+            if let Some(identifier) = identifier {
+                query.push(("identifier", identifier));
+            }
+        }
+
+        let url = self.url(&format!("hostedzone/{}/rrset", self.hosted_zone_id), &query)?;
+        let body = self.send("GET", url, String::new()).await?;
+
+        quick_xml::de::from_str(&body).map_err(|error| {
+            tracing::error!("Failed to parse response body: {}", error);
+            Error::new("Failed to parse response body".to_string())
+        })
+    }
+
+    /// Apply the changes as a single batch, Route 53 applies all of them or none.
+    pub async fn change_record_sets(&self, changes: &[Change]) -> Result<(), Error> {
+        let url = self.url(&format!("hostedzone/{}/rrset", self.hosted_zone_id), &[])?;
+
+        self.send("POST", url, change_batch(changes)).await?;
+
+        Ok(())
+    }
+
+    fn url(&self, path: &str, query: &[(&str, &str)]) -> Result<Url, Error> {
+        let mut url = Url::parse(&format!("{}/{}/{}", self.api_url.trim_end_matches('/'), API_VERSION, path)).map_err(|error| {
+            Error::new(format!("Invalid Route 53 API URL {}: {}", self.api_url, error))
+        })?;
+
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        Ok(url)
+    }
+
+    /// Sign and send the request, returning the response body.
+    async fn send(&self, method: &str, url: Url, body: String) -> Result<String, Error> {
+        let headers = sigv4::sign(&self.credentials, SIGNING_REGION, SIGNING_SERVICE, method, &url, body.as_bytes(), SystemTime::now());
+
+        let mut request_builder = match method {
+            "POST" => self.http.post(url.clone()).header("Content-Type", "application/xml").body(body),
+            _ => self.http.get(url.clone()),
+        };
+        for (name, value) in headers {
+            request_builder = request_builder.header(name, value);
+        }
+
+        let response = self.http.send(request_builder).await.map_err(|error| {
+            tracing::error!("Failed to send Route 53 request {} {}: {}", method, url.path(), error);
+            Error::new(format!("Failed to send Route 53 request {} {}", method, url.path()))
+        })?;
+
+        let status: StatusCode = response.status();
+        let body: String = response.text().await.unwrap_or_else(|_| "Empty response body".to_string());
+
+        tracing::debug!("Received response {}, {}", status, body);
+
+        if status.is_success() {
+            return Ok(body);
+        }
+
+        let reason = quick_xml::de::from_str::<ErrorResponse>(&body)
+            .map(|response| format!("{}: {}", response.error.code, response.error.message))
+            .unwrap_or_else(|_| status.to_string());
+
+        Err(Error::new(format!("Route 53 request {} {} failed, {}", method, url.path(), reason)))
+    }
+}
+
+/// The A and AAAA record sets with values, which leaves out alias records, and without a
+/// routing policy.
+fn simple_record_sets(response: &ListResourceRecordSetsResponse) -> Vec<RecordSet> {
+    response.resource_record_sets.resource_record_set.iter()
+        .filter(|record_set| matches!(record_set.type_field.as_str(), "A" | "AAAA") && record_set.set_identifier.is_none())
+        .filter_map(|record_set| {
+            let resource_records = record_set.resource_records.as_ref()?;
+
+            Some(RecordSet {
+                name: unescape_name(&record_set.name),
+                type_field: record_set.type_field.clone(),
+                ttl: record_set.ttl.unwrap_or_default(),
+                values: resource_records.resource_record.iter().map(|record| record.value.clone()).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Route 53 returns special characters as octal escapes, e.g. `\052` for the `*` of a wildcard.
+fn unescape_name(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut rest = name.trim_end_matches('.');
+
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        let escape = rest.get(index + 1..index + 4).and_then(|digits| u8::from_str_radix(digits, 8).ok());
+
+        match escape {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[index + 4..];
+            },
+            None => {
+                unescaped.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+
+    unescaped
+}
+
+fn change_batch(changes: &[Change]) -> String {
+    let changes: String = changes.iter()
+        .map(|change| {
+            let record_set = &change.record_set;
+            let values: String = record_set.values.iter()
+                .map(|value| format!("<ResourceRecord><Value>{}</Value></ResourceRecord>", escape(value)))
+                .collect();
+
+            format!(
+                "<Change><Action>{}</Action><ResourceRecordSet><Name>{}.</Name><Type>{}</Type><TTL>{}</TTL><ResourceRecords>{}</ResourceRecords></ResourceRecordSet></Change>",
+                change.action.as_str(),
+                escape(&record_set.name),
+                escape(&record_set.type_field),
+                record_set.ttl,
+                values
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><ChangeResourceRecordSetsRequest xmlns="https://route53.amazonaws.com/doc/{}/"><ChangeBatch><Comment>dynamic-dns</Comment><Changes>{}</Changes></ChangeBatch></ChangeResourceRecordSetsRequest>"#,
+        API_VERSION,
+        changes
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, header_exists, header_regex, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use crate::route53::types::ChangeAction;
+
+    fn client(server: &MockServer) -> Client {
+        let credentials = Credentials::new("AKIDEXAMPLE".to_string(), "secret".to_string(), Some("token".to_string()));
+
+        Client::new(server.uri(), "Z1".to_string(), credentials, 300)
+    }
+
+    fn record_set(name: &str, type_field: &str, values: &[&str]) -> String {
+        let values: String = values.iter().map(|value| format!("<ResourceRecord><Value>{}</Value></ResourceRecord>", value)).collect();
+
+        format!("<ResourceRecordSet><Name>{}</Name><Type>{}</Type><TTL>300</TTL><ResourceRecords>{}</ResourceRecords></ResourceRecordSet>", name, type_field, values)
+    }
+
+    fn record_sets_body(record_sets: &[String], next: Option<(&str, &str)>) -> String {
+        let next = next
+            .map(|(name, type_field)| format!("<IsTruncated>true</IsTruncated><NextRecordName>{}</NextRecordName><NextRecordType>{}</NextRecordType>", name, type_field))
+            .unwrap_or_else(|| "<IsTruncated>false</IsTruncated>".to_string());
+
+        format!(
+            r#"<?xml version="1.0"?><ListResourceRecordSetsResponse xmlns="https://route53.amazonaws.com/doc/2013-04-01/"><ResourceRecordSets>{}</ResourceRecordSets>{}<MaxItems>300</MaxItems></ListResourceRecordSetsResponse>"#,
+            record_sets.concat(),
+            next
+        )
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_signed() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .and(header_regex("authorization", r"^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/\d{8}/us-east-1/route53/aws4_request, SignedHeaders=host;x-amz-date;x-amz-security-token, Signature=[0-9a-f]{64}$"))
+            .and(header_exists("x-amz-date"))
+            .and(header_regex("x-amz-security-token", "^token$"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(record_sets_body(&[], None)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert!(client(&server).get_record_sets().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_record_sets_follows_pagination() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .and(query_param("name", "www.example.com."))
+            .respond_with(ResponseTemplate::new(200).set_body_string(record_sets_body(&[
+                record_set("www.example.com.", "A", &["192.0.2.1"]),
+            ], None)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(record_sets_body(&[
+                record_set("example.com.", "MX", &["10 mail.example.com."]),
+                record_set("\\052.dev.example.com.", "AAAA", &["2001:db8::1", "2001:db8::2"]),
+            ], Some(("www.example.com.", "A")))))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let record_sets = client(&server).get_record_sets().await.unwrap();
+
+        assert_eq!(record_sets, vec![
+            RecordSet {
+                name: "*.dev.example.com".to_string(),
+                type_field: "AAAA".to_string(),
+                ttl: 300,
+                values: vec!["2001:db8::1".to_string(), "2001:db8::2".to_string()],
+            },
+            RecordSet {
+                name: "www.example.com".to_string(),
+                type_field: "A".to_string(),
+                ttl: 300,
+                values: vec!["192.0.2.1".to_string()],
+            },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_change_record_sets_sends_batch() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .and(header_exists("authorization"))
+            .and(body_string_contains("<Change><Action>UPSERT</Action><ResourceRecordSet><Name>www.example.com.</Name><Type>A</Type><TTL>60</TTL><ResourceRecords><ResourceRecord><Value>192.0.2.1</Value></ResourceRecord></ResourceRecords></ResourceRecordSet></Change>"))
+            .and(body_string_contains("<Change><Action>DELETE</Action><ResourceRecordSet><Name>old.example.com.</Name>"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<ChangeResourceRecordSetsResponse/>"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let changes = vec![
+            Change {
+                action: ChangeAction::Upsert,
+                record_set: RecordSet {
+                    name: "www.example.com".to_string(),
+                    type_field: "A".to_string(),
+                    ttl: 60,
+                    values: vec!["192.0.2.1".to_string()],
+                },
+            },
+            Change {
+                action: ChangeAction::Delete,
+                record_set: RecordSet {
+                    name: "old.example.com".to_string(),
+                    type_field: "A".to_string(),
+                    ttl: 300,
+                    values: vec!["192.0.2.9".to_string()],
+                },
+            },
+        ];
+
+        assert!(client(&server).change_record_sets(&changes).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_error_response_message() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_string(
+                "<ErrorResponse><Error><Type>Sender</Type><Code>InvalidChangeBatch</Code><Message>Tried to delete resource record set but it was not found</Message></Error></ErrorResponse>"
+            ))
+            .mount(&server)
+            .await;
+
+        let error = client(&server).change_record_sets(&[]).await.unwrap_err();
+
+        assert_eq!(error.message, "Route 53 request POST /2013-04-01/hostedzone/Z1/rrset failed, InvalidChangeBatch: Tried to delete resource record set but it was not found");
+    }
+
+    #[tokio::test]
+    async fn test_find_hosted_zone_id() {
+        let server = MockServer::start().await;
+
+        let zones_body = |zones: &str| format!("<ListHostedZonesByNameResponse><HostedZones>{}</HostedZones><IsTruncated>false</IsTruncated></ListHostedZonesByNameResponse>", zones);
+        let zone = |id: &str, name: &str, private_zone: bool| format!(
+            "<HostedZone><Id>/hostedzone/{}</Id><Name>{}</Name><Config><PrivateZone>{}</PrivateZone></Config></HostedZone>", id, name, private_zone
+        );
+
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzonesbyname"))
+            .and(query_param("dnsname", "home.example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(zones_body(&zone("Z9", "lab.example.com.", false))))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzonesbyname"))
+            .and(query_param("dnsname", "example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(zones_body(&[
+                zone("Z2", "example.com.", true),
+                zone("Z1", "example.com.", false),
+            ].concat())))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzonesbyname"))
+            .and(query_param("dnsname", "example.org"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(zones_body("")))
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+
+        assert_eq!(client.find_hosted_zone_id("Home.example.com.").await.unwrap(), "Z1");
+        assert!(client.find_hosted_zone_id("example.org").await.unwrap_err().message.starts_with("No public Route 53 hosted zone for example.org"));
+    }
+
+    #[test]
+    fn test_unescape_name() {
+        assert_eq!(unescape_name("\\052.dev.example.com."), "*.dev.example.com");
+        assert_eq!(unescape_name("www.example.com"), "www.example.com");
+        assert_eq!(unescape_name("a\\b.example.com."), "a\\b.example.com");
+    }
+}
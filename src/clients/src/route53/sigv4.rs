@@ -0,0 +1,192 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use reqwest::Url;
+use ring::{digest, hmac};
+use crate::route53::credentials::Credentials;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// Sign a request with AWS Signature Version 4.
+///
+/// # Arguments
+/// * `credentials` - The credentials to sign with.
+/// * `region` - The region of the signing scope, `us-east-1` for global services like Route 53.
+/// * `service` - The service of the signing scope.
+/// * `method` - The HTTP method of the request.
+/// * `url` - The full request URL, including the query.
+/// * `payload` - The request body.
+/// * `time` - The time of the request, the signature is valid for 5 minutes.
+///
+/// # Returns
+/// The headers to add to the request, including `Authorization`.
+pub(crate) fn sign(
+    credentials: &Credentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    url: &Url,
+    payload: &[u8],
+    time: SystemTime
+) -> Vec<(String, String)> {
+    let amz_date = amz_date(time);
+    let date = &amz_date[..8];
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let mut headers: Vec<(String, String)> = vec![
+        ("host".to_string(), host),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(session_token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), session_token.clone()));
+    }
+    headers.sort();
+
+    let canonical_headers: String = headers.iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers: String = headers.iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        uri_encode(url.path(), false),
+        canonical_query(url),
+        canonical_headers,
+        signed_headers,
+        hex(digest::digest(&digest::SHA256, payload).as_ref())
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        ALGORITHM,
+        amz_date,
+        scope,
+        hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+
+    let signing_key = [date, region, service, "aws4_request"].iter()
+        .fold(format!("AWS4{}", credentials.secret_access_key).into_bytes(), |key, part| hmac_sha256(&key, part.as_bytes()));
+    let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+    let authorization = format!(
+        "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+        ALGORITHM,
+        credentials.access_key_id,
+        scope,
+        signed_headers,
+        signature
+    );
+
+    // reqwest sets the host header itself.
+    headers.retain(|(name, _)| name != "host");
+    headers.push(("authorization".to_string(), authorization));
+
+    headers
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data).as_ref().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent encode everything but the unreserved characters, and `/` unless `encode_slash`.
+fn uri_encode(input: &str, encode_slash: bool) -> String {
+    input.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            b'/' if !encode_slash => "/".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// The query parameters encoded and sorted by name and value.
+fn canonical_query(url: &Url) -> String {
+    let mut parameters: Vec<(String, String)> = url.query_pairs()
+        .map(|(name, value)| (uri_encode(&name, true), uri_encode(&value, true)))
+        .collect();
+    parameters.sort();
+
+    parameters.iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// The time in the `YYYYMMDD'T'HHMMSS'Z'` format of the `x-amz-date` header.
+fn amz_date(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let seconds_of_day = seconds % 86400;
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// The proleptic Gregorian date of a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn credentials() -> Credentials {
+        Credentials::new("AKIDEXAMPLE".to_string(), "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(), None)
+    }
+
+    #[test]
+    fn test_amz_date() {
+        assert_eq!(amz_date(UNIX_EPOCH + Duration::from_secs(1440938160)), "20150830T123600Z");
+        assert_eq!(amz_date(UNIX_EPOCH + Duration::from_secs(951868799)), "20000229T235959Z");
+    }
+
+    /// The `get-vanilla` case of the AWS Signature Version 4 test suite.
+    #[test]
+    fn test_sign_get_vanilla() {
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+
+        let headers = sign(&credentials(), "us-east-1", "service", "GET", &url, b"", UNIX_EPOCH + Duration::from_secs(1440938160));
+
+        assert_eq!(headers, vec![
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+            ("authorization".to_string(), "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_canonical_query_is_sorted_and_encoded() {
+        let url = Url::parse("https://route53.amazonaws.com/rrset?type=A&name=%2A.example.com.&maxitems=1").unwrap();
+
+        assert_eq!(canonical_query(&url), "maxitems=1&name=%2A.example.com.&type=A");
+    }
+}
@@ -0,0 +1,120 @@
+use serde::Deserialize;
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListHostedZonesByNameResponse {
+    #[serde(default)]
+    pub hosted_zones: HostedZones,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct HostedZones {
+    #[serde(rename = "HostedZone", default)]
+    pub hosted_zone: Vec<HostedZone>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HostedZone {
+    /// The zone id prefixed with `/hostedzone/`.
+    pub id: String,
+    /// The zone name with the trailing dot.
+    pub name: String,
+    pub config: Option<HostedZoneConfig>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HostedZoneConfig {
+    #[serde(default)]
+    pub private_zone: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListResourceRecordSetsResponse {
+    #[serde(default)]
+    pub resource_record_sets: ResourceRecordSets,
+    #[serde(default)]
+    pub is_truncated: bool,
+    pub next_record_name: Option<String>,
+    pub next_record_type: Option<String>,
+    pub next_record_identifier: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct ResourceRecordSets {
+    #[serde(rename = "ResourceRecordSet", default)]
+    pub resource_record_set: Vec<ResourceRecordSet>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ResourceRecordSet {
+    pub name: String,
+    #[serde(rename = "Type")]
+    pub type_field: String,
+    #[serde(rename = "TTL")]
+    pub ttl: Option<u64>,
+    pub resource_records: Option<ResourceRecords>,
+    /// Set on records with a routing policy, such as weighted or latency records.
+    pub set_identifier: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct ResourceRecords {
+    #[serde(rename = "ResourceRecord", default)]
+    pub resource_record: Vec<ResourceRecord>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ResourceRecord {
+    pub value: String,
+}
+
+/// A simple record set, all the values of one name and type. Names are fully qualified
+/// without the trailing dot.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct RecordSet {
+    pub name: String,
+    pub type_field: String,
+    pub ttl: u64,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeAction {
+    Upsert,
+    Delete,
+}
+
+impl ChangeAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeAction::Upsert => "UPSERT",
+            ChangeAction::Delete => "DELETE",
+        }
+    }
+}
+
+/// A change of a `ChangeResourceRecordSets` batch. A delete must match the current record set
+/// exactly, including the TTL and every value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub action: ChangeAction,
+    pub record_set: RecordSet,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ErrorResponse {
+    pub error: ErrorDetail,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ErrorDetail {
+    pub code: String,
+    pub message: String,
+}
@@ -1,6 +1,9 @@
-use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
-use crate::StripQuotes;
+use base64::Engine;
+use crate::error::Error;
+use crate::variables::Variables;
 
 /// Struct representing the DNS provider configuration
 #[derive(Debug, Clone)]
@@ -9,37 +12,33 @@ pub struct DnsProvider {
 }
 
 /// Enum representing the DNS provider selection
-/// Currently only Cloudflare is supported but this can be extended to support other providers
 #[derive(Debug, Clone)]
 pub enum DnsProviderSelection {
     Cloudflare(CloudflareProviderSettings),
+    Route53(Route53ProviderSettings),
+    Rfc2136(Rfc2136ProviderSettings),
+    Dyndns2(Dyndns2ProviderSettings),
+    DigitalOcean(DigitalOceanProviderSettings),
+    Hetzner(HetznerProviderSettings),
+    PowerDns(PowerDnsProviderSettings),
 }
 
-/// Implement the FromStr trait for DnsProviderConfig, essentially based on the selected provider
-/// we will load the required environment variables, and the required environment variables are
-/// different for each provider.
-impl FromStr for DnsProviderSelection {
-    type Err = String;
-
-    fn from_str(raw_input: &str) -> Result<Self, Self::Err> {
+impl DnsProviderSelection {
+    /// Load the settings of the selected provider, the required variables are different for
+    /// each provider.
+    pub(crate) fn load(raw_input: &str, variables: &Variables) -> Result<Self, Error> {
         let binding = raw_input.to_lowercase();
         let input = binding.as_str();
 
         match input {
             "cloudflare" => {
                 tracing::debug!("Loading Cloudflare provider settings");
-                let api_url: String = env::var("CLOUDFLARE_API_URL")
-                    .unwrap_or("https://api.cloudflare.com/client/v4".to_string());
+                let api_url: String = variables.get_or("CLOUDFLARE_API_URL", "https://api.cloudflare.com/client/v4");
 
-                let zone_id: String = env::var("CLOUDFLARE_ZONE_ID").expect("CLOUDFLARE_ZONE_ID must be set").strip_quotes();
-                let api_key: String = env::var("CLOUDFLARE_API_KEY").expect("CLOUDFLARE_API_KEY must be set").strip_quotes();
+                let zone_id: Option<String> = variables.get("CLOUDFLARE_ZONE_ID");
+                let api_key: String = variables.require("CLOUDFLARE_API_KEY")?;
 
-                let proxy_enabled: bool = env::var("CLOUDFLARE_PROXY_ENABLED")
-                    .unwrap_or("false".to_string())
-                    .to_lowercase()
-                    .strip_quotes()
-                    .parse()
-                    .expect("CLOUDFLARE_PROXY_ENABLED must be a boolean");
+                let proxy_enabled: bool = variables.parse_bool_or("CLOUDFLARE_PROXY_ENABLED", false)?;
 
                 let settings = CloudflareProviderSettings::new(
                     zone_id,
@@ -50,8 +49,106 @@ impl FromStr for DnsProviderSelection {
 
                 Ok(DnsProviderSelection::Cloudflare(settings))
             },
+            "route53" => {
+                tracing::debug!("Loading Route 53 provider settings");
+                let api_url: String = variables.get_or("ROUTE53_API_URL", "https://route53.amazonaws.com");
+
+                let hosted_zone_id: Option<String> = variables.get("ROUTE53_HOSTED_ZONE_ID");
+                let ttl: u64 = variables.parse_or("ROUTE53_TTL", "300", "a number of seconds")?;
+
+                let settings = Route53ProviderSettings::new(
+                    api_url,
+                    hosted_zone_id,
+                    AwsCredentials::load(variables)?,
+                    ttl
+                );
+
+                Ok(DnsProviderSelection::Route53(settings))
+            },
+            "rfc2136" => {
+                tracing::debug!("Loading RFC 2136 provider settings");
+                let server: String = server_address(&variables.require("RFC2136_SERVER")?);
+
+                let zone: Option<String> = variables.get("RFC2136_ZONE");
+                let ttl: u32 = variables.parse_or("RFC2136_TTL", "300", "a number of seconds")?;
+                let timeout_seconds: u64 = variables.parse_or("RFC2136_TIMEOUT_SECONDS", "10", "a number of seconds")?;
+
+                let settings = Rfc2136ProviderSettings::new(
+                    server,
+                    zone,
+                    TsigKeySettings::load(variables)?,
+                    ttl,
+                    timeout_seconds
+                );
+
+                Ok(DnsProviderSelection::Rfc2136(settings))
+            },
+            "dyndns2" => {
+                tracing::debug!("Loading dyndns2 provider settings");
+                let url: String = variables.require("DYNDNS2_URL")?;
+
+                let username: String = variables.require("DYNDNS2_USERNAME")?;
+                let password: String = variables.require("DYNDNS2_PASSWORD")?;
+
+                let settings = Dyndns2ProviderSettings::new(
+                    url,
+                    username,
+                    password
+                );
+
+                Ok(DnsProviderSelection::Dyndns2(settings))
+            },
+            "digitalocean" => {
+                tracing::debug!("Loading DigitalOcean provider settings");
+                let api_url: String = variables.get_or("DIGITALOCEAN_API_URL", "https://api.digitalocean.com/v2");
+
+                let zone: Option<String> = variables.get("DIGITALOCEAN_ZONE");
+                let api_token: String = variables.require("DIGITALOCEAN_API_TOKEN")?;
+
+                let settings = DigitalOceanProviderSettings::new(
+                    zone,
+                    api_url,
+                    api_token
+                );
+
+                Ok(DnsProviderSelection::DigitalOcean(settings))
+            },
+            "hetzner" => {
+                tracing::debug!("Loading Hetzner provider settings");
+                let api_url: String = variables.get_or("HETZNER_API_URL", "https://dns.hetzner.com/api/v1");
+
+                let zone_id: Option<String> = variables.get("HETZNER_ZONE_ID");
+                let api_token: String = variables.require("HETZNER_API_TOKEN")?;
+
+                let settings = HetznerProviderSettings::new(
+                    zone_id,
+                    api_url,
+                    api_token
+                );
+
+                Ok(DnsProviderSelection::Hetzner(settings))
+            },
+            "powerdns" => {
+                tracing::debug!("Loading PowerDNS provider settings");
+                let api_url: String = variables.require("POWERDNS_API_URL")?;
+                let api_key: String = variables.require("POWERDNS_API_KEY")?;
+
+                let server_id: String = variables.get_or("POWERDNS_SERVER_ID", "localhost");
+                let zone: Option<String> = variables.get("POWERDNS_ZONE");
+                let ttl: u64 = variables.parse_or("POWERDNS_TTL", "300", "a number of seconds")?;
+
+                let settings = PowerDnsProviderSettings::new(
+                    api_url,
+                    api_key,
+                    server_id,
+                    zone,
+                    ttl
+                );
+
+                Ok(DnsProviderSelection::PowerDns(settings))
+            },
             _ => {
-                Err(format!("Unsupported DNS provider: {}", input))
+                Err(Error::new(format!("Unsupported DNS provider: {}", input)))
             }
         }
     }
@@ -60,14 +157,15 @@ impl FromStr for DnsProviderSelection {
 
 #[derive(Debug, Clone)]
 pub struct CloudflareProviderSettings {
-    pub zone_id: String,
+    /// The zone to update, looked up from the domain at startup when not set.
+    pub zone_id: Option<String>,
     pub api_url: String,
     pub api_key: String,
     pub proxy_enabled: bool,
 }
 
 impl CloudflareProviderSettings {
-    pub fn new(zone_id: String, api_url: String, api_key: String, proxy_enabled: bool) -> Self {
+    pub fn new(zone_id: Option<String>, api_url: String, api_key: String, proxy_enabled: bool) -> Self {
         Self {
             zone_id,
             api_url,
@@ -76,3 +174,318 @@ impl CloudflareProviderSettings {
         }
     }
 }
+
+#[derive(Clone)]
+pub struct DigitalOceanProviderSettings {
+    /// The DigitalOcean domain to update, looked up from the domain at startup when not set.
+    pub zone: Option<String>,
+    pub api_url: String,
+    pub api_token: String,
+}
+
+impl DigitalOceanProviderSettings {
+    pub fn new(zone: Option<String>, api_url: String, api_token: String) -> Self {
+        Self {
+            zone,
+            api_url,
+            api_token,
+        }
+    }
+}
+
+impl std::fmt::Debug for DigitalOceanProviderSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DigitalOceanProviderSettings")
+            .field("zone", &self.zone)
+            .field("api_url", &self.api_url)
+            .field("api_token", &"<redacted>")
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct HetznerProviderSettings {
+    /// The zone to update, looked up from the domain at startup when not set.
+    pub zone_id: Option<String>,
+    pub api_url: String,
+    pub api_token: String,
+}
+
+impl HetznerProviderSettings {
+    pub fn new(zone_id: Option<String>, api_url: String, api_token: String) -> Self {
+        Self {
+            zone_id,
+            api_url,
+            api_token,
+        }
+    }
+}
+
+impl std::fmt::Debug for HetznerProviderSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HetznerProviderSettings")
+            .field("zone_id", &self.zone_id)
+            .field("api_url", &self.api_url)
+            .field("api_token", &"<redacted>")
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct PowerDnsProviderSettings {
+    /// The webserver of PowerDNS, e.g. `http://127.0.0.1:8081`.
+    pub api_url: String,
+    pub api_key: String,
+    pub server_id: String,
+    /// The zone to update, looked up from the domain at startup when not set.
+    pub zone: Option<String>,
+    /// TTL of records without their own, PowerDNS requires one on every record set.
+    pub ttl: u64,
+}
+
+impl PowerDnsProviderSettings {
+    pub fn new(api_url: String, api_key: String, server_id: String, zone: Option<String>, ttl: u64) -> Self {
+        Self {
+            api_url,
+            api_key,
+            server_id,
+            zone,
+            ttl,
+        }
+    }
+}
+
+impl std::fmt::Debug for PowerDnsProviderSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PowerDnsProviderSettings")
+            .field("api_url", &self.api_url)
+            .field("api_key", &"<redacted>")
+            .field("server_id", &self.server_id)
+            .field("zone", &self.zone)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Route53ProviderSettings {
+    pub api_url: String,
+    /// The hosted zone to update, looked up from the domain at startup when not set.
+    pub hosted_zone_id: Option<String>,
+    pub credentials: AwsCredentials,
+    /// TTL of records without their own, Route 53 requires one on every record.
+    pub ttl: u64,
+}
+
+impl Route53ProviderSettings {
+    pub fn new(api_url: String, hosted_zone_id: Option<String>, credentials: AwsCredentials, ttl: u64) -> Self {
+        Self {
+            api_url,
+            hosted_zone_id,
+            credentials,
+            ttl,
+        }
+    }
+}
+
+/// Where the AWS credentials come from, the keys set in the environment or a profile of the
+/// shared credentials file.
+#[derive(Clone, PartialEq)]
+pub enum AwsCredentials {
+    Keys {
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+    },
+    Profile {
+        name: String,
+        path: PathBuf,
+    },
+}
+
+impl AwsCredentials {
+    /// The keys when `AWS_ACCESS_KEY_ID` is set, otherwise the `AWS_PROFILE` profile of the
+    /// shared credentials file, `~/.aws/credentials` unless `AWS_SHARED_CREDENTIALS_FILE` is set.
+    pub(crate) fn load(variables: &Variables) -> Result<Self, Error> {
+        match (variables.get("AWS_ACCESS_KEY_ID"), variables.get("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key_id), Some(secret_access_key)) => {
+                return Ok(AwsCredentials::Keys {
+                    access_key_id,
+                    secret_access_key,
+                    session_token: variables.get("AWS_SESSION_TOKEN"),
+                });
+            },
+            (Some(_), None) => return Err(Error::new(format!("{} requires AWS_SECRET_ACCESS_KEY to be set", variables.origin("AWS_ACCESS_KEY_ID")))),
+            (None, Some(_)) => return Err(Error::new(format!("{} requires AWS_ACCESS_KEY_ID to be set", variables.origin("AWS_SECRET_ACCESS_KEY")))),
+            (None, None) => {},
+        }
+
+        let path = match (variables.get("AWS_SHARED_CREDENTIALS_FILE"), variables.get("HOME")) {
+            (Some(path), _) => PathBuf::from(path),
+            (None, Some(home)) => PathBuf::from(home).join(".aws").join("credentials"),
+            (None, None) => return Err(Error::new("AWS_ACCESS_KEY_ID or AWS_SHARED_CREDENTIALS_FILE must be set".to_string())),
+        };
+
+        Ok(AwsCredentials::Profile {
+            name: variables.get_or("AWS_PROFILE", "default"),
+            path,
+        })
+    }
+}
+
+impl std::fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AwsCredentials::Keys { access_key_id, session_token, .. } => f.debug_struct("Keys")
+                .field("access_key_id", access_key_id)
+                .field("secret_access_key", &"<redacted>")
+                .field("session_token", &session_token.as_ref().map(|_| "<redacted>"))
+                .finish(),
+            AwsCredentials::Profile { name, path } => f.debug_struct("Profile")
+                .field("name", name)
+                .field("path", path)
+                .finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rfc2136ProviderSettings {
+    /// The primary name server as `host:port`.
+    pub server: String,
+    /// The zone to update, looked up from the domain at startup when not set.
+    pub zone: Option<String>,
+    pub tsig_key: Option<TsigKeySettings>,
+    /// TTL of records without their own.
+    pub ttl: u32,
+    pub timeout_seconds: u64,
+}
+
+impl Rfc2136ProviderSettings {
+    pub fn new(server: String, zone: Option<String>, tsig_key: Option<TsigKeySettings>, ttl: u32, timeout_seconds: u64) -> Self {
+        Self {
+            server,
+            zone,
+            tsig_key,
+            ttl,
+            timeout_seconds,
+        }
+    }
+}
+
+/// The TSIG key the updates are signed with.
+#[derive(Clone, PartialEq)]
+pub struct TsigKeySettings {
+    pub name: String,
+    pub algorithm: TsigAlgorithm,
+    /// The decoded secret.
+    pub secret: Vec<u8>,
+}
+
+impl TsigKeySettings {
+    /// The key named by `RFC2136_TSIG_KEY_NAME`, with the base64 secret of
+    /// `RFC2136_TSIG_SECRET`. Messages are not signed when neither is set.
+    pub(crate) fn load(variables: &Variables) -> Result<Option<Self>, Error> {
+        let (name, secret) = match (variables.get("RFC2136_TSIG_KEY_NAME"), variables.get("RFC2136_TSIG_SECRET")) {
+            (Some(name), Some(secret)) => (name, secret),
+            (Some(_), None) => return Err(Error::new(format!("{} requires RFC2136_TSIG_SECRET to be set", variables.origin("RFC2136_TSIG_KEY_NAME")))),
+            (None, Some(_)) => return Err(Error::new(format!("{} requires RFC2136_TSIG_KEY_NAME to be set", variables.origin("RFC2136_TSIG_SECRET")))),
+            (None, None) => return Ok(None),
+        };
+
+        let secret = base64::engine::general_purpose::STANDARD.decode(secret.trim()).map_err(|error| {
+            Error::new(format!("{} must be base64 encoded: {}", variables.origin("RFC2136_TSIG_SECRET"), error))
+        })?;
+
+        Ok(Some(Self {
+            name,
+            algorithm: variables.parse_or("RFC2136_TSIG_ALGORITHM", "hmac-sha256", "hmac-sha256 or hmac-sha512")?,
+            secret,
+        }))
+    }
+}
+
+impl std::fmt::Debug for TsigKeySettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TsigKeySettings")
+            .field("name", &self.name)
+            .field("algorithm", &self.algorithm)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct Dyndns2ProviderSettings {
+    /// The full update URL, e.g. `https://dynupdate.no-ip.com/nic/update`.
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl Dyndns2ProviderSettings {
+    pub fn new(url: String, username: String, password: String) -> Self {
+        Self {
+            url,
+            username,
+            password,
+        }
+    }
+}
+
+impl std::fmt::Debug for Dyndns2ProviderSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dyndns2ProviderSettings")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsigAlgorithm {
+    HmacSha256,
+    HmacSha512,
+}
+
+impl FromStr for TsigAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().trim_end_matches('.') {
+            "hmac-sha256" => Ok(TsigAlgorithm::HmacSha256),
+            "hmac-sha512" => Ok(TsigAlgorithm::HmacSha512),
+            _ => Err(format!("Unsupported TSIG algorithm: {}", s)),
+        }
+    }
+}
+
+/// The server as `host:port`, port 53 when the server has none.
+fn server_address(raw_input: &str) -> String {
+    let input = raw_input.trim();
+
+    if input.parse::<SocketAddr>().is_ok() {
+        return input.to_string();
+    }
+
+    match input.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => SocketAddr::new(ip, 53).to_string(),
+        Err(_) if input.contains(':') => input.to_string(),
+        Err(_) => format!("{}:53", input),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_address() {
+        assert_eq!(server_address("ns1.example.com"), "ns1.example.com:53");
+        assert_eq!(server_address("ns1.example.com:5353"), "ns1.example.com:5353");
+        assert_eq!(server_address("192.0.2.53"), "192.0.2.53:53");
+        assert_eq!(server_address("2001:db8::53"), "[2001:db8::53]:53");
+        assert_eq!(server_address("[2001:db8::53]:5353"), "[2001:db8::53]:5353");
+    }
+}
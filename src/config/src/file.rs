@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::error::Error;
+use crate::sync_entry::{RecordSettings, RecordType, SyncEntry};
+
+/// A configuration file, TOML or YAML depending on the extension.
+///
+/// Every setting is optional and mirrors an environment variable, which takes precedence over
+/// the file. Unknown keys are rejected so typos do not go unnoticed.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub log_level: Option<String>,
+    pub environment: Option<String>,
+    pub domain: Option<String>,
+    pub dns_provider: Option<String>,
+    pub dns_record_types: Option<Vec<RecordType>>,
+    pub refresh_interval_seconds: Option<u64>,
+    pub ip_sources: Option<Vec<String>>,
+    pub ip_sources_quorum: Option<usize>,
+    pub allow_private_ips: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub plan_format: Option<String>,
+    pub owner_id: Option<String>,
+    pub prune: Option<bool>,
+    pub duplicate_policy: Option<String>,
+    #[serde(default)]
+    pub records: Vec<FileRecord>,
+    #[serde(default)]
+    pub retry: FileRetry,
+    #[serde(default)]
+    pub http: FileHttp,
+    #[serde(default)]
+    pub cloudflare: FileCloudflare,
+    #[serde(default)]
+    pub route53: FileRoute53,
+    #[serde(default)]
+    pub rfc2136: FileRfc2136,
+    #[serde(default)]
+    pub dyndns2: FileDyndns2,
+    #[serde(default)]
+    pub digitalocean: FileDigitalOcean,
+    #[serde(default)]
+    pub hetzner: FileHetzner,
+    #[serde(default)]
+    pub powerdns: FilePowerDns,
+    #[serde(default)]
+    pub zones: Vec<FileZone>,
+}
+
+/// A `[[zones]]` entry, a zone synced next to the others with its own provider and records.
+/// Settings left out of the zone fall back to the global settings.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileZone {
+    /// Name used in logs and plans, the domain by default.
+    pub name: Option<String>,
+    pub domain: String,
+    pub dns_provider: Option<String>,
+    pub dns_record_types: Option<Vec<RecordType>>,
+    #[serde(default)]
+    pub records: Vec<FileRecord>,
+    #[serde(default)]
+    pub cloudflare: FileCloudflare,
+    #[serde(default)]
+    pub route53: FileRoute53,
+    #[serde(default)]
+    pub rfc2136: FileRfc2136,
+    #[serde(default)]
+    pub dyndns2: FileDyndns2,
+    #[serde(default)]
+    pub digitalocean: FileDigitalOcean,
+    #[serde(default)]
+    pub hetzner: FileHetzner,
+    #[serde(default)]
+    pub powerdns: FilePowerDns,
+}
+
+/// A `[[records]]` entry, the file equivalent of a `DNS_ENTRIES_TO_SYNC` entry together with
+/// its record settings.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRecord {
+    pub name: String,
+    pub types: Option<Vec<RecordType>>,
+    pub ttl: Option<u64>,
+    pub proxied: Option<bool>,
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRetry {
+    pub max_attempts: Option<u32>,
+    pub max_delay_seconds: Option<u64>,
+    pub deadline_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileHttp {
+    pub connect_timeout_seconds: Option<u64>,
+    pub request_timeout_seconds: Option<u64>,
+    pub user_agent: Option<String>,
+    pub proxy_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileCloudflare {
+    pub api_url: Option<String>,
+    pub zone_id: Option<String>,
+    pub api_key: Option<String>,
+    pub proxy_enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRoute53 {
+    pub api_url: Option<String>,
+    pub hosted_zone_id: Option<String>,
+    pub ttl: Option<u64>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    pub profile: Option<String>,
+    pub credentials_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRfc2136 {
+    pub server: Option<String>,
+    pub zone: Option<String>,
+    pub ttl: Option<u32>,
+    pub timeout_seconds: Option<u64>,
+    pub tsig_key_name: Option<String>,
+    pub tsig_secret: Option<String>,
+    pub tsig_algorithm: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileDyndns2 {
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileDigitalOcean {
+    pub api_url: Option<String>,
+    pub zone: Option<String>,
+    pub api_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileHetzner {
+    pub api_url: Option<String>,
+    pub zone_id: Option<String>,
+    pub api_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilePowerDns {
+    pub api_url: Option<String>,
+    pub api_key: Option<String>,
+    pub server_id: Option<String>,
+    pub zone: Option<String>,
+    pub ttl: Option<u64>,
+}
+
+/// A setting read from the configuration file.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FileValue {
+    /// The dotted path of the key in the file, e.g. `retry.max_attempts`.
+    pub(crate) key: &'static str,
+    pub(crate) value: String,
+}
+
+/// A loaded configuration file.
+#[derive(Debug, Clone)]
+pub(crate) struct ConfigFile {
+    pub(crate) path: PathBuf,
+    pub(crate) config: FileConfig,
+}
+
+impl ConfigFile {
+    /// Read and deserialize the file, the format is picked from the extension.
+    pub(crate) fn load(path: &Path) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|error| {
+            Error::new(format!("Failed to read config file {}: {}", path.display(), error))
+        })?;
+
+        let extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        let config = match extension.as_deref() {
+            Some("toml") => FileConfig::from_toml(&contents),
+            Some("yaml") | Some("yml") => FileConfig::from_yaml(&contents),
+            _ => Err(Error::new("expected a .toml, .yaml or .yml extension".to_string())),
+        }.map_err(|error| {
+            Error::new(format!("Invalid config file {}: {}", path.display(), error))
+        })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            config,
+        })
+    }
+
+    /// The scalar settings of the file keyed by the environment variable they stand in for.
+    pub(crate) fn variables(&self) -> HashMap<String, FileValue> {
+        let config = &self.config;
+
+        let join = |values: &Vec<String>, separator: &str| values.join(separator);
+
+        let values: Vec<(&str, &'static str, Option<String>)> = vec![
+            ("RUST_LOG", "log_level", config.log_level.clone()),
+            ("ENVIRONMENT", "environment", config.environment.clone()),
+            ("DOMAIN", "domain", config.domain.clone()),
+            ("DNS_PROVIDER", "dns_provider", config.dns_provider.clone()),
+            ("DNS_RECORD_TYPES", "dns_record_types", config.dns_record_types.as_deref().map(join_record_types)),
+            ("REFRESH_INTERVAL_SECONDS", "refresh_interval_seconds", config.refresh_interval_seconds.map(|v| v.to_string())),
+            ("IP_SOURCES", "ip_sources", config.ip_sources.as_ref().map(|v| join(v, ","))),
+            ("IP_SOURCES_QUORUM", "ip_sources_quorum", config.ip_sources_quorum.map(|v| v.to_string())),
+            ("ALLOW_PRIVATE_IPS", "allow_private_ips", config.allow_private_ips.map(|v| v.to_string())),
+            ("DRY_RUN", "dry_run", config.dry_run.map(|v| v.to_string())),
+            ("PLAN_FORMAT", "plan_format", config.plan_format.clone()),
+            ("OWNER_ID", "owner_id", config.owner_id.clone()),
+            ("PRUNE", "prune", config.prune.map(|v| v.to_string())),
+            ("DUPLICATE_POLICY", "duplicate_policy", config.duplicate_policy.clone()),
+            ("RETRY_MAX_ATTEMPTS", "retry.max_attempts", config.retry.max_attempts.map(|v| v.to_string())),
+            ("RETRY_MAX_DELAY_SECONDS", "retry.max_delay_seconds", config.retry.max_delay_seconds.map(|v| v.to_string())),
+            ("RETRY_DEADLINE_SECONDS", "retry.deadline_seconds", config.retry.deadline_seconds.map(|v| v.to_string())),
+            ("HTTP_CONNECT_TIMEOUT_SECONDS", "http.connect_timeout_seconds", config.http.connect_timeout_seconds.map(|v| v.to_string())),
+            ("HTTP_REQUEST_TIMEOUT_SECONDS", "http.request_timeout_seconds", config.http.request_timeout_seconds.map(|v| v.to_string())),
+            ("HTTP_USER_AGENT", "http.user_agent", config.http.user_agent.clone()),
+            ("HTTP_PROXY_URL", "http.proxy_url", config.http.proxy_url.clone()),
+        ];
+
+        let values = values.into_iter()
+            .chain(config.cloudflare.values())
+            .chain(config.route53.values())
+            .chain(config.rfc2136.values())
+            .chain(config.dyndns2.values())
+            .chain(config.digitalocean.values())
+            .chain(config.hetzner.values())
+            .chain(config.powerdns.values())
+            .collect();
+
+        into_file_values(values)
+    }
+
+    /// The settings of the zone keyed by the environment variable they stand in for.
+    pub(crate) fn zone_variables(&self, zone: &FileZone) -> HashMap<String, FileValue> {
+        let values: Vec<(&str, &'static str, Option<String>)> = vec![
+            ("DOMAIN", "domain", Some(zone.domain.clone())),
+            ("DNS_PROVIDER", "dns_provider", zone.dns_provider.clone()),
+            ("DNS_RECORD_TYPES", "dns_record_types", zone.dns_record_types.as_deref().map(join_record_types)),
+        ];
+
+        let values = values.into_iter()
+            .chain(zone.cloudflare.values())
+            .chain(zone.route53.values())
+            .chain(zone.rfc2136.values())
+            .chain(zone.dyndns2.values())
+            .chain(zone.digitalocean.values())
+            .chain(zone.hetzner.values())
+            .chain(zone.powerdns.values())
+            .collect();
+
+        into_file_values(values)
+    }
+
+    /// The `[[records]]` entries, records without `types` use the default record types.
+    pub(crate) fn sync_entries(&self, default_record_types: &[RecordType]) -> Result<Vec<SyncEntry>, Error> {
+        self.records_to_entries("records", &self.config.records, default_record_types)
+    }
+
+    /// The records of a zone, `prefix` is the path of the records in the file used in errors.
+    pub(crate) fn records_to_entries(&self, prefix: &str, records: &[FileRecord], default_record_types: &[RecordType]) -> Result<Vec<SyncEntry>, Error> {
+        records.iter()
+            .enumerate()
+            .map(|(index, record)| {
+                let name = record.name.trim();
+
+                if name.is_empty() {
+                    return Err(Error::new(format!("{}[{}].name in {} must not be empty", prefix, index, self.path.display())));
+                }
+
+                let record_types = match &record.types {
+                    Some(types) if types.is_empty() => {
+                        return Err(Error::new(format!("{}[{}].types in {} must not be empty", prefix, index, self.path.display())));
+                    },
+                    Some(types) => types.clone(),
+                    None => default_record_types.to_vec(),
+                };
+
+                if record.ttl == Some(0) {
+                    return Err(Error::new(format!("{}[{}].ttl in {} must be greater than 0", prefix, index, self.path.display())));
+                }
+
+                Ok(SyncEntry {
+                    name: name.to_string(),
+                    record_types,
+                    settings: RecordSettings {
+                        ttl: record.ttl,
+                        proxied: record.proxied,
+                        comment: record.comment.clone(),
+                        tags: record.tags.clone(),
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+impl FileCloudflare {
+    fn values(&self) -> Vec<(&'static str, &'static str, Option<String>)> {
+        vec![
+            ("CLOUDFLARE_API_URL", "cloudflare.api_url", self.api_url.clone()),
+            ("CLOUDFLARE_ZONE_ID", "cloudflare.zone_id", self.zone_id.clone()),
+            ("CLOUDFLARE_API_KEY", "cloudflare.api_key", self.api_key.clone()),
+            ("CLOUDFLARE_PROXY_ENABLED", "cloudflare.proxy_enabled", self.proxy_enabled.map(|v| v.to_string())),
+        ]
+    }
+}
+
+impl FileRoute53 {
+    fn values(&self) -> Vec<(&'static str, &'static str, Option<String>)> {
+        vec![
+            ("ROUTE53_API_URL", "route53.api_url", self.api_url.clone()),
+            ("ROUTE53_HOSTED_ZONE_ID", "route53.hosted_zone_id", self.hosted_zone_id.clone()),
+            ("ROUTE53_TTL", "route53.ttl", self.ttl.map(|v| v.to_string())),
+            ("AWS_ACCESS_KEY_ID", "route53.access_key_id", self.access_key_id.clone()),
+            ("AWS_SECRET_ACCESS_KEY", "route53.secret_access_key", self.secret_access_key.clone()),
+            ("AWS_SESSION_TOKEN", "route53.session_token", self.session_token.clone()),
+            ("AWS_PROFILE", "route53.profile", self.profile.clone()),
+            ("AWS_SHARED_CREDENTIALS_FILE", "route53.credentials_file", self.credentials_file.clone()),
+        ]
+    }
+}
+
+impl FileRfc2136 {
+    fn values(&self) -> Vec<(&'static str, &'static str, Option<String>)> {
+        vec![
+            ("RFC2136_SERVER", "rfc2136.server", self.server.clone()),
+            ("RFC2136_ZONE", "rfc2136.zone", self.zone.clone()),
+            ("RFC2136_TTL", "rfc2136.ttl", self.ttl.map(|v| v.to_string())),
+            ("RFC2136_TIMEOUT_SECONDS", "rfc2136.timeout_seconds", self.timeout_seconds.map(|v| v.to_string())),
+            ("RFC2136_TSIG_KEY_NAME", "rfc2136.tsig_key_name", self.tsig_key_name.clone()),
+            ("RFC2136_TSIG_SECRET", "rfc2136.tsig_secret", self.tsig_secret.clone()),
+            ("RFC2136_TSIG_ALGORITHM", "rfc2136.tsig_algorithm", self.tsig_algorithm.clone()),
+        ]
+    }
+}
+
+impl FileDyndns2 {
+    fn values(&self) -> Vec<(&'static str, &'static str, Option<String>)> {
+        vec![
+            ("DYNDNS2_URL", "dyndns2.url", self.url.clone()),
+            ("DYNDNS2_USERNAME", "dyndns2.username", self.username.clone()),
+            ("DYNDNS2_PASSWORD", "dyndns2.password", self.password.clone()),
+        ]
+    }
+}
+
+impl FileDigitalOcean {
+    fn values(&self) -> Vec<(&'static str, &'static str, Option<String>)> {
+        vec![
+            ("DIGITALOCEAN_API_URL", "digitalocean.api_url", self.api_url.clone()),
+            ("DIGITALOCEAN_ZONE", "digitalocean.zone", self.zone.clone()),
+            ("DIGITALOCEAN_API_TOKEN", "digitalocean.api_token", self.api_token.clone()),
+        ]
+    }
+}
+
+impl FileHetzner {
+    fn values(&self) -> Vec<(&'static str, &'static str, Option<String>)> {
+        vec![
+            ("HETZNER_API_URL", "hetzner.api_url", self.api_url.clone()),
+            ("HETZNER_ZONE_ID", "hetzner.zone_id", self.zone_id.clone()),
+            ("HETZNER_API_TOKEN", "hetzner.api_token", self.api_token.clone()),
+        ]
+    }
+}
+
+impl FilePowerDns {
+    fn values(&self) -> Vec<(&'static str, &'static str, Option<String>)> {
+        vec![
+            ("POWERDNS_API_URL", "powerdns.api_url", self.api_url.clone()),
+            ("POWERDNS_API_KEY", "powerdns.api_key", self.api_key.clone()),
+            ("POWERDNS_SERVER_ID", "powerdns.server_id", self.server_id.clone()),
+            ("POWERDNS_ZONE", "powerdns.zone", self.zone.clone()),
+            ("POWERDNS_TTL", "powerdns.ttl", self.ttl.map(|v| v.to_string())),
+        ]
+    }
+}
+
+fn join_record_types(record_types: &[RecordType]) -> String {
+    record_types.iter().map(|record_type| record_type.to_string()).collect::<Vec<_>>().join("+")
+}
+
+fn into_file_values(values: Vec<(&str, &'static str, Option<String>)>) -> HashMap<String, FileValue> {
+    values.into_iter()
+        .filter_map(|(variable, key, value)| {
+            value.map(|value| (variable.to_string(), FileValue { key, value }))
+        })
+        .collect()
+}
+
+impl FileConfig {
+    pub(crate) fn from_toml(contents: &str) -> Result<Self, Error> {
+        toml::from_str(contents).map_err(|error| Error::new(error.to_string()))
+    }
+
+    pub(crate) fn from_yaml(contents: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(contents).map_err(|error| Error::new(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"
+domain = "example.com"
+dns_provider = "cloudflare"
+ip_sources = ["ipify", "opendns"]
+
+[[records]]
+name = "www"
+types = ["A", "AAAA"]
+
+[[records]]
+name = "api"
+ttl = 300
+proxied = false
+comment = "managed by dynamic-dns"
+tags = ["home"]
+
+[retry]
+max_attempts = 3
+
+[cloudflare]
+zone_id = "zone"
+"#;
+
+    fn file(config: FileConfig) -> ConfigFile {
+        ConfigFile {
+            path: PathBuf::from("dynamic-dns.toml"),
+            config,
+        }
+    }
+
+    #[test]
+    fn test_toml_and_yaml_are_equivalent() {
+        let yaml = r#"
+domain: example.com
+dns_provider: cloudflare
+ip_sources: [ipify, opendns]
+records:
+  - name: www
+    types: [A, AAAA]
+  - name: api
+    ttl: 300
+    proxied: false
+    comment: managed by dynamic-dns
+    tags: [home]
+retry:
+  max_attempts: 3
+cloudflare:
+  zone_id: zone
+"#;
+
+        assert_eq!(FileConfig::from_toml(TOML).unwrap(), FileConfig::from_yaml(yaml).unwrap());
+    }
+
+    #[test]
+    fn test_variables() {
+        let variables = file(FileConfig::from_toml(TOML).unwrap()).variables();
+
+        assert_eq!(variables.get("IP_SOURCES").unwrap().value, "ipify,opendns");
+        assert_eq!(variables.get("RETRY_MAX_ATTEMPTS"), Some(&FileValue {
+            key: "retry.max_attempts",
+            value: "3".to_string(),
+        }));
+        assert_eq!(variables.get("CLOUDFLARE_ZONE_ID").unwrap().value, "zone");
+        assert!(!variables.contains_key("HTTP_PROXY_URL"));
+    }
+
+    #[test]
+    fn test_sync_entries() {
+        let entries = file(FileConfig::from_toml(TOML).unwrap()).sync_entries(&[RecordType::A]).unwrap();
+
+        assert_eq!(entries, vec![
+            SyncEntry {
+                name: "www".to_string(),
+                record_types: vec![RecordType::A, RecordType::AAAA],
+                settings: RecordSettings::default(),
+            },
+            SyncEntry {
+                name: "api".to_string(),
+                record_types: vec![RecordType::A],
+                settings: RecordSettings {
+                    ttl: Some(300),
+                    proxied: Some(false),
+                    comment: Some("managed by dynamic-dns".to_string()),
+                    tags: vec!["home".to_string()],
+                },
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_errors_point_to_the_key() {
+        let error = FileConfig::from_toml("[retry]\nmax_attempts = \"three\"\n").unwrap_err();
+        assert!(error.message.contains("max_attempts"), "{}", error);
+
+        let error = FileConfig::from_toml("refresh_interval = 60\n").unwrap_err();
+        assert!(error.message.contains("refresh_interval"), "{}", error);
+
+        let error = FileConfig::from_yaml("records:\n  - name: www\n    types: [MX]\n").unwrap_err();
+        assert!(error.message.contains("records[0].types"), "{}", error);
+
+        let config = FileConfig::from_toml("[[records]]\nname = \" \"\n").unwrap();
+        let error = file(config).sync_entries(&[RecordType::A]).unwrap_err();
+        assert_eq!(error.message, "records[0].name in dynamic-dns.toml must not be empty");
+    }
+}
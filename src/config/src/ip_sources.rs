@@ -0,0 +1,116 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The services that can be queried to detect the public IP address, configured through
+/// `IP_SOURCES` as a comma separated list that is tried in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpSourceConfig {
+    /// `ipify`, the api.ipify.org / api6.ipify.org service.
+    Ipify,
+    /// `icanhazip`, the ipv4.icanhazip.com / ipv6.icanhazip.com service.
+    Icanhazip,
+    /// `ifconfig.co`, plain text response from ifconfig.co.
+    IfconfigCo,
+    /// `opendns`, a DNS lookup of `myip.opendns.com` against the OpenDNS resolvers.
+    OpenDns,
+    /// `url:<url>`, any URL responding with the address as plain text.
+    PlainText { url: String },
+    /// `json:<url>#<field.path>`, any URL responding with JSON containing the address at the
+    /// dot separated field path.
+    Json { url: String, field_path: String },
+    /// `interface:<name>`, the first public address assigned to a local network interface.
+    Interface { name: String },
+    /// `upnp` or `upnp:<description-url>`, the external address reported by a UPnP gateway,
+    /// discovered with SSDP unless the device description URL is given.
+    Upnp { description_url: Option<String> },
+}
+
+impl FromStr for IpSourceConfig {
+    type Err = String;
+
+    fn from_str(raw_input: &str) -> Result<Self, Self::Err> {
+        let input = raw_input.trim();
+
+        if let Some(url) = input.strip_prefix("url:") {
+            return Ok(IpSourceConfig::PlainText { url: url.to_string() });
+        }
+
+        if let Some(raw_json) = input.strip_prefix("json:") {
+            let (url, field_path) = raw_json.rsplit_once('#')
+                .ok_or_else(|| format!("JSON IP source must be json:<url>#<field.path>: {}", input))?;
+
+            return Ok(IpSourceConfig::Json { url: url.to_string(), field_path: field_path.to_string() });
+        }
+
+        if let Some(name) = input.strip_prefix("interface:") {
+            return Ok(IpSourceConfig::Interface { name: name.to_string() });
+        }
+
+        if let Some(description_url) = input.strip_prefix("upnp:") {
+            return Ok(IpSourceConfig::Upnp { description_url: Some(description_url.to_string()) });
+        }
+
+        match input.to_lowercase().as_str() {
+            "ipify" => Ok(IpSourceConfig::Ipify),
+            "icanhazip" => Ok(IpSourceConfig::Icanhazip),
+            "ifconfig.co" => Ok(IpSourceConfig::IfconfigCo),
+            "opendns" => Ok(IpSourceConfig::OpenDns),
+            "upnp" => Ok(IpSourceConfig::Upnp { description_url: None }),
+            _ => Err(format!("Unsupported IP source: {}", input)),
+        }
+    }
+}
+
+impl Display for IpSourceConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IpSourceConfig::Ipify => write!(f, "ipify"),
+            IpSourceConfig::Icanhazip => write!(f, "icanhazip"),
+            IpSourceConfig::IfconfigCo => write!(f, "ifconfig.co"),
+            IpSourceConfig::OpenDns => write!(f, "opendns"),
+            IpSourceConfig::PlainText { url } => write!(f, "url:{}", url),
+            IpSourceConfig::Json { url, field_path } => write!(f, "json:{}#{}", url, field_path),
+            IpSourceConfig::Interface { name } => write!(f, "interface:{}", name),
+            IpSourceConfig::Upnp { description_url: None } => write!(f, "upnp"),
+            IpSourceConfig::Upnp { description_url: Some(description_url) } => write!(f, "upnp:{}", description_url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_named_sources() {
+        assert_eq!("ipify".parse::<IpSourceConfig>().unwrap(), IpSourceConfig::Ipify);
+        assert_eq!(" OpenDNS ".parse::<IpSourceConfig>().unwrap(), IpSourceConfig::OpenDns);
+        assert!("unknown".parse::<IpSourceConfig>().is_err());
+    }
+
+    #[test]
+    fn test_parse_url_sources() {
+        assert_eq!(
+            "url:https://example.com/ip".parse::<IpSourceConfig>().unwrap(),
+            IpSourceConfig::PlainText { url: "https://example.com/ip".to_string() }
+        );
+        assert_eq!(
+            "json:https://example.com/ip?format=json#data.ip".parse::<IpSourceConfig>().unwrap(),
+            IpSourceConfig::Json { url: "https://example.com/ip?format=json".to_string(), field_path: "data.ip".to_string() }
+        );
+        assert!("json:https://example.com/ip".parse::<IpSourceConfig>().is_err());
+    }
+
+    #[test]
+    fn test_parse_local_sources() {
+        assert_eq!(
+            "interface:ppp0".parse::<IpSourceConfig>().unwrap(),
+            IpSourceConfig::Interface { name: "ppp0".to_string() }
+        );
+        assert_eq!("upnp".parse::<IpSourceConfig>().unwrap(), IpSourceConfig::Upnp { description_url: None });
+        assert_eq!(
+            "upnp:http://192.168.1.1:5000/rootDesc.xml".parse::<IpSourceConfig>().unwrap(),
+            IpSourceConfig::Upnp { description_url: Some("http://192.168.1.1:5000/rootDesc.xml".to_string()) }
+        );
+    }
+}
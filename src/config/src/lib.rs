@@ -1,9 +1,21 @@
 pub mod dns_providers;
+pub mod error;
+pub mod file;
+pub mod ip_sources;
+pub mod sync_entry;
+mod variables;
 
-use std::env;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
 use dotenvy::dotenv;
 
 use crate::dns_providers::{DnsProvider, DnsProviderSelection};
+use crate::error::Error;
+use crate::file::ConfigFile;
+use crate::variables::{Variables, ZONE_IDENTITY_KEYS};
+use crate::ip_sources::IpSourceConfig;
+use crate::sync_entry::{parse_record_types, RecordType, SyncEntry};
 
 /// Struct representing the application state
 /// This struct is used to store the application configuration
@@ -14,10 +26,103 @@ use crate::dns_providers::{DnsProvider, DnsProviderSelection};
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub environment: String,
-    pub dns_entries_to_sync: Vec<String>,
-    pub domain: String,
+    pub zones: Vec<ZoneConfig>,
     pub refresh_interval_seconds: u64,
-    pub dns_provider: DnsProvider
+    pub ip_sources: Vec<IpSourceConfig>,
+    pub ip_sources_quorum: usize,
+    pub allow_private_ips: bool,
+    pub retry: RetrySettings,
+    pub http: HttpSettings,
+    pub dry_run: bool,
+    pub plan_format: PlanFormat,
+    pub ownership: OwnershipSettings,
+    pub duplicate_policy: DuplicatePolicy,
+}
+
+/// A DNS zone kept in sync, with its own provider and records.
+#[derive(Debug, Clone)]
+pub struct ZoneConfig {
+    /// Name used in logs and plans, the domain unless configured otherwise.
+    pub name: String,
+    pub domain: String,
+    pub dns_provider: DnsProvider,
+    pub dns_entries_to_sync: Vec<SyncEntry>,
+}
+
+impl ZoneConfig {
+    /// Every sync entry of the zones, used to decide which address families to resolve.
+    pub fn all_entries(zones: &[ZoneConfig]) -> Vec<SyncEntry> {
+        zones.iter().flat_map(|zone| zone.dns_entries_to_sync.clone()).collect()
+    }
+}
+
+/// Output format of the plan printed in dry-run mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for PlanFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(PlanFormat::Text),
+            "json" => Ok(PlanFormat::Json),
+            _ => Err(format!("Unknown plan format: {}", s)),
+        }
+    }
+}
+
+/// What the sync does with a name that has several records of the same type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Update every record of the name.
+    UpdateAll,
+    /// Update one record and delete the others, only the owned ones are deleted.
+    KeepOne,
+    /// Leave the records as they are and report the conflict.
+    #[default]
+    Refuse,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "update-all" => Ok(DuplicatePolicy::UpdateAll),
+            "keep-one" => Ok(DuplicatePolicy::KeepOne),
+            "refuse" => Ok(DuplicatePolicy::Refuse),
+            _ => Err(format!("Unknown duplicate policy: {}", s)),
+        }
+    }
+}
+
+/// Settings of the HTTP client shared by every API client.
+#[derive(Debug, Clone)]
+pub struct HttpSettings {
+    pub connect_timeout_seconds: u64,
+    pub request_timeout_seconds: u64,
+    pub user_agent: String,
+    pub proxy_url: Option<String>,
+}
+
+/// Retry settings shared by every HTTP client.
+#[derive(Debug, Clone)]
+pub struct RetrySettings {
+    pub max_attempts: u32,
+    pub max_delay_seconds: u64,
+    pub deadline_seconds: Option<u64>,
+}
+
+/// Which records the daemon marks as its own and whether it deletes the owned records that are
+/// no longer configured. Records without the marker of `owner_id` are never deleted.
+#[derive(Debug, Clone, Default)]
+pub struct OwnershipSettings {
+    pub owner_id: Option<String>,
+    pub prune: bool,
 }
 
 trait StripQuotes {
@@ -33,8 +138,11 @@ impl StripQuotes for String {
 
 /// Initialize the observability layer, this allows tracing, metrics, etc. to be configured.
 /// Not fully implemented yet, but will be expanded upon in the future.
+///
+/// Logs are written to stderr so stdout only carries the output of the command, such as a plan.
 fn init_observability(log_level: tracing::Level) {
     tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
         .with_level(true)
         .with_max_level(log_level)
         .event_format(
@@ -46,15 +154,29 @@ fn init_observability(log_level: tracing::Level) {
         .init();
 }
 
-/// Load the application state from the environment variables.
-pub async fn load_state() -> AppState {
+/// Load the application state from the environment variables and the optional configuration
+/// file named by `CONFIG_FILE`.
+///
+/// # Arguments
+/// * `overrides` - Values taking precedence over the environment variables of the same name,
+///   such as command line flags.
+///
+/// # Returns
+/// The application state, or an error naming the first invalid variable.
+pub async fn load_state(overrides: &HashMap<String, String>) -> Result<AppState, Error> {
     // Log configuration and bootstrap
     let load_env = dotenv();
-    if load_env.is_err() {
-        tracing::warn!("No .env file found");
-    }
 
-    let raw_log_level: String = env::var("RUST_LOG").unwrap_or_else(|_| "INFO".to_string()).strip_quotes();
+    // The file is loaded before logging is set up so it can configure the log level, a broken
+    // file is only reported once logging is initialized.
+    let config_file: Result<Option<ConfigFile>, Error> = Variables::new(overrides, None)
+        .get("CONFIG_FILE")
+        .map(|path| ConfigFile::load(Path::new(&path)))
+        .transpose();
+
+    let variables = Variables::new(overrides, config_file.as_ref().ok().and_then(|file| file.as_ref()));
+
+    let raw_log_level: String = variables.get_or("RUST_LOG", "INFO");
     let uppercased_log_level: String = raw_log_level.to_uppercase();
 
     let tracing_level: tracing::Level = match uppercased_log_level.as_str() {
@@ -67,44 +189,499 @@ pub async fn load_state() -> AppState {
 
     init_observability(tracing_level);
 
+    if load_env.is_err() {
+        tracing::warn!("No .env file found");
+    }
+
     tracing::info!("Starting application with tracing level: {}", tracing_level);
 
+    let config_file = config_file?;
+    if let Some(config_file) = &config_file {
+        tracing::info!("Loaded config file {}", config_file.path.display());
+    }
+
     // Core environment variables
-    let environment = env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()).strip_quotes();
+    let environment = variables.get_or("ENVIRONMENT", "development");
+
+    let zones: Vec<ZoneConfig> = match &config_file {
+        Some(config_file) if !config_file.config.zones.is_empty() => load_file_zones(config_file, &variables)?,
+        _ => vec![load_zone(&variables, config_file.as_ref())?],
+    };
 
-    let domain: String = env::var("DOMAIN").expect("DOMAIN must be set").strip_quotes();
+    let refresh_interval_seconds: u64 = variables.parse_or("REFRESH_INTERVAL_SECONDS", "60", "a number")?;
 
-    let raw_dns_entries_to_sync: String = env::var("DNS_ENTRIES_TO_SYNC").unwrap_or_default();
-    let dns_entries_to_sync: Vec<String> = raw_dns_entries_to_sync
-        .strip_quotes()
-        .split(',')
-        .map(|s| s.to_string()).collect();
+    let (ip_sources, ip_sources_quorum) = load_ip_sources(&variables)?;
 
-    let refresh_interval_seconds: u64 = env::var("REFRESH_INTERVAL_SECONDS")
-        .unwrap_or_else(|_| String::from("60"))
-        .strip_quotes()
-        .parse()
-        .expect("REFRESH_INTERVAL_SECONDS must be a number");
+    let allow_private_ips: bool = variables.parse_bool_or("ALLOW_PRIVATE_IPS", false)?;
 
-    let dns_provider_selection: DnsProviderSelection = env::var("DNS_PROVIDER")
-        .expect("DNS_PROVIDER must be set")
-        .strip_quotes()
-        .parse()
-        .expect("Invalid DNS_PROVIDER value");
+    let retry_max_attempts: u32 = variables.parse_or("RETRY_MAX_ATTEMPTS", "5", "a number")?;
 
-    let dns_provider: DnsProvider = DnsProvider {
-        config: dns_provider_selection
+    let retry_max_delay_seconds: u64 = variables.parse_or("RETRY_MAX_DELAY_SECONDS", "30", "a number")?;
+
+    // A deadline of 0 disables it, leaving only the attempt limit.
+    let retry_deadline_seconds: u64 = variables.parse_or("RETRY_DEADLINE_SECONDS", "60", "a number")?;
+
+    let http_connect_timeout_seconds: u64 = variables.parse_or("HTTP_CONNECT_TIMEOUT_SECONDS", "10", "a number")?;
+
+    let http_request_timeout_seconds: u64 = variables.parse_or("HTTP_REQUEST_TIMEOUT_SECONDS", "30", "a number")?;
+
+    let http_user_agent: String = variables.get_or("HTTP_USER_AGENT", "dynamic-dns");
+
+    let http_proxy_url: Option<String> = variables.get("HTTP_PROXY_URL");
+
+    let http = HttpSettings {
+        connect_timeout_seconds: http_connect_timeout_seconds,
+        request_timeout_seconds: http_request_timeout_seconds,
+        user_agent: http_user_agent,
+        proxy_url: http_proxy_url,
+    };
+
+    let retry = RetrySettings {
+        max_attempts: retry_max_attempts,
+        max_delay_seconds: retry_max_delay_seconds,
+        deadline_seconds: Some(retry_deadline_seconds).filter(|seconds| *seconds > 0),
     };
 
+    let dry_run: bool = variables.parse_bool_or("DRY_RUN", false)?;
+
+    let plan_format: PlanFormat = variables.parse_or("PLAN_FORMAT", "text", "text or json")?;
+
+    let ownership = load_ownership(&variables)?;
+
+    let duplicate_policy: DuplicatePolicy = variables.parse_or("DUPLICATE_POLICY", "refuse", "update-all, keep-one or refuse")?;
 
     // for each strip all single and double quote from start/end if present
     let app_state: AppState = AppState {
         environment,
+        zones,
+        refresh_interval_seconds,
+        ip_sources,
+        ip_sources_quorum,
+        allow_private_ips,
+        retry,
+        http,
+        dry_run,
+        plan_format,
+        ownership,
+        duplicate_policy,
+    };
+
+    Ok(app_state)
+}
+
+/// Load `IP_SOURCES` and `IP_SOURCES_QUORUM`, the quorum must be reachable with the sources.
+fn load_ip_sources(variables: &Variables) -> Result<(Vec<IpSourceConfig>, usize), Error> {
+    let ip_sources: Vec<IpSourceConfig> = variables.get_or("IP_SOURCES", "ipify")
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|error| Error::new(format!("Invalid value for {}: {}", variables.origin("IP_SOURCES"), error)))?;
+
+    let ip_sources_quorum: usize = variables.parse_or("IP_SOURCES_QUORUM", "1", "a number")?;
+
+    if ip_sources_quorum == 0 || ip_sources_quorum > ip_sources.len() {
+        return Err(Error::new(format!(
+            "{} must be between 1 and the number of IP sources ({}), got {}",
+            variables.origin("IP_SOURCES_QUORUM"),
+            ip_sources.len(),
+            ip_sources_quorum
+        )));
+    }
+
+    Ok((ip_sources, ip_sources_quorum))
+}
+
+/// Load `OWNER_ID` and `PRUNE`, pruning is only allowed when the records are marked.
+fn load_ownership(variables: &Variables) -> Result<OwnershipSettings, Error> {
+    let owner_id: Option<String> = variables.get("OWNER_ID");
+
+    if let Some(owner_id) = &owner_id {
+        if !owner_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+            return Err(Error::new(format!("{} may only contain letters, digits, -, _ and .", variables.origin("OWNER_ID"))));
+        }
+    }
+
+    let prune: bool = variables.parse_bool_or("PRUNE", false)?;
+
+    if prune && owner_id.is_none() {
+        return Err(Error::new(format!("{} requires OWNER_ID to be set", variables.origin("PRUNE"))));
+    }
+
+    Ok(OwnershipSettings {
+        owner_id,
+        prune,
+    })
+}
+
+/// Load the single zone configured by the global variables, `DOMAIN`, `DNS_PROVIDER`,
+/// `DNS_ENTRIES_TO_SYNC` or the `[[records]]` of the file.
+fn load_zone(variables: &Variables, config_file: Option<&ConfigFile>) -> Result<ZoneConfig, Error> {
+    let domain: String = variables.require("DOMAIN")?;
+
+    let default_record_types: Vec<RecordType> = parse_record_types(&variables.get_or("DNS_RECORD_TYPES", "A"))
+        .map_err(|error| Error::new(format!("{} must be a + separated list of A and AAAA: {}", variables.origin("DNS_RECORD_TYPES"), error)))?;
+
+    // The records of the file are replaced as a whole by DNS_ENTRIES_TO_SYNC.
+    let dns_entries_to_sync: Vec<SyncEntry> = match config_file {
+        Some(config_file) if !variables.is_set("DNS_ENTRIES_TO_SYNC") && !config_file.config.records.is_empty() => {
+            config_file.sync_entries(&default_record_types)?
+        },
+        _ => variables.get_or("DNS_ENTRIES_TO_SYNC", "")
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| SyncEntry::parse(s, &default_record_types))
+            .collect::<Result<_, _>>()
+            .map_err(|error| Error::new(format!("Invalid value for DNS_ENTRIES_TO_SYNC: {}", error)))?,
+    };
+
+    if dns_entries_to_sync.is_empty() {
+        return Err(Error::new("No entries configured, set DNS_ENTRIES_TO_SYNC or add [[records]] to the configuration file".to_string()));
+    }
+
+    let dns_provider_selection = DnsProviderSelection::load(&variables.require("DNS_PROVIDER")?, variables)?;
+
+    let dns_provider: DnsProvider = DnsProvider {
+        config: dns_provider_selection
+    };
+
+    Ok(ZoneConfig {
+        name: domain.clone(),
         domain,
-        dns_entries_to_sync,
         dns_provider,
-        refresh_interval_seconds
-    };
+        dns_entries_to_sync,
+    })
+}
+
+/// Load the `[[zones]]` of the file, settings a zone leaves out fall back to the global ones.
+fn load_file_zones(config_file: &ConfigFile, variables: &Variables) -> Result<Vec<ZoneConfig>, Error> {
+    let mut zones: Vec<ZoneConfig> = vec![];
+
+    for key in ZONE_IDENTITY_KEYS.iter().filter(|key| variables.get(key).is_some()) {
+        tracing::warn!("{} is ignored by the zones of {}, set it on each zone instead", variables.origin(key), config_file.path.display());
+    }
+
+    for (index, file_zone) in config_file.config.zones.iter().enumerate() {
+        let prefix = format!("zones[{}]", index);
+        let zone_variables = variables.for_zone(prefix.clone(), config_file.zone_variables(file_zone));
+
+        let domain: String = zone_variables.require("DOMAIN")?;
+
+        let default_record_types: Vec<RecordType> = parse_record_types(&zone_variables.get_or("DNS_RECORD_TYPES", "A"))
+            .map_err(|error| Error::new(format!("{} must be a + separated list of A and AAAA: {}", zone_variables.origin("DNS_RECORD_TYPES"), error)))?;
+
+        if file_zone.records.is_empty() {
+            return Err(Error::new(format!("{}.records in {} must not be empty", prefix, config_file.path.display())));
+        }
+
+        let dns_entries_to_sync = config_file.records_to_entries(&format!("{}.records", prefix), &file_zone.records, &default_record_types)?;
+
+        let dns_provider_selection = DnsProviderSelection::load(&zone_variables.require("DNS_PROVIDER")?, &zone_variables)
+            .map_err(|error| if error.message.starts_with(&prefix) { error } else { Error::new(format!("{}: {}", prefix, error)) })?;
+
+        let name = file_zone.name.clone().unwrap_or_else(|| domain.clone());
+
+        if zones.iter().any(|zone| zone.name == name) {
+            return Err(Error::new(format!("{} in {} has the same name as another zone: {}", prefix, config_file.path.display(), name)));
+        }
+
+        zones.push(ZoneConfig {
+            name,
+            domain,
+            dns_provider: DnsProvider {
+                config: dns_provider_selection
+            },
+            dns_entries_to_sync,
+        });
+    }
+
+    Ok(zones)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::dns_providers::{AwsCredentials, TsigAlgorithm};
+    use crate::file::FileConfig;
+
+    fn config_file(contents: &str) -> ConfigFile {
+        ConfigFile {
+            path: PathBuf::from("dynamic-dns.toml"),
+            config: FileConfig::from_toml(contents).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_load_file_zones() {
+        let file = config_file(r#"
+dns_provider = "cloudflare"
+dns_record_types = ["AAAA"]
+
+[cloudflare]
+api_key = "shared"
+
+[[zones]]
+domain = "example.com"
+cloudflare = { zone_id = "one" }
+records = [{ name = "www" }]
+
+[[zones]]
+name = "lab"
+domain = "example.org"
+dns_record_types = ["A"]
+cloudflare = { zone_id = "two", api_key = "lab" }
+records = [{ name = "@" }]
+"#);
+        let overrides = HashMap::new();
+
+        let zones = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap();
+
+        assert_eq!(zones.iter().map(|zone| zone.name.as_str()).collect::<Vec<_>>(), vec!["example.com", "lab"]);
+        assert_eq!(zones[0].dns_entries_to_sync[0].record_types, vec![RecordType::AAAA]);
+        assert_eq!(zones[1].dns_entries_to_sync[0].record_types, vec![RecordType::A]);
+
+        let DnsProviderSelection::Cloudflare(first) = &zones[0].dns_provider.config else { panic!("expected Cloudflare") };
+        let DnsProviderSelection::Cloudflare(second) = &zones[1].dns_provider.config else { panic!("expected Cloudflare") };
+        assert_eq!((first.zone_id.as_deref(), first.api_key.as_str()), (Some("one"), "shared"));
+        assert_eq!((second.zone_id.as_deref(), second.api_key.as_str()), (Some("two"), "lab"));
+    }
+
+    #[test]
+    fn test_load_file_zones_zone_id_is_optional() {
+        let file = config_file(r#"
+[[zones]]
+domain = "example.com"
+dns_provider = "cloudflare"
+cloudflare = { api_key = "key" }
+records = [{ name = "www" }]
+"#);
+        let overrides = HashMap::new();
+
+        let zones = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap();
+
+        let DnsProviderSelection::Cloudflare(settings) = &zones[0].dns_provider.config else { panic!("expected Cloudflare") };
+        assert_eq!(settings.zone_id, None);
+    }
+
+    #[test]
+    fn test_load_file_zones_route53() {
+        let file = config_file(r#"
+dns_provider = "route53"
+
+[route53]
+credentials_file = "/etc/dynamic-dns/aws"
+
+[[zones]]
+domain = "example.com"
+route53 = { hosted_zone_id = "Z1", ttl = 60 }
+records = [{ name = "www" }]
+
+[[zones]]
+domain = "example.org"
+route53 = { access_key_id = "AKID", secret_access_key = "secret" }
+records = [{ name = "www" }]
+"#);
+        let overrides = HashMap::new();
+
+        let zones = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap();
+
+        let DnsProviderSelection::Route53(first) = &zones[0].dns_provider.config else { panic!("expected Route 53") };
+        assert_eq!((first.hosted_zone_id.as_deref(), first.ttl), (Some("Z1"), 60));
+        assert_eq!(first.credentials, AwsCredentials::Profile {
+            name: "default".to_string(),
+            path: PathBuf::from("/etc/dynamic-dns/aws"),
+        });
+
+        let DnsProviderSelection::Route53(second) = &zones[1].dns_provider.config else { panic!("expected Route 53") };
+        assert_eq!((second.hosted_zone_id.as_deref(), second.ttl), (None, 300));
+        assert_eq!(second.credentials, AwsCredentials::Keys {
+            access_key_id: "AKID".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+        });
+    }
+
+    #[test]
+    fn test_load_file_zones_rfc2136() {
+        let file = config_file(r#"
+dns_provider = "rfc2136"
+
+[rfc2136]
+server = "192.0.2.53"
+tsig_key_name = "ddns-key"
+tsig_secret = "c2VjcmV0"
+
+[[zones]]
+domain = "example.com"
+rfc2136 = { tsig_algorithm = "hmac-sha512", ttl = 60 }
+records = [{ name = "www" }]
+"#);
+        let overrides = HashMap::new();
+
+        let zones = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap();
+
+        let DnsProviderSelection::Rfc2136(settings) = &zones[0].dns_provider.config else { panic!("expected RFC 2136") };
+        assert_eq!((settings.server.as_str(), settings.zone.as_deref(), settings.ttl), ("192.0.2.53:53", None, 60));
+        let tsig_key = settings.tsig_key.as_ref().unwrap();
+        assert_eq!((tsig_key.name.as_str(), tsig_key.algorithm, tsig_key.secret.as_slice()), ("ddns-key", TsigAlgorithm::HmacSha512, b"secret".as_slice()));
+
+        let file = config_file(r#"
+[[zones]]
+domain = "example.org"
+dns_provider = "rfc2136"
+rfc2136 = { server = "ns1.example.org", tsig_key_name = "ddns-key", tsig_secret = "not base64!" }
+records = [{ name = "www" }]
+"#);
+
+        let error = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap_err();
+        assert_eq!(error.message, "zones[0].rfc2136.tsig_secret in dynamic-dns.toml must be base64 encoded: Invalid symbol 32, offset 3.");
+    }
+
+    #[test]
+    fn test_load_zone_skips_empty_entries() {
+        let overrides = HashMap::from([
+            ("DOMAIN".to_string(), "example.com".to_string()),
+            ("DNS_PROVIDER".to_string(), "dyndns2".to_string()),
+            ("DYNDNS2_URL".to_string(), "https://ddns.example.org/nic/update".to_string()),
+            ("DYNDNS2_USERNAME".to_string(), "user".to_string()),
+            ("DYNDNS2_PASSWORD".to_string(), "pass".to_string()),
+            ("DNS_ENTRIES_TO_SYNC".to_string(), "www, ,api,".to_string()),
+        ]);
+
+        let zone = load_zone(&Variables::new(&overrides, None), None).unwrap();
+        let names: Vec<&str> = zone.dns_entries_to_sync.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["www", "api"]);
+
+        let mut overrides = overrides;
+        overrides.insert("DNS_ENTRIES_TO_SYNC".to_string(), " , ".to_string());
+        assert!(load_zone(&Variables::new(&overrides, None), None).unwrap_err().message.starts_with("No entries configured"));
+    }
+
+    #[test]
+    fn test_load_file_zones_dyndns2() {
+        let file_source = r#"
+dns_provider = "dyndns2"
+
+[dyndns2]
+username = "user"
+password = "pass"
+
+[[zones]]
+domain = "example.com"
+dyndns2 = { url = "https://dynupdate.no-ip.com/nic/update" }
+records = [{ name = "home" }]
+
+[[zones]]
+domain = "example.org"
+records = [{ name = "home" }]
+"#;
+        let file = config_file(file_source);
+        let overrides = HashMap::new();
+
+        let error = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap_err();
+        assert_eq!(error.message, "zones[1]: DYNDNS2_URL must be set");
+
+        let file = config_file(&file_source.replace("password = \"pass\"", "password = \"pass\"\nurl = \"https://ddns.example.org/nic/update\""));
+        let zones = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap();
+
+        let DnsProviderSelection::Dyndns2(settings) = &zones[0].dns_provider.config else { panic!("expected dyndns2") };
+        assert_eq!((settings.url.as_str(), settings.username.as_str(), settings.password.as_str()), ("https://dynupdate.no-ip.com/nic/update", "user", "pass"));
+        let DnsProviderSelection::Dyndns2(settings) = &zones[1].dns_provider.config else { panic!("expected dyndns2") };
+        assert_eq!(settings.url, "https://ddns.example.org/nic/update");
+    }
+
+    #[test]
+    fn test_load_file_zones_digitalocean_and_hetzner() {
+        let file = config_file(r#"
+[[zones]]
+domain = "staging.example.com"
+dns_provider = "digitalocean"
+digitalocean = { api_token = "do-token" }
+records = [{ name = "api" }]
 
-    app_state
+[[zones]]
+domain = "staging.example.org"
+dns_provider = "hetzner"
+hetzner = { api_token = "hetzner-token", zone_id = "HBhuAj8UXBkCPbfwk7E7Zn" }
+records = [{ name = "api" }]
+"#);
+        let overrides = HashMap::new();
+
+        let zones = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap();
+
+        let DnsProviderSelection::DigitalOcean(settings) = &zones[0].dns_provider.config else { panic!("expected DigitalOcean") };
+        assert_eq!((settings.api_url.as_str(), settings.zone.as_deref(), settings.api_token.as_str()), ("https://api.digitalocean.com/v2", None, "do-token"));
+        let DnsProviderSelection::Hetzner(settings) = &zones[1].dns_provider.config else { panic!("expected Hetzner") };
+        assert_eq!((settings.api_url.as_str(), settings.zone_id.as_deref(), settings.api_token.as_str()), ("https://dns.hetzner.com/api/v1", Some("HBhuAj8UXBkCPbfwk7E7Zn"), "hetzner-token"));
+    }
+
+    #[test]
+    fn test_load_file_zones_powerdns() {
+        let file = config_file(r#"
+[powerdns]
+api_url = "http://127.0.0.1:8081"
+api_key = "secret"
+
+[[zones]]
+domain = "example.com"
+dns_provider = "powerdns"
+records = [{ name = "home" }]
+
+[[zones]]
+domain = "lab.example.org"
+dns_provider = "powerdns"
+powerdns = { server_id = "primary", zone = "example.org", ttl = 60 }
+records = [{ name = "api" }]
+"#);
+        let overrides = HashMap::new();
+
+        let zones = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap();
+
+        let DnsProviderSelection::PowerDns(settings) = &zones[0].dns_provider.config else { panic!("expected PowerDNS") };
+        assert_eq!((settings.api_url.as_str(), settings.api_key.as_str(), settings.server_id.as_str(), settings.zone.as_deref(), settings.ttl), ("http://127.0.0.1:8081", "secret", "localhost", None, 300));
+        let DnsProviderSelection::PowerDns(settings) = &zones[1].dns_provider.config else { panic!("expected PowerDNS") };
+        assert_eq!((settings.api_key.as_str(), settings.server_id.as_str(), settings.zone.as_deref(), settings.ttl), ("secret", "primary", Some("example.org"), 60));
+    }
+
+    #[test]
+    fn test_load_ip_sources_quorum() {
+        let overrides = HashMap::from([
+            ("IP_SOURCES".to_string(), "ipify,icanhazip".to_string()),
+            ("IP_SOURCES_QUORUM".to_string(), "2".to_string()),
+        ]);
+        let (ip_sources, quorum) = load_ip_sources(&Variables::new(&overrides, None)).unwrap();
+        assert_eq!((ip_sources.len(), quorum), (2, 2));
+
+        let overrides = HashMap::from([("IP_SOURCES_QUORUM".to_string(), "2".to_string())]);
+        let error = load_ip_sources(&Variables::new(&overrides, None)).unwrap_err();
+        assert_eq!(error.message, "IP_SOURCES_QUORUM must be between 1 and the number of IP sources (1), got 2");
+
+        let overrides = HashMap::from([("IP_SOURCES_QUORUM".to_string(), "0".to_string())]);
+        assert!(load_ip_sources(&Variables::new(&overrides, None)).is_err());
+    }
+
+    #[test]
+    fn test_load_ownership() {
+        let overrides = HashMap::from([("OWNER_ID".to_string(), "home-router".to_string())]);
+        let ownership = load_ownership(&Variables::new(&overrides, None)).unwrap();
+        assert_eq!((ownership.owner_id.as_deref(), ownership.prune), (Some("home-router"), false));
+
+        let file = config_file("prune = true\n");
+        let error = load_ownership(&Variables::new(&HashMap::new(), Some(&file))).unwrap_err();
+        assert_eq!(error.message, "prune in dynamic-dns.toml requires OWNER_ID to be set");
+
+        let overrides = HashMap::from([("OWNER_ID".to_string(), "home router".to_string())]);
+        assert!(load_ownership(&Variables::new(&overrides, None)).is_err());
+    }
+
+    #[test]
+    fn test_load_file_zones_rejects_zone_without_records() {
+        let file = config_file("[[zones]]\ndomain = \"example.com\"\n");
+        let overrides = HashMap::new();
+
+        let error = load_file_zones(&file, &Variables::new(&overrides, Some(&file))).unwrap_err();
+
+        assert_eq!(error.message, "zones[0].records in dynamic-dns.toml must not be empty");
+    }
 }
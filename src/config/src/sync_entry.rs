@@ -0,0 +1,147 @@
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+/// The address record types the daemon can keep in sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RecordType {
+    /// IPv4 address record.
+    A,
+    /// IPv6 address record.
+    AAAA,
+}
+
+impl RecordType {
+    /// The record type able to hold the given address.
+    pub fn of(ip: &IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => RecordType::A,
+            IpAddr::V6(_) => RecordType::AAAA,
+        }
+    }
+}
+
+impl FromStr for RecordType {
+    type Err = String;
+
+    fn from_str(raw_input: &str) -> Result<Self, Self::Err> {
+        match raw_input.trim().to_uppercase().as_str() {
+            "A" => Ok(RecordType::A),
+            "AAAA" => Ok(RecordType::AAAA),
+            _ => Err(format!("Unsupported record type: {}", raw_input)),
+        }
+    }
+}
+
+impl Display for RecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RecordType::A => write!(f, "A"),
+            RecordType::AAAA => write!(f, "AAAA"),
+        }
+    }
+}
+
+/// Parse a `+` separated list of record types, e.g. `A+AAAA`.
+pub fn parse_record_types(raw_input: &str) -> Result<Vec<RecordType>, String> {
+    let mut record_types: Vec<RecordType> = vec![];
+
+    for raw_type in raw_input.split('+') {
+        let record_type: RecordType = raw_type.parse()?;
+
+        if !record_types.contains(&record_type) {
+            record_types.push(record_type);
+        }
+    }
+
+    Ok(record_types)
+}
+
+/// Entry name of the record at the domain itself.
+pub const APEX: &str = "@";
+
+/// Per record settings applied on top of the address. Unset settings are left as they are in
+/// the zone, or use the provider default when the record is created.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordSettings {
+    /// Time to live in seconds.
+    pub ttl: Option<u64>,
+    /// Whether traffic is proxied by the DNS provider, where supported.
+    pub proxied: Option<bool>,
+    /// Free form comment stored with the record, where supported.
+    pub comment: Option<String>,
+    /// Tags stored with the record, where supported. An empty list leaves the tags as they are.
+    pub tags: Vec<String>,
+}
+
+/// A single entry of `DNS_ENTRIES_TO_SYNC`, the subdomain together with the address families
+/// that should be managed for it.
+///
+/// Entries are written as `name` to use the default record types, or `name:TYPES` where
+/// `TYPES` is a `+` separated list such as `AAAA` or `A+AAAA`. Record settings can only be set
+/// from the configuration file.
+///
+/// The name is relative to the domain of the zone, `@` is the domain itself and a name ending
+/// with `.` is already fully qualified.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncEntry {
+    pub name: String,
+    pub record_types: Vec<RecordType>,
+    pub settings: RecordSettings,
+}
+
+impl SyncEntry {
+    /// Parse a sync entry, falling back to `default_record_types` when the entry does not
+    /// specify its own.
+    pub fn parse(raw_input: &str, default_record_types: &[RecordType]) -> Result<Self, String> {
+        let (name, record_types) = match raw_input.split_once(':') {
+            Some((name, raw_types)) => (name, parse_record_types(raw_types)?),
+            None => (raw_input, default_record_types.to_vec()),
+        };
+
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(format!("Entry {:?} has no name, use @ for the domain itself", raw_input));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            record_types,
+            settings: RecordSettings::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry_with_default_types() {
+        let entry = SyncEntry::parse("www", &[RecordType::A]).unwrap();
+
+        assert_eq!(entry.name, "www");
+        assert_eq!(entry.record_types, vec![RecordType::A]);
+    }
+
+    #[test]
+    fn test_parse_entry_with_types() {
+        let entry = SyncEntry::parse("*.dev:a+AAAA", &[RecordType::A]).unwrap();
+
+        assert_eq!(entry.name, "*.dev");
+        assert_eq!(entry.record_types, vec![RecordType::A, RecordType::AAAA]);
+    }
+
+    #[test]
+    fn test_parse_entry_with_invalid_type() {
+        assert!(SyncEntry::parse("www:MX", &[RecordType::A]).is_err());
+    }
+
+    #[test]
+    fn test_parse_entry_without_name() {
+        assert!(SyncEntry::parse("", &[RecordType::A]).is_err());
+        assert!(SyncEntry::parse(" :AAAA", &[RecordType::A]).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+use crate::error::Error;
+use crate::file::{ConfigFile, FileValue};
+use crate::StripQuotes;
+
+/// Variables naming the zone a provider updates. A zone of the configuration file only takes
+/// them from its own settings, a global value would point every zone at the same zone.
+pub(crate) const ZONE_IDENTITY_KEYS: [&str; 7] = [
+    "DOMAIN",
+    "CLOUDFLARE_ZONE_ID",
+    "ROUTE53_HOSTED_ZONE_ID",
+    "RFC2136_ZONE",
+    "DIGITALOCEAN_ZONE",
+    "HETZNER_ZONE_ID",
+    "POWERDNS_ZONE",
+];
+
+/// The configuration variables, looked up in the overrides first (such as command line flags),
+/// in the environment second and in the configuration file last. Values have their surrounding
+/// quotes stripped and empty values are treated as unset.
+///
+/// Variables scoped to a zone of the configuration file look up the settings of the zone right
+/// after the overrides, so every zone can have its own domain and provider credentials. The
+/// [`ZONE_IDENTITY_KEYS`] of such variables only come from the zone.
+pub(crate) struct Variables<'a> {
+    overrides: &'a HashMap<String, String>,
+    file: Option<(PathBuf, HashMap<String, FileValue>)>,
+    zone: Option<(String, HashMap<String, FileValue>)>,
+}
+
+impl<'a> Variables<'a> {
+    pub(crate) fn new(overrides: &'a HashMap<String, String>, file: Option<&ConfigFile>) -> Self {
+        Self {
+            overrides,
+            file: file.map(|file| (file.path.clone(), file.variables())),
+            zone: None,
+        }
+    }
+
+    /// The variables of a zone, `prefix` is the path of the zone in the file such as `zones[0]`.
+    pub(crate) fn for_zone(&self, prefix: String, values: HashMap<String, FileValue>) -> Variables<'a> {
+        Variables {
+            overrides: self.overrides,
+            file: self.file.clone(),
+            zone: Some((prefix, values)),
+        }
+    }
+
+    fn get_from_zone(&self, key: &str) -> Option<&FileValue> {
+        self.zone.as_ref().and_then(|(_, values)| values.get(key))
+    }
+
+    fn get_override(&self, key: &str) -> Option<String> {
+        self.overrides.get(key)
+            .map(|value| value.strip_quotes())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn get_env(&self, key: &str) -> Option<String> {
+        env::var(key).ok()
+            .map(|value| value.strip_quotes())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn get_from_file(&self, key: &str) -> Option<&FileValue> {
+        self.file.as_ref().and_then(|(_, values)| values.get(key))
+    }
+
+    /// Whether the variable is set on the command line or in the environment, ignoring the file.
+    pub(crate) fn is_set(&self, key: &str) -> bool {
+        self.get_override(key).or_else(|| self.get_env(key)).is_some()
+    }
+
+    /// The value of the variable, if set.
+    pub(crate) fn get(&self, key: &str) -> Option<String> {
+        if self.zone.is_some() && ZONE_IDENTITY_KEYS.contains(&key) {
+            return self.get_from_zone(key).map(|file_value| file_value.value.clone());
+        }
+
+        self.get_override(key)
+            .or_else(|| self.get_from_zone(key).map(|file_value| file_value.value.clone()))
+            .or_else(|| self.get_env(key))
+            .or_else(|| self.get_from_file(key).map(|file_value| file_value.value.clone()))
+    }
+
+    /// Where the value of the variable comes from, used to point errors at the offending key.
+    pub(crate) fn origin(&self, key: &str) -> String {
+        let path = self.file.as_ref().map(|(path, _)| path.display().to_string()).unwrap_or_default();
+        let zone_only = self.zone.is_some() && ZONE_IDENTITY_KEYS.contains(&key);
+
+        if !zone_only && self.get_override(key).is_some() {
+            return key.to_string();
+        }
+
+        if let (Some((prefix, _)), Some(file_value)) = (&self.zone, self.get_from_zone(key)) {
+            return format!("{}.{} in {}", prefix, file_value.key, path);
+        }
+
+        match (self.get_env(key), self.get_from_file(key)) {
+            (None, Some(file_value)) => format!("{} in {}", file_value.key, path),
+            _ => key.to_string(),
+        }
+    }
+
+    /// The value of the variable, or the default when unset.
+    pub(crate) fn get_or(&self, key: &str, default: &str) -> String {
+        self.get(key).unwrap_or_else(|| default.to_string())
+    }
+
+    /// The value of a variable that must be set.
+    pub(crate) fn require(&self, key: &str) -> Result<String, Error> {
+        self.get(key).ok_or_else(|| Error::new(format!("{} must be set", key)))
+    }
+
+    /// Parse the value of the variable, or the default when unset.
+    pub(crate) fn parse_or<T>(&self, key: &str, default: &str, expected: &str) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.get_or(key, default);
+
+        value.parse().map_err(|error| {
+            Error::new(format!("{} must be {}, got {:?}: {}", self.origin(key), expected, value, error))
+        })
+    }
+
+    /// Parse a boolean variable, case insensitive.
+    pub(crate) fn parse_bool_or(&self, key: &str, default: bool) -> Result<bool, Error> {
+        let value = self.get(key).map(|value| value.to_lowercase());
+
+        match value.as_deref() {
+            None => Ok(default),
+            Some("true") => Ok(true),
+            Some("false") => Ok(false),
+            Some(other) => Err(Error::new(format!("{} must be a boolean, got {:?}", self.origin(key), other))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::FileConfig;
+
+    #[test]
+    fn test_overrides_win_and_quotes_are_stripped() {
+        let overrides = HashMap::from([
+            ("DYNAMIC_DNS_TEST_VALUE".to_string(), "\"42\"".to_string()),
+            ("DYNAMIC_DNS_TEST_EMPTY".to_string(), "".to_string()),
+        ]);
+        let variables = Variables::new(&overrides, None);
+
+        assert_eq!(variables.get("DYNAMIC_DNS_TEST_VALUE"), Some("42".to_string()));
+        assert_eq!(variables.get("DYNAMIC_DNS_TEST_EMPTY"), None);
+        assert_eq!(variables.parse_or::<u64>("DYNAMIC_DNS_TEST_VALUE", "1", "a number").unwrap(), 42);
+        assert_eq!(variables.parse_or::<u64>("DYNAMIC_DNS_TEST_UNSET", "1", "a number").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_errors_name_the_variable() {
+        let overrides = HashMap::from([
+            ("DYNAMIC_DNS_TEST_NUMBER".to_string(), "ten".to_string()),
+            ("DYNAMIC_DNS_TEST_BOOL".to_string(), "TRUE".to_string()),
+        ]);
+        let variables = Variables::new(&overrides, None);
+
+        let error = variables.parse_or::<u64>("DYNAMIC_DNS_TEST_NUMBER", "1", "a number").unwrap_err();
+        assert!(error.message.starts_with("DYNAMIC_DNS_TEST_NUMBER must be a number"));
+
+        let error = variables.require("DYNAMIC_DNS_TEST_UNSET").unwrap_err();
+        assert_eq!(error.message, "DYNAMIC_DNS_TEST_UNSET must be set");
+
+        assert!(variables.parse_bool_or("DYNAMIC_DNS_TEST_BOOL", false).unwrap());
+        assert!(variables.parse_bool_or("DYNAMIC_DNS_TEST_NUMBER", false).is_err());
+    }
+
+    #[test]
+    fn test_file_is_used_last_and_named_in_errors() {
+        let file = ConfigFile {
+            path: PathBuf::from("dynamic-dns.toml"),
+            config: FileConfig::from_toml("ip_sources_quorum = 3\n[retry]\nmax_attempts = 0\n").unwrap(),
+        };
+        let overrides = HashMap::from([("IP_SOURCES_QUORUM".to_string(), "2".to_string())]);
+        let variables = Variables::new(&overrides, Some(&file));
+
+        assert_eq!(variables.get("IP_SOURCES_QUORUM"), Some("2".to_string()));
+        assert_eq!(variables.origin("IP_SOURCES_QUORUM"), "IP_SOURCES_QUORUM");
+        assert_eq!(variables.origin("RETRY_MAX_ATTEMPTS"), "retry.max_attempts in dynamic-dns.toml");
+
+        let error = variables.parse_or::<std::num::NonZeroU32>("RETRY_MAX_ATTEMPTS", "5", "a positive number").unwrap_err();
+        assert!(error.message.starts_with("retry.max_attempts in dynamic-dns.toml must be a positive number"));
+    }
+
+    #[test]
+    fn test_zone_domain_wins_over_everything() {
+        let file = ConfigFile {
+            path: PathBuf::from("dynamic-dns.toml"),
+            config: FileConfig::from_toml("domain = \"example.com\"\n[[zones]]\ndomain = \"example.org\"\n").unwrap(),
+        };
+        let overrides = HashMap::from([("DOMAIN".to_string(), "example.net".to_string())]);
+        let variables = Variables::new(&overrides, Some(&file));
+        let zone_variables = variables.for_zone("zones[0]".to_string(), file.zone_variables(&file.config.zones[0]));
+
+        assert_eq!(variables.get("DOMAIN"), Some("example.net".to_string()));
+        assert_eq!(zone_variables.get("DOMAIN"), Some("example.org".to_string()));
+        assert_eq!(zone_variables.origin("DOMAIN"), "zones[0].domain in dynamic-dns.toml");
+    }
+
+    #[test]
+    fn test_zone_identity_is_not_inherited() {
+        let file = ConfigFile {
+            path: PathBuf::from("dynamic-dns.toml"),
+            config: FileConfig::from_toml("[hetzner]\nzone_id = \"global\"\napi_token = \"token\"\n[[zones]]\ndomain = \"example.org\"\n").unwrap(),
+        };
+        let overrides = HashMap::from([("CLOUDFLARE_ZONE_ID".to_string(), "leftover".to_string())]);
+        let variables = Variables::new(&overrides, Some(&file));
+        let zone_variables = variables.for_zone("zones[0]".to_string(), file.zone_variables(&file.config.zones[0]));
+
+        assert_eq!(zone_variables.get("CLOUDFLARE_ZONE_ID"), None);
+        assert_eq!(zone_variables.get("HETZNER_ZONE_ID"), None);
+        assert_eq!(zone_variables.get("HETZNER_API_TOKEN"), Some("token".to_string()));
+    }
+
+    #[test]
+    fn test_overrides_win_over_the_file() {
+        let file = ConfigFile {
+            path: PathBuf::from("dynamic-dns.toml"),
+            config: FileConfig::from_toml("[hetzner]\napi_token = \"global\"\n[[zones]]\ndomain = \"example.org\"\nhetzner = { api_token = \"zone\" }\n").unwrap(),
+        };
+        let overrides = HashMap::from([("HETZNER_API_TOKEN".to_string(), "flag".to_string())]);
+        let variables = Variables::new(&overrides, Some(&file));
+        let zone_variables = variables.for_zone("zones[0]".to_string(), file.zone_variables(&file.config.zones[0]));
+
+        assert_eq!(variables.get("HETZNER_API_TOKEN"), Some("flag".to_string()));
+        assert_eq!(zone_variables.get("HETZNER_API_TOKEN"), Some("flag".to_string()));
+        assert_eq!(zone_variables.origin("HETZNER_API_TOKEN"), "HETZNER_API_TOKEN");
+    }
+}
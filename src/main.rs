@@ -1,92 +1,269 @@
 mod app_error;
+mod cli;
 
-use config::AppState;
-use services::dns_record::DnsRecord;
-use services::error::Error;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::process::ExitCode;
+use clap::Parser;
+use clients::exponential_backoff::RetryPolicy;
+use clients::http::HttpClient;
+use config::{AppState, OwnershipSettings, PlanFormat, ZoneConfig};
+use config::sync_entry::RecordType;
+use futures::future::join_all;
+use services::dns_record::DomainName;
+use services::ip_detection::IpDetector;
+use services::provider::DnsProviderBackend;
+use services::sync::{self, PublicIps, ZonePlan};
 use crate::app_error::AppError;
+use crate::cli::{Cli, Command};
 
-async fn workflow(config: &AppState) -> Result<(), AppError> {
-    let fetch_public_ip: Result<String, Error> = services::resolve_public_ip().await;
-    let public_ip: String = match fetch_public_ip {
-        Ok(ip) => ip,
-        Err(error) => {
-            tracing::error!("Failed to fetch public IP address: {:?}", error);
-            return Err(AppError::new("Failed to fetch public IP address".to_string()));
-        }
-    };
+/// A configured zone together with the provider backend it is synced with.
+struct Zone {
+    config: ZoneConfig,
+    domain: DomainName,
+    backend: Box<dyn DnsProviderBackend>,
+}
+
+/// Check a zone without contacting its provider: the domain, the names of the entries and
+/// whether the provider can mark and delete records when `OWNER_ID` and `PRUNE` ask for it.
+///
+/// # Returns
+/// The domain of the zone.
+fn check_zone(zone: &ZoneConfig, ownership: &OwnershipSettings) -> Result<DomainName, AppError> {
+    let domain = DomainName::parse(&zone.domain).map_err(|error| {
+        AppError::new(format!("Invalid domain of zone {}: {}", zone.name, error))
+    })?;
+
+    for entry in &zone.dns_entries_to_sync {
+        DomainName::resolve(&entry.name, &domain).map_err(|error| {
+            AppError::new(format!("Invalid entry {:?} of zone {}: {}", entry.name, zone.name, error))
+        })?;
+    }
+
+    let (provider, capabilities) = services::provider::backend_capabilities(&zone.dns_provider);
+    if ownership.owner_id.is_some() && !capabilities.ownership {
+        return Err(AppError::new(format!("OWNER_ID is set but {}, the provider of zone {}, cannot mark records", provider, zone.name)));
+    }
+    if ownership.prune && !capabilities.delete {
+        return Err(AppError::new(format!("PRUNE is set but {}, the provider of zone {}, cannot delete records", provider, zone.name)));
+    }
+
+    Ok(domain)
+}
+
+/// Build the provider backend of every zone, once at startup.
+async fn build_zones(config: &AppState, http: &HttpClient) -> Result<Vec<Zone>, AppError> {
+    let mut zones: Vec<Zone> = vec![];
+
+    for zone in &config.zones {
+        let domain = check_zone(zone, &config.ownership)?;
+
+        let backend = services::provider::build_backend(&zone.dns_provider, domain.as_str(), http.clone()).await.map_err(|error| {
+            AppError::new(format!("Failed to set up zone {}: {}", zone.name, error))
+        })?;
+
+        zones.push(Zone {
+            config: zone.clone(),
+            domain,
+            backend,
+        });
+    }
+
+    Ok(zones)
+}
+
+/// Resolve the public addresses of every record type used by any zone.
+async fn resolve_public_ips(config: &AppState, ip_detector: &IpDetector) -> Result<PublicIps, AppError> {
+    let public_ips = sync::resolve_public_ips(ip_detector, &ZoneConfig::all_entries(&config.zones)).await;
+
+    if public_ips.addresses.is_empty() {
+        return Err(AppError::new("Failed to fetch public IP address".to_string()));
+    }
+
+    Ok(public_ips)
+}
 
-    let fetch_a_records = services::get_dns_records(&config.dns_provider).await;
+/// Fetch the existing records of the zone and plan the changes.
+async fn plan_zone(zone: &Zone, public_ips: &HashMap<RecordType, IpAddr>, config: &AppState) -> Result<ZonePlan, AppError> {
+    let fetch_address_records = services::get_dns_records(zone.backend.as_ref()).await;
 
-    let a_records = match fetch_a_records {
+    let address_records = match fetch_address_records {
         Ok(records) => records,
         Err(error) => {
-            tracing::error!("Failed to fetch domain records: {:?}", error);
-            return Err(AppError::new("Failed to fetch domain records".to_string()));
+            tracing::error!("Failed to fetch domain records of zone {}: {:?}", zone.config.name, error);
+            return Err(AppError::new(format!("Failed to fetch domain records of zone {}", zone.config.name)));
         }
     };
 
-    tracing::debug!("A Records: {:?}", a_records.keys());
+    tracing::debug!("Address Records of zone {}: {:?}", zone.config.name, address_records.keys());
 
-    // Check each subdomain to see if it exists in the map. If it exists, ensure the IP is correct.
-    // If it is correct, do nothing. If it is incorrect, update the record.
-    // If it is missing then create the record, We do not remove or touch any other records.
-    let sync_entries = config.dns_entries_to_sync.clone();
+    let entries = sync::provider_entries(zone.backend.as_ref(), &zone.config.dns_entries_to_sync);
+    let plan = sync::build_plan(&zone.domain, &entries, public_ips, &address_records, &config.ownership, config.duplicate_policy).map_err(|error| {
+        AppError::new(format!("Failed to plan zone {}: {}", zone.config.name, error))
+    })?;
 
-    let mut update_records: Vec<DnsRecord> = vec![];
+    Ok(ZonePlan {
+        zone: zone.config.name.clone(),
+        plan,
+    })
+}
 
-    for entry in &sync_entries {
-        tracing::debug!("Checking entry: {}", entry);
-        let fqdn = format!("{}.{}", entry, config.domain);
+async fn sync_zone(zone: &Zone, public_ips: &HashMap<RecordType, IpAddr>, config: &AppState) -> Result<(), AppError> {
+    let zone_plan = plan_zone(zone, public_ips, config).await?;
 
-        if !a_records.contains_key(&fqdn) {
-            tracing::info!("Missing record: {}", entry);
-            let new_record = DnsRecord::build_record(entry, &public_ip, None);
-            update_records.push(new_record);
-        }
+    tracing::info!("{} records of zone {} need to change", zone_plan.plan.pending().count(), zone.config.name);
+    tracing::debug!("Records to update: {:?}", zone_plan.plan.pending().collect::<Vec<_>>());
+
+    let failed_updates = sync::apply_plan(zone.backend.as_ref(), &zone_plan.plan).await;
 
-        if let Some(record) = a_records.get(&fqdn) {
-            let updated_record = DnsRecord::build_record(entry, &public_ip, Some(record));
+    if failed_updates > 0 {
+        return Err(AppError::new(format!("Failed to update {} records of zone {}", failed_updates, zone.config.name)));
+    }
 
-            tracing::debug!("Checking existing record: {}", record);
-            tracing::debug!("Checking against desired record: {}", updated_record);
+    if !zone_plan.plan.conflicts.is_empty() {
+        return Err(AppError::new(format!("Refused to sync {} names with duplicate records in zone {}", zone_plan.plan.conflicts.len(), zone.config.name)));
+    }
 
-            if updated_record != *record {
-                update_records.push(updated_record);
-            }
+    Ok(())
+}
+
+/// Sync every zone concurrently, a failing zone does not affect the others.
+async fn workflow(config: &AppState, ip_detector: &IpDetector, zones: &[Zone]) -> Result<(), AppError> {
+    let public_ips = resolve_public_ips(config, ip_detector).await?;
+
+    let results = join_all(zones.iter().map(|zone| sync_zone(zone, &public_ips.addresses, config))).await;
+
+    let mut failed_zones: usize = 0;
+    for (zone, result) in zones.iter().zip(results) {
+        if let Err(error) = result {
+            tracing::error!("Sync of zone {} failed: {}", zone.config.name, error);
+            failed_zones += 1;
         }
     }
 
-    tracing::info!("{} records need to update", update_records.len());
-    tracing::debug!("Records to update: {:?}", update_records);
+    if !public_ips.failed.is_empty() {
+        return Err(AppError::new("Failed to fetch public IP address for some record types".to_string()));
+    }
+
+    if failed_zones > 0 {
+        return Err(AppError::new(format!("Sync of {} out of {} zones failed", failed_zones, zones.len())));
+    }
+
+    Ok(())
+}
 
-    for record in &update_records {
-        tracing::info!("Updating record: {}", record.name);
+/// Print the plan of every zone without changing any record.
+///
+/// # Returns
+/// Success when every zone is in sync, `2` when drift was found and `1` when planning failed.
+async fn print_plan(config: &AppState, ip_detector: &IpDetector, zones: &[Zone]) -> Result<ExitCode, AppError> {
+    let public_ips = resolve_public_ips(config, ip_detector).await?;
 
-        let update_results = services::ensure_dns_record(&config.dns_provider, record).await;
+    let results = join_all(zones.iter().map(|zone| plan_zone(zone, &public_ips.addresses, config))).await;
 
-        match update_results {
-            Ok(_) => {
-                tracing::info!("{} record updated to IP {}", record.name, public_ip);
+    let mut failed_zones: usize = 0;
+    let mut zone_plans: Vec<ZonePlan> = vec![];
+    for (zone, result) in zones.iter().zip(results) {
+        match result {
+            Ok(zone_plan) => {
+                if !zone_plan.plan.conflicts.is_empty() {
+                    tracing::error!("Zone {} has names with duplicate records", zone.config.name);
+                    failed_zones += 1;
+                }
+                zone_plans.push(zone_plan);
             },
             Err(error) => {
-                tracing::error!("Failed to update record {}: {:?}", record.name, error);
+                tracing::error!("Planning zone {} failed: {}", zone.config.name, error);
+                failed_zones += 1;
             }
         }
     }
 
-    Ok(())
+    match config.plan_format {
+        PlanFormat::Text => {
+            let text: Vec<String> = zone_plans.iter().map(|zone_plan| zone_plan.to_string()).collect();
+            println!("{}", text.join("\n\n"));
+        },
+        PlanFormat::Json => {
+            let json = serde_json::to_string_pretty(&zone_plans).map_err(|error| {
+                AppError::new(format!("Failed to serialize plan: {}", error))
+            })?;
+            println!("{}", json);
+        }
+    }
+
+    if !public_ips.failed.is_empty() {
+        return Err(AppError::new("Failed to fetch public IP address for some record types".to_string()));
+    }
+
+    if failed_zones > 0 {
+        return Err(AppError::new(format!("Planning {} out of {} zones failed", failed_zones, zones.len())));
+    }
+
+    if zone_plans.iter().any(|zone_plan| zone_plan.plan.has_drift()) {
+        return Ok(ExitCode::from(2));
+    }
+
+    Ok(ExitCode::SUCCESS)
 }
 
+/// Print the public address of every record type in use.
+async fn show_ip(config: &AppState, ip_detector: &IpDetector) -> Result<ExitCode, AppError> {
+    let public_ips = sync::resolve_public_ips(ip_detector, &ZoneConfig::all_entries(&config.zones)).await;
 
-#[tokio::main]
-async fn main() -> Result<(), AppError> {
-    let config = config::load_state().await;
+    let mut addresses: Vec<_> = public_ips.addresses.iter().collect();
+    addresses.sort();
+
+    for (record_type, ip) in addresses {
+        println!("{:<4} {}", record_type, ip);
+    }
+
+    if !public_ips.failed.is_empty() {
+        return Err(AppError::new("Failed to fetch public IP address for some record types".to_string()));
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the address records currently at the DNS provider of every zone.
+async fn list_records(zones: &[Zone]) -> Result<ExitCode, AppError> {
+    let mut failed_zones: usize = 0;
 
+    for zone in zones {
+        let address_records = match services::get_dns_records(zone.backend.as_ref()).await {
+            Ok(records) => records,
+            Err(error) => {
+                tracing::error!("Failed to fetch domain records of zone {}: {}", zone.config.name, error);
+                failed_zones += 1;
+                continue;
+            }
+        };
+
+        let mut records: Vec<_> = address_records.into_values().flatten().collect();
+        records.sort_by(|a, b| (&a.name, a.record_type(), &a.id).cmp(&(&b.name, b.record_type(), &b.id)));
+
+        for record in records {
+            println!("{} {:<4} {} {} {}", zone.config.name, record.record_type(), record.name, record.content, record.id.unwrap_or_default());
+        }
+    }
+
+    if failed_zones > 0 {
+        return Err(AppError::new(format!("Failed to fetch the records of {} zones", failed_zones)));
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Sync the records every refresh interval, never returns.
+async fn run_daemon(config: &AppState, ip_detector: &IpDetector, zones: &[Zone]) -> Result<ExitCode, AppError> {
     tracing::info!("Starting application");
+    for zone in zones {
+        tracing::info!("Syncing zone {} using DNS provider: {}", zone.config.name, zone.backend.name());
+    }
     tracing::info!("Refresh check interval set to {} seconds", config.refresh_interval_seconds);
 
     loop {
-        let execution_result = workflow(&config).await;
+        let execution_result = workflow(config, ip_detector, zones).await;
 
         match execution_result {
             Ok(_) => {
@@ -102,3 +279,89 @@ async fn main() -> Result<(), AppError> {
         tokio::time::sleep(tokio::time::Duration::from_secs(config.refresh_interval_seconds)).await;
     }
 }
+
+async fn execute(cli: Cli) -> Result<ExitCode, AppError> {
+    let config = config::load_state(&cli.overrides.to_map()).await.map_err(|error| {
+        AppError::new(format!("Invalid configuration: {}", error))
+    })?;
+
+    let command = Command::resolve(cli.command, config.dry_run);
+
+    let http = HttpClient::new(&config.http, RetryPolicy::from(&config.retry)).map_err(|error| {
+        AppError::new(format!("Failed to build HTTP client: {}", error))
+    })?;
+    let ip_detector = IpDetector::from_config(&config.ip_sources, config.ip_sources_quorum, config.allow_private_ips, http.clone()).map_err(|error| {
+        AppError::new(format!("Failed to build IP sources: {}", error))
+    })?;
+
+    match command {
+        Command::Run => run_daemon(&config, &ip_detector, &build_zones(&config, &http).await?).await,
+        Command::Once => {
+            workflow(&config, &ip_detector, &build_zones(&config, &http).await?).await?;
+            Ok(ExitCode::SUCCESS)
+        },
+        Command::Plan => {
+            tracing::info!("Running in dry-run mode, no records will be changed");
+            print_plan(&config, &ip_detector, &build_zones(&config, &http).await?).await
+        },
+        Command::ShowIp => show_ip(&config, &ip_detector).await,
+        Command::ListRecords => list_records(&build_zones(&config, &http).await?).await,
+        Command::ValidateConfig => {
+            for zone in &config.zones {
+                check_zone(zone, &config.ownership)?;
+            }
+            println!("Configuration is valid");
+            Ok(ExitCode::SUCCESS)
+        },
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match execute(cli).await {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            tracing::error!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::dns_providers::{DnsProvider, DnsProviderSelection, Dyndns2ProviderSettings};
+    use config::sync_entry::SyncEntry;
+
+    fn zone(domain: &str, entry: &str) -> ZoneConfig {
+        ZoneConfig {
+            name: "home".to_string(),
+            domain: domain.to_string(),
+            dns_provider: DnsProvider {
+                config: DnsProviderSelection::Dyndns2(Dyndns2ProviderSettings::new("https://ddns.example.org/nic/update".to_string(), "user".to_string(), "pass".to_string())),
+            },
+            dns_entries_to_sync: vec![SyncEntry {
+                name: entry.to_string(),
+                record_types: vec![RecordType::A],
+                settings: Default::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_check_zone() {
+        let ownership = OwnershipSettings::default();
+
+        assert_eq!(check_zone(&zone("example.com", "www"), &ownership).unwrap().as_str(), "example.com");
+        assert!(check_zone(&zone("example..com", "www"), &ownership).unwrap_err().message.contains("Invalid domain of zone home"));
+        assert!(check_zone(&zone("example.com", "a..b"), &ownership).unwrap_err().message.contains("Invalid entry \"a..b\" of zone home"));
+
+        let ownership = OwnershipSettings {
+            owner_id: Some("router".to_string()),
+            prune: false,
+        };
+        assert!(check_zone(&zone("example.com", "www"), &ownership).unwrap_err().message.starts_with("OWNER_ID is set but dyndns2"));
+    }
+}
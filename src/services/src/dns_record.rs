@@ -1,56 +1,321 @@
 use std::fmt::Display;
+use std::net::IpAddr;
+use std::str::FromStr;
 use clients::cloudflare::types::Record;
+use config::sync_entry::{RecordSettings, RecordType, APEX};
 use serde::{Deserialize, Serialize};
+use crate::error::Error;
+
+/// Longest name DNS allows in its text form, without the trailing dot.
+const MAX_NAME_LENGTH: usize = 253;
+
+/// Longest label DNS allows.
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// A fully qualified domain name, normalized so names that only differ in case, encoding or a
+/// trailing dot are equal. Internationalized names are kept in their ASCII (punycode) form,
+/// which is what providers store, and the trailing dot is left out.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DomainName(String);
+
+impl DomainName {
+    /// Parse an absolute name, with or without the trailing dot.
+    pub fn parse(raw_input: &str) -> Result<Self, Error> {
+        let trimmed = raw_input.trim();
+        let name = trimmed.strip_suffix('.').unwrap_or(trimmed);
+
+        if name.is_empty() {
+            return Err(Error::new(format!("Invalid domain name {:?}, it must not be empty", raw_input)));
+        }
+
+        let ascii = idna::domain_to_ascii(name).map_err(|_| {
+            Error::new(format!("Invalid domain name {:?}, it is not a valid internationalized name", raw_input))
+        })?;
+
+        if ascii.len() > MAX_NAME_LENGTH {
+            return Err(Error::new(format!("Invalid domain name {:?}, it is longer than {} characters", raw_input, MAX_NAME_LENGTH)));
+        }
+
+        // Underscores and wildcards are not valid host names but common in DNS, e.g. `_acme` or `*.dev`.
+        if let Some(invalid) = ascii.chars().find(|c| !c.is_ascii_alphanumeric() && !matches!(c, '-' | '_' | '*' | '.')) {
+            return Err(Error::new(format!("Invalid domain name {:?}, it contains {:?}", raw_input, invalid)));
+        }
+
+        if ascii.split('.').any(|label| label.is_empty() || label.len() > MAX_LABEL_LENGTH) {
+            return Err(Error::new(format!("Invalid domain name {:?}, every label must have 1 to {} characters", raw_input, MAX_LABEL_LENGTH)));
+        }
+
+        Ok(Self(ascii))
+    }
+
+    /// Resolve the name of a sync entry in the domain. `@` is the domain itself, a name ending
+    /// with `.` is already absolute and any other name is relative to the domain.
+    pub fn resolve(name: &str, domain: &DomainName) -> Result<Self, Error> {
+        let name = name.trim();
+
+        if name == APEX {
+            return Ok(domain.clone());
+        }
+
+        if name.ends_with('.') {
+            return Self::parse(name);
+        }
+
+        Self::parse(&format!("{}.{}", name, domain))
+    }
+
+    /// The name relative to the domain, as providers with relative record names expect it.
+    /// The domain itself is `@`, a name outside the domain stays absolute with a trailing `.`.
+    pub fn relative_to(&self, domain: &DomainName) -> String {
+        if self == domain {
+            return APEX.to_string();
+        }
+
+        match self.0.strip_suffix(domain.as_str()).and_then(|name| name.strip_suffix('.')) {
+            Some(name) => name.to_string(),
+            None => format!("{}.", self.0),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for DomainName {
+    type Err = Error;
+
+    fn from_str(raw_input: &str) -> Result<Self, Self::Err> {
+        Self::parse(raw_input)
+    }
+}
+
+impl TryFrom<String> for DomainName {
+    type Error = Error;
+
+    fn try_from(raw_input: String) -> Result<Self, Self::Error> {
+        Self::parse(&raw_input)
+    }
+}
+
+impl From<DomainName> for String {
+    fn from(name: DomainName) -> Self {
+        name.0
+    }
+}
+
+impl Display for DomainName {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DnsRecord {
     pub id: Option<String>,
-    pub name: String,
-    pub content: String,
+    pub name: DomainName,
+    pub content: IpAddr,
+    /// Time to live in seconds, `None` when unknown or left to the provider.
+    pub ttl: Option<u64>,
+    /// Whether the provider proxies the traffic, `None` when unknown or left to the provider.
+    pub proxied: Option<bool>,
+    pub comment: Option<String>,
+    pub tags: Vec<String>,
+    /// The owner id the record is marked with, see `OWNER_ID`. Providers store the marker in
+    /// their own way and leave it out of the comment and tags.
+    pub owner: Option<String>,
 }
 
-impl From<Record> for DnsRecord {
-    fn from(record: Record) -> Self {
-        Self {
-            id: record.id,
-            name: record.name,
-            content: record.content,
+impl TryFrom<Record> for DnsRecord {
+    type Error = Error;
+
+    fn try_from(record: Record) -> Result<Self, Self::Error> {
+        let record_type: RecordType = record.type_field.parse().map_err(Error::new)?;
+        let name: DomainName = record.name.parse()?;
+
+        let content: IpAddr = record.content.parse().map_err(|_| {
+            Error::new(format!("Record {} has invalid address {}", record.name, record.content))
+        })?;
+
+        if RecordType::of(&content) != record_type {
+            return Err(Error::new(format!("Record {} of type {} holds address {}", record.name, record_type, content)));
         }
+
+        Ok(Self {
+            id: record.id,
+            name,
+            content,
+            ttl: record.ttl,
+            proxied: Some(record.proxied),
+            comment: record.comment.filter(|comment| !comment.is_empty()),
+            tags: record.tags,
+            owner: None,
+        })
     }
 }
 
 impl Display for DnsRecord {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "ID: {}, Name: {}, Content: {}", self.id.clone().unwrap_or_else(|| "None".to_string()), self.name, self.content)
+        write!(f, "ID: {}, Name: {}, Type: {}, Content: {}", self.id.clone().unwrap_or_else(|| "None".to_string()), self.name, self.record_type(), self.content)
     }
 }
 
 impl DnsRecord {
+    /// The record type is always derived from the address, so an A record can never hold an
+    /// IPv6 address or the other way around.
+    pub fn record_type(&self) -> RecordType {
+        RecordType::of(&self.content)
+    }
+
     /// Build a new DNS record or update an existing one.
     ///
     /// # Arguments
-    /// subdomain - The subdomain to create or update.
-    /// ip - The IP address to set for the record.
+    /// name - The name of the record to create.
+    /// ip - The IP address to set for the record, which also decides between A and AAAA.
+    /// settings - The configured record settings, unset settings keep the existing value.
     /// existing_record - The existing record to update, if it exists.
     ///
     /// # Returns
     /// A new DNS record.
-    pub fn build_record(subdomain: &str, ip: &str, existing_record: Option<&DnsRecord>) -> DnsRecord {
+    pub fn build_record(name: &DomainName, ip: IpAddr, settings: &RecordSettings, existing_record: Option<&DnsRecord>) -> DnsRecord {
         match existing_record {
             Some(record) => {
                 DnsRecord {
                     id: record.id.clone(),
                     name: record.name.clone(),
-                    content: ip.to_string(),
+                    content: ip,
+                    ttl: settings.ttl.or(record.ttl),
+                    proxied: settings.proxied.or(record.proxied),
+                    comment: settings.comment.clone().or_else(|| record.comment.clone()),
+                    tags: if settings.tags.is_empty() { record.tags.clone() } else { settings.tags.clone() },
+                    owner: record.owner.clone(),
                 }
             },
             None => {
                 DnsRecord {
                     id: None,
-                    name: subdomain.to_string(),
-                    content: ip.to_string(),
+                    name: name.clone(),
+                    content: ip,
+                    ttl: settings.ttl,
+                    proxied: settings.proxied,
+                    comment: settings.comment.clone(),
+                    tags: settings.tags.clone(),
+                    owner: None,
                 }
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cloudflare_record(type_field: &str, content: &str) -> Record {
+        Record {
+            id: Some("1".to_string()),
+            name: "www.example.com".to_string(),
+            type_field: type_field.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_try_from_record() {
+        let record = DnsRecord::try_from(cloudflare_record("AAAA", "2001:db8::1")).unwrap();
+
+        assert_eq!(record.record_type(), RecordType::AAAA);
+        assert_eq!(record.content, "2001:db8::1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_try_from_record_rejects_invalid_content() {
+        assert!(DnsRecord::try_from(cloudflare_record("A", "<html>")).is_err());
+        assert!(DnsRecord::try_from(cloudflare_record("A", "2001:db8::1")).is_err());
+        assert!(DnsRecord::try_from(cloudflare_record("CNAME", "example.com")).is_err());
+    }
+
+    fn name(raw_input: &str) -> String {
+        DomainName::parse(raw_input).unwrap().to_string()
+    }
+
+    fn resolve(entry: &str) -> String {
+        DomainName::resolve(entry, &"Example.com.".parse().unwrap()).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse_domain_name_normalizes() {
+        assert_eq!(name("Dev.Example.COM"), "dev.example.com");
+        assert_eq!(name("dev.example.com."), "dev.example.com");
+        assert_eq!(name("*.dev.example.com"), "*.dev.example.com");
+        assert_eq!(name("_acme-challenge.example.com"), "_acme-challenge.example.com");
+        assert_eq!(name("Bücher.example"), "xn--bcher-kva.example");
+        assert_eq!(name("xn--bcher-kva.example"), "xn--bcher-kva.example");
+    }
+
+    #[test]
+    fn test_parse_domain_name_rejects_invalid() {
+        assert!(DomainName::parse("").is_err());
+        assert!(DomainName::parse(".").is_err());
+        assert!(DomainName::parse("www..example.com").is_err());
+        assert!(DomainName::parse("www example.com").is_err());
+        assert!(DomainName::parse(&format!("{}.example.com", "a".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn test_resolve_entry_names() {
+        assert_eq!(resolve("@"), "example.com");
+        assert_eq!(resolve("dev"), "dev.example.com");
+        assert_eq!(resolve("*.dev"), "*.dev.example.com");
+        assert_eq!(resolve("api.eu.lab"), "api.eu.lab.example.com");
+        assert_eq!(resolve("Dev.Example.com."), "dev.example.com");
+        assert_eq!(resolve("bücher"), "xn--bcher-kva.example.com");
+    }
+
+    #[test]
+    fn test_relative_names() {
+        let domain: DomainName = "example.com".parse().unwrap();
+        let relative = |name: &str| name.parse::<DomainName>().unwrap().relative_to(&domain);
+
+        assert_eq!(relative("example.com"), "@");
+        assert_eq!(relative("www.example.com"), "www");
+        assert_eq!(relative("*.dev.example.com"), "*.dev");
+        assert_eq!(relative("wwwexample.com"), "wwwexample.com.");
+        assert_eq!(relative("www.example.org"), "www.example.org.");
+    }
+
+    #[test]
+    fn test_try_from_record_normalizes_name() {
+        let record = DnsRecord::try_from(Record {
+            name: "WWW.Example.com".to_string(),
+            ..cloudflare_record("A", "192.0.2.1")
+        }).unwrap();
+
+        assert_eq!(record.name, DomainName::resolve("www", &"example.com".parse().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_build_record_applies_settings_over_existing() {
+        let existing = DnsRecord {
+            ttl: Some(1),
+            comment: Some("old".to_string()),
+            tags: vec!["home".to_string()],
+            ..DnsRecord::try_from(cloudflare_record("A", "192.0.2.1")).unwrap()
+        };
+        let settings = RecordSettings {
+            ttl: Some(300),
+            proxied: Some(true),
+            ..Default::default()
+        };
+
+        let record = DnsRecord::build_record(&"www.example.com".parse().unwrap(), "192.0.2.2".parse().unwrap(), &settings, Some(&existing));
+
+        assert_eq!(record.id, Some("1".to_string()));
+        assert_eq!(record.ttl, Some(300));
+        assert_eq!(record.proxied, Some(true));
+        assert_eq!(record.comment, Some("old".to_string()));
+        assert_eq!(record.tags, vec!["home".to_string()]);
+    }
+}
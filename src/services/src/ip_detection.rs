@@ -0,0 +1,176 @@
+use std::net::IpAddr;
+use clients::http::HttpClient;
+use clients::ip::dns::DnsSource;
+use clients::ip::http::{JsonSource, PlainTextSource};
+use clients::ip::interface::InterfaceSource;
+use clients::ip::upnp::UpnpSource;
+use clients::ip::{is_public, IpSource, IpVersion};
+use clients::ipify::{Format, Ipify};
+use config::ip_sources::IpSourceConfig;
+use crate::error::Error;
+
+/// Detects the public IP address by asking the configured sources in order.
+///
+/// With a quorum of one the first source that answers wins and failing sources fall through to
+/// the next one. With a higher quorum sources are queried until that many of them agree on the
+/// same address, if the sources run out first the detection fails rather than guessing.
+///
+/// Unless private addresses are allowed, answers in private, loopback, link-local, CGNAT or
+/// other reserved ranges are treated as a failure of that source, so a misbehaving service or a
+/// captive portal can never end up in the zone.
+pub struct IpDetector {
+    sources: Vec<Box<dyn IpSource>>,
+    quorum: usize,
+    allow_private: bool,
+}
+
+impl IpDetector {
+    pub fn new(sources: Vec<Box<dyn IpSource>>, quorum: usize, allow_private: bool) -> Self {
+        Self {
+            sources,
+            quorum: quorum.max(1),
+            allow_private,
+        }
+    }
+
+    /// Build the detector from the configured IP sources, HTTP based sources share the given
+    /// client and retry according to its policy before the detector falls back to the next source.
+    /// UPnP sources talk to the router with a client of their own that skips the proxy.
+    pub fn from_config(ip_sources: &[IpSourceConfig], quorum: usize, allow_private: bool, http: HttpClient) -> Result<Self, Error> {
+        let local_http = http.for_local_network().map_err(|error| {
+            Error::new(format!("Failed to build the local network HTTP client: {}", error))
+        })?;
+
+        let sources: Vec<Box<dyn IpSource>> = ip_sources.iter().map(|source| -> Box<dyn IpSource> {
+            let http = http.clone();
+
+            match source {
+                IpSourceConfig::Ipify => Box::new(Ipify::new(Format::Text).with_http_client(http)),
+                IpSourceConfig::Icanhazip => Box::new(PlainTextSource::icanhazip().with_http_client(http)),
+                IpSourceConfig::IfconfigCo => Box::new(PlainTextSource::ifconfig_co().with_http_client(http)),
+                IpSourceConfig::OpenDns => Box::new(DnsSource::opendns()),
+                IpSourceConfig::PlainText { url } => Box::new(PlainTextSource::new(url.clone()).with_http_client(http)),
+                IpSourceConfig::Json { url, field_path } => Box::new(JsonSource::new(url.clone(), field_path.clone()).with_http_client(http)),
+                IpSourceConfig::Interface { name } => Box::new(InterfaceSource::new(name.clone())),
+                IpSourceConfig::Upnp { description_url: None } => Box::new(UpnpSource::discover().with_http_client(local_http.clone())),
+                IpSourceConfig::Upnp { description_url: Some(description_url) } => Box::new(UpnpSource::new(description_url.clone()).with_http_client(local_http.clone())),
+            }
+        }).collect();
+
+        Ok(Self::new(sources, quorum, allow_private))
+    }
+
+    /// Detect the public address of the given IP version.
+    pub async fn detect(&self, version: IpVersion) -> Result<IpAddr, Error> {
+        let mut votes: Vec<(IpAddr, usize)> = vec![];
+
+        for source in &self.sources {
+            let ip = match source.lookup(version).await {
+                Ok(ip) => ip,
+                Err(error) => {
+                    tracing::warn!("IP source {} failed: {}", source.name(), error);
+                    continue;
+                }
+            };
+
+            tracing::debug!("IP source {} reported {}", source.name(), ip);
+
+            if !self.allow_private && !is_public(&ip) {
+                tracing::warn!("IP source {} reported non public address {}, ignoring it", source.name(), ip);
+                continue;
+            }
+
+            let count = match votes.iter_mut().find(|(candidate, _)| *candidate == ip) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                },
+                None => {
+                    votes.push((ip, 1));
+                    1
+                }
+            };
+
+            if count >= self.quorum {
+                return Ok(ip);
+            }
+        }
+
+        if votes.is_empty() {
+            return Err(Error::new(format!("All IP sources failed to resolve the public {} address", version)));
+        }
+
+        tracing::error!("IP sources did not reach a quorum of {}: {:?}", self.quorum, votes);
+
+        Err(Error::new(format!("IP sources did not agree on the public {} address", version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use clients::error::Error as SourceError;
+
+    struct StaticSource(Option<&'static str>);
+
+    #[async_trait]
+    impl IpSource for StaticSource {
+        fn name(&self) -> String {
+            "static".to_string()
+        }
+
+        async fn lookup(&self, _version: IpVersion) -> Result<IpAddr, SourceError> {
+            match self.0 {
+                Some(ip) => Ok(ip.parse().unwrap()),
+                None => Err(SourceError::network("Source unavailable".to_string())),
+            }
+        }
+    }
+
+    fn detector(sources: Vec<Option<&'static str>>, quorum: usize) -> IpDetector {
+        IpDetector::new(
+            sources.into_iter().map(|ip| Box::new(StaticSource(ip)) as Box<dyn IpSource>).collect(),
+            quorum,
+            false
+        )
+    }
+
+    #[tokio::test]
+    async fn test_detect_falls_back_to_next_source() {
+        let detector = detector(vec![None, Some("1.1.1.1"), Some("8.8.8.8")], 1);
+
+        assert_eq!(detector.detect(IpVersion::V4).await.unwrap(), "1.1.1.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_detect_requires_quorum() {
+        let agreeing = detector(vec![Some("1.1.1.1"), Some("8.8.8.8"), None, Some("1.1.1.1")], 2);
+        let disagreeing = detector(vec![Some("1.1.1.1"), Some("8.8.8.8"), None], 2);
+
+        assert_eq!(agreeing.detect(IpVersion::V4).await.unwrap(), "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert!(disagreeing.detect(IpVersion::V4).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_detect_fails_when_all_sources_fail() {
+        let detector = detector(vec![None, None], 1);
+
+        assert!(detector.detect(IpVersion::V4).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_detect_rejects_private_addresses() {
+        let sources = vec![Some("192.168.1.10"), Some("1.1.1.1")];
+
+        let rejecting = detector(sources.clone(), 1);
+        let allowing = IpDetector::new(
+            sources.into_iter().map(|ip| Box::new(StaticSource(ip)) as Box<dyn IpSource>).collect(),
+            1,
+            true
+        );
+
+        assert_eq!(rejecting.detect(IpVersion::V4).await.unwrap(), "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!(allowing.detect(IpVersion::V4).await.unwrap(), "192.168.1.10".parse::<IpAddr>().unwrap());
+    }
+}
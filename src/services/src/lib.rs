@@ -1,118 +1,187 @@
 use std::collections::HashMap;
-use clients::cloudflare;
-use clients::ipify::{Format, Ipify};
+use std::net::IpAddr;
+use clients::ip::IpVersion;
+use config::sync_entry::RecordType;
 use crate::error::Error;
-use config::dns_providers::{DnsProvider};
-use config::dns_providers::DnsProviderSelection::Cloudflare;
-use crate::dns_record::DnsRecord;
+use crate::dns_record::{DnsRecord, DomainName};
+use crate::ip_detection::IpDetector;
+use crate::provider::DnsProviderBackend;
 
 pub mod error;
 pub mod dns_record;
+pub mod ip_detection;
+pub mod provider;
+pub mod sync;
 
-/// Resolves the public IP address of the current machine, using the Ipify service.
+/// Resolves the public IP address of the current machine, using the configured IP sources.
+///
+/// # Arguments
+/// * `detector` - The IP detector built from the configured sources.
+/// * `version` - The IP version of the address to resolve.
 ///
 /// # Returns
-/// String containing the public IP address.
-pub async fn resolve_public_ip() -> Result<String, Error> {
-    let fetch_ip_address = Ipify::new(Format::Text).get_ip().await;
+/// The validated public IP address.
+pub async fn resolve_public_ip(detector: &IpDetector, version: IpVersion) -> Result<IpAddr, Error> {
+    let fetch_ip_address = detector.detect(version).await;
 
-    let ip_address: String = fetch_ip_address.map_err(|error| {
+    let ip_address: IpAddr = fetch_ip_address.map_err(|error| {
         tracing::error!("Failed to fetch public IP address: {:?}", error);
         Error::new("Failed to fetch public IP address".to_string())
     })?;
 
-    tracing::info!("Resolved Public IP: {}", ip_address);
+    tracing::info!("Resolved Public {} address: {}", version, ip_address);
 
     Ok(ip_address)
 }
 
+/// The IP version whose public address belongs in a record of the given type.
+pub fn ip_version_for(record_type: RecordType) -> IpVersion {
+    match record_type {
+        RecordType::A => IpVersion::V4,
+        RecordType::AAAA => IpVersion::V6,
+    }
+}
+
 /// Fetches the existing DNS records from the DNS provider.
 ///
 /// # Arguments
-/// * `dns_provider` - The DNS provider configuration.
+/// * `backend` - The DNS provider backend.
 ///
 /// # Returns
-/// A HashMap containing the existing DNS records.
+/// A HashMap containing the existing DNS records grouped by name and record type, a name can
+/// have several records of a type, e.g. for round-robin.
 pub async fn get_dns_records(
-    dns_provider: &DnsProvider
-) -> Result<HashMap<String, DnsRecord>, Error> {
-    match &dns_provider.config {
-        Cloudflare(provider) => {
-            tracing::debug!("Fetching DNS records from Cloudflare");
-
-            let api_url = &provider.api_url;
-            let api_key = &provider.api_key;
-            let zone_id = &provider.zone_id;
-
-            let client = cloudflare::Client::new(
-                api_url.clone(),
-                api_key.clone(),
-                zone_id.clone(),
-                provider.proxy_enabled
-            );
-
-            let fetch_domain_records = client.get_zone_records().await;
-
-            let dns_records = match fetch_domain_records {
-                Ok(records) => records,
-                Err(error) => {
-                    tracing::error!("Failed to fetch domain records: {:?}", error);
-                    return Err(Error::new("Failed to fetch domain records".to_string()));
-                }
-            };
-
-            let mut dns_records_map: HashMap<String, DnsRecord> = HashMap::new();
-            dns_records.iter().for_each(|record| {
-                dns_records_map.insert(record.name.clone(), record.clone().into());
-            });
-
-            tracing::info!("Found {} existing A records", dns_records.len());
-            tracing::debug!("Existing A records: {:?}", dns_records);
-
-            Ok(dns_records_map)
+    backend: &dyn DnsProviderBackend
+) -> Result<HashMap<(DomainName, RecordType), Vec<DnsRecord>>, Error> {
+    tracing::debug!("Fetching DNS records from {}", backend.name());
+
+    let fetch_domain_records = backend.list_records().await;
+
+    let dns_records = match fetch_domain_records {
+        Ok(records) => records,
+        Err(error) => {
+            tracing::error!("Failed to fetch domain records: {:?}", error);
+            return Err(Error::new("Failed to fetch domain records".to_string()));
         }
-    }
-}
+    };
+
+    let mut dns_records_map: HashMap<(DomainName, RecordType), Vec<DnsRecord>> = HashMap::new();
+    dns_records.iter().for_each(|record| {
+        dns_records_map.entry((record.name.clone(), record.record_type())).or_default().push(record.clone());
+    });
 
-pub async fn ensure_dns_record(
-    dns_provider: &DnsProvider,
-    record: &DnsRecord
-) -> Result<(), Error> {
-    match &dns_provider.config {
-        Cloudflare(provider) => {
-            let api_url = provider.api_url.clone();
-            let api_key = provider.api_key.clone();
-            let zone_id = provider.zone_id.clone();
-            let proxy_enabled = provider.proxy_enabled.clone();
-
-            let client = cloudflare::Client::new(api_url, api_key, zone_id, proxy_enabled);
-
-            let updated_record = match &record.id {
-                Some(id) => {
-                    client.update_zone_record(&id, &record.name, &record.content).await
-                },
-                None => {
-                    client.create_zone_record(&record.name, &record.content).await
-                }
-            };
-
-            match updated_record {
-                Ok(_) => {
-                    tracing::info!("Record updated successfully");
-                    Ok(())
-                },
-                Err(error) => {
-                    tracing::error!("Failed to update record: {:?}", error);
-                    Err(Error::new("Failed to update record".to_string()))
-                }
-            }
+    for ((name, record_type), records) in &dns_records_map {
+        if records.len() > 1 {
+            tracing::warn!("Found {} {} records for {}", records.len(), record_type, name);
         }
     }
+
+    tracing::info!("Found {} existing address records", dns_records.len());
+    tracing::debug!("Existing address records: {:?}", dns_records);
+
+    Ok(dns_records_map)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use clients::ipify::{Format, Ipify};
+    use async_trait::async_trait;
+    use crate::provider::Capabilities;
+
+    /// In-memory provider backend listing a fixed set of records.
+    #[derive(Default)]
+    struct FakeBackend {
+        records: Vec<DnsRecord>,
+    }
+
+    #[async_trait]
+    impl DnsProviderBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        async fn list_records(&self) -> Result<Vec<DnsRecord>, Error> {
+            Ok(self.records.clone())
+        }
+
+        async fn create_record(&self, _record: &DnsRecord) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn update_record(&self, _record: &DnsRecord) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn delete_record(&self, _record: &DnsRecord) -> Result<(), Error> {
+            Err(Error::new("Delete is not supported".to_string()))
+        }
+    }
+
+    fn record(id: Option<&str>, name: &str, content: &str) -> DnsRecord {
+        DnsRecord {
+            id: id.map(|id| id.to_string()),
+            name: name.parse().unwrap(),
+            content: content.parse().unwrap(),
+            ttl: None,
+            proxied: None,
+            comment: None,
+            tags: vec![],
+            owner: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_dns_records_keyed_by_name_and_type() {
+        let backend = FakeBackend {
+            records: vec![
+                record(Some("1"), "www.example.com", "1.1.1.1"),
+                record(Some("2"), "api.example.com", "2.2.2.2"),
+                record(Some("3"), "www.example.com", "2001:db8::1"),
+            ],
+        };
+
+        let records = get_dns_records(&backend).await.unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[&("api.example.com".parse().unwrap(), RecordType::A)][0].id.as_deref(), Some("2"));
+        assert_eq!(records[&("www.example.com".parse().unwrap(), RecordType::AAAA)][0].id.as_deref(), Some("3"));
+    }
+
+    #[tokio::test]
+    async fn test_get_dns_records_keeps_duplicates() {
+        let backend = FakeBackend {
+            records: vec![
+                record(Some("1"), "www.example.com", "1.1.1.1"),
+                record(Some("2"), "www.example.com", "2.2.2.2"),
+            ],
+        };
+
+        let records = get_dns_records(&backend).await.unwrap();
+        let ids: Vec<_> = records[&("www.example.com".parse().unwrap(), RecordType::A)].iter()
+            .map(|record| record.id.as_deref())
+            .collect();
+
+        assert_eq!(ids, vec![Some("1"), Some("2")]);
+    }
+
+    #[test]
+    fn test_desired_settings_drop_unsupported_proxied() {
+        let settings = config::sync_entry::RecordSettings {
+            ttl: Some(300),
+            proxied: Some(true),
+            ..Default::default()
+        };
+
+        let desired = FakeBackend::default().desired_settings(&settings);
+
+        assert_eq!(desired.proxied, None);
+        assert_eq!(desired.ttl, Some(300));
+    }
 
     #[tokio::test]
     async fn test_resolve_public_ip_text() {
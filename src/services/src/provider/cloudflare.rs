@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use clients::cloudflare::Client;
+use clients::cloudflare::types::{Record, RecordRequest};
+use config::sync_entry::RecordSettings;
+use crate::dns_record::DnsRecord;
+use crate::error::Error;
+use crate::provider::{Capabilities, DnsProviderBackend};
+
+pub(crate) const NAME: &str = "cloudflare";
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    proxied: true,
+    delete: true,
+    ownership: true,
+};
+
+/// TTL Cloudflare reports for records with an automatic TTL, which proxied records always have.
+const AUTOMATIC_TTL: u64 = 1;
+
+/// Prefix of the marker of an owned record, followed by the owner id. The marker is kept at the
+/// end of the record comment rather than in a tag, tags are not available on the Free plan.
+const OWNER_MARKER: &str = "dynamic-dns-owner:";
+
+/// The Cloudflare request body for the record, tags are only sent when some are set. The owner
+/// marker is appended to the comment.
+fn request(record: &DnsRecord) -> RecordRequest {
+    let comment = match (&record.comment, &record.owner) {
+        (Some(comment), Some(owner)) => Some(format!("{} {}{}", comment, OWNER_MARKER, owner)),
+        (None, Some(owner)) => Some(format!("{}{}", OWNER_MARKER, owner)),
+        (comment, None) => comment.clone(),
+    };
+
+    RecordRequest {
+        name: record.name.to_string(),
+        type_field: record.record_type().to_string(),
+        content: record.content.to_string(),
+        proxied: record.proxied,
+        ttl: record.ttl,
+        comment,
+        tags: Some(record.tags.clone()).filter(|tags| !tags.is_empty()),
+    }
+}
+
+/// The record with the owner marker taken out of its comment.
+fn from_record(record: Record) -> Result<DnsRecord, Error> {
+    let mut record = DnsRecord::try_from(record)?;
+
+    let marked = record.comment.as_deref().and_then(|comment| {
+        let (text, owner) = match comment.rsplit_once(' ') {
+            Some((text, marker)) => (Some(text), marker.strip_prefix(OWNER_MARKER)?),
+            None => (None, comment.strip_prefix(OWNER_MARKER)?),
+        };
+        Some((text.map(str::to_string), owner.to_string()))
+    });
+
+    if let Some((comment, owner)) = marked {
+        record.comment = comment;
+        record.owner = Some(owner);
+    }
+
+    Ok(record)
+}
+
+#[async_trait]
+impl DnsProviderBackend for Client {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    /// Records without their own proxied setting follow `CLOUDFLARE_PROXY_ENABLED`, so a record
+    /// toggled in the dashboard is switched back. Proxied records always have an automatic TTL
+    /// on Cloudflare, a configured TTL only applies to records that are not proxied.
+    fn desired_settings(&self, settings: &RecordSettings) -> RecordSettings {
+        let proxied = settings.proxied.unwrap_or(self.proxy_enabled());
+
+        RecordSettings {
+            proxied: Some(proxied),
+            ttl: if proxied { Some(AUTOMATIC_TTL) } else { settings.ttl },
+            ..settings.clone()
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<DnsRecord>, Error> {
+        let records = self.get_zone_records().await.map_err(|error| {
+            tracing::error!("Failed to fetch domain records: {:?}", error);
+            Error::new("Failed to fetch domain records".to_string())
+        })?;
+
+        records.into_iter().map(from_record).collect()
+    }
+
+    async fn create_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        self.create_zone_record(&request(record)).await.map_err(|error| {
+            tracing::error!("Failed to create record: {:?}", error);
+            Error::new("Failed to create record".to_string())
+        })
+    }
+
+    async fn update_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        let id = record.id.as_ref().ok_or_else(|| {
+            Error::new(format!("Cannot update record {} without an id", record.name))
+        })?;
+
+        self.update_zone_record(id, &request(record)).await.map_err(|error| {
+            tracing::error!("Failed to update record: {:?}", error);
+            Error::new("Failed to update record".to_string())
+        })
+    }
+
+    async fn delete_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        let id = record.id.as_ref().ok_or_else(|| {
+            Error::new(format!("Cannot delete record {} without an id", record.name))
+        })?;
+
+        self.delete_zone_record(id).await.map_err(|error| {
+            tracing::error!("Failed to delete record: {:?}", error);
+            Error::new("Failed to delete record".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(proxy_enabled: bool) -> Client {
+        Client::new("http://localhost".to_string(), "key".to_string(), "zone".to_string(), proxy_enabled)
+    }
+
+    #[test]
+    fn test_desired_settings_default_to_zone_proxy_setting() {
+        let settings = RecordSettings {
+            ttl: Some(300),
+            ..Default::default()
+        };
+
+        let proxied = client(true).desired_settings(&settings);
+        assert_eq!((proxied.proxied, proxied.ttl), (Some(true), Some(AUTOMATIC_TTL)));
+
+        let not_proxied = client(false).desired_settings(&settings);
+        assert_eq!((not_proxied.proxied, not_proxied.ttl), (Some(false), Some(300)));
+    }
+
+    #[test]
+    fn test_owner_is_stored_in_comment() {
+        let cloudflare_record = |comment: &str| Record {
+            id: Some("1".to_string()),
+            name: "www.example.com".to_string(),
+            type_field: "A".to_string(),
+            content: "192.0.2.1".to_string(),
+            comment: Some(comment.to_string()),
+            tags: vec!["home".to_string()],
+            ..Default::default()
+        };
+
+        let record = from_record(cloudflare_record("home router dynamic-dns-owner:router")).unwrap();
+        assert_eq!((record.comment.as_deref(), record.owner.as_deref()), (Some("home router"), Some("router")));
+        assert_eq!(record.tags, vec!["home".to_string()]);
+        assert_eq!(request(&record).comment.as_deref(), Some("home router dynamic-dns-owner:router"));
+        assert_eq!(request(&record).tags, Some(vec!["home".to_string()]));
+
+        let record = from_record(cloudflare_record("dynamic-dns-owner:router")).unwrap();
+        assert_eq!((record.comment.as_deref(), record.owner.as_deref()), (None, Some("router")));
+        assert_eq!(request(&record).comment.as_deref(), Some("dynamic-dns-owner:router"));
+
+        let record = from_record(cloudflare_record("home router")).unwrap();
+        assert_eq!((record.comment.as_deref(), record.owner.as_deref()), (Some("home router"), None));
+    }
+
+    #[test]
+    fn test_desired_settings_prefer_record_proxied() {
+        let settings = RecordSettings {
+            proxied: Some(false),
+            ..Default::default()
+        };
+
+        assert_eq!(client(true).desired_settings(&settings).proxied, Some(false));
+    }
+}
@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use clients::digitalocean::Client;
+use clients::digitalocean::types::{Record, RecordRequest};
+use config::sync_entry::RecordSettings;
+use crate::dns_record::{DnsRecord, DomainName};
+use crate::error::Error;
+use crate::provider::{Capabilities, DnsProviderBackend};
+
+pub(crate) const NAME: &str = "digitalocean";
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    proxied: false,
+    delete: true,
+    ownership: false,
+};
+
+/// The request body for the record, named relative to the domain.
+fn request(zone: &DomainName, record: &DnsRecord) -> RecordRequest {
+    RecordRequest {
+        type_field: record.record_type().to_string(),
+        name: record.name.relative_to(zone),
+        data: record.content.to_string(),
+        ttl: record.ttl,
+    }
+}
+
+fn from_record(zone: &DomainName, record: Record) -> Result<DnsRecord, Error> {
+    let name = DomainName::resolve(&record.name, zone)?;
+
+    let content = record.data.parse().map_err(|_| {
+        Error::new(format!("Record {} has invalid address {}", name, record.data))
+    })?;
+
+    Ok(DnsRecord {
+        id: Some(record.id.to_string()),
+        name,
+        content,
+        ttl: record.ttl,
+        proxied: None,
+        comment: None,
+        tags: vec![],
+        owner: None,
+    })
+}
+
+#[async_trait]
+impl DnsProviderBackend for Client {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    /// DigitalOcean records have neither comments nor tags.
+    fn desired_settings(&self, settings: &RecordSettings) -> RecordSettings {
+        RecordSettings {
+            ttl: settings.ttl,
+            proxied: None,
+            comment: None,
+            tags: vec![],
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<DnsRecord>, Error> {
+        let zone = DomainName::parse(self.zone())?;
+
+        let records = self.get_domain_records().await.map_err(|error| {
+            tracing::error!("Failed to fetch domain records: {:?}", error);
+            Error::new("Failed to fetch domain records".to_string())
+        })?;
+
+        records.into_iter().map(|record| from_record(&zone, record)).collect()
+    }
+
+    async fn create_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        let zone = DomainName::parse(self.zone())?;
+
+        self.create_domain_record(&request(&zone, record)).await.map(|_| ()).map_err(|error| {
+            tracing::error!("Failed to create record: {:?}", error);
+            Error::new("Failed to create record".to_string())
+        })
+    }
+
+    async fn update_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        let zone = DomainName::parse(self.zone())?;
+        let id = record.id.as_ref().ok_or_else(|| {
+            Error::new(format!("Cannot update record {} without an id", record.name))
+        })?;
+
+        self.update_domain_record(id, &request(&zone, record)).await.map(|_| ()).map_err(|error| {
+            tracing::error!("Failed to update record: {:?}", error);
+            Error::new("Failed to update record".to_string())
+        })
+    }
+
+    async fn delete_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        let id = record.id.as_ref().ok_or_else(|| {
+            Error::new(format!("Cannot delete record {} without an id", record.name))
+        })?;
+
+        self.delete_domain_record(id).await.map_err(|error| {
+            tracing::error!("Failed to delete record: {:?}", error);
+            Error::new("Failed to delete record".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_named_relative_to_the_domain() {
+        let zone: DomainName = "example.com".parse().unwrap();
+
+        let apex = from_record(&zone, Record {
+            id: 3352897,
+            type_field: "AAAA".to_string(),
+            name: "@".to_string(),
+            data: "2001:db8::1".to_string(),
+            ttl: Some(1800),
+        }).unwrap();
+        assert_eq!((apex.id.as_deref(), apex.name.as_str()), (Some("3352897"), "example.com"));
+
+        let www = from_record(&zone, Record {
+            id: 3352896,
+            type_field: "A".to_string(),
+            name: "www".to_string(),
+            data: "192.0.2.1".to_string(),
+            ttl: Some(3600),
+        }).unwrap();
+        assert_eq!(www.name.as_str(), "www.example.com");
+
+        assert_eq!(request(&zone, &apex).name, "@");
+        assert_eq!(request(&zone, &www), RecordRequest {
+            type_field: "A".to_string(),
+            name: "www".to_string(),
+            data: "192.0.2.1".to_string(),
+            ttl: Some(3600),
+        });
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use clients::dyndns2::Client;
+use clients::dyndns2::types::UpdateResponse;
+use config::sync_entry::{RecordSettings, RecordType};
+use crate::dns_record::{DnsRecord, DomainName};
+use crate::error::Error;
+use crate::provider::{Capabilities, DnsProviderBackend};
+
+pub(crate) const NAME: &str = "dyndns2";
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    proxied: false,
+    delete: false,
+    ownership: false,
+};
+
+/// How long updates are held back after the server reported a failure on its side, the
+/// protocol asks clients to wait at least 30 minutes after a `911` or `dnserr`.
+const SERVER_ERROR_HOLD: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Default)]
+struct State {
+    /// The records as last successfully updated, the protocol has no way to read them.
+    records: BTreeMap<(DomainName, RecordType), DnsRecord>,
+    /// The answer that stopped every further update until the daemon is restarted.
+    blocked: Option<UpdateResponse>,
+    /// Hostnames the server refused, they are not sent again until the daemon is restarted.
+    failed_hosts: HashMap<DomainName, UpdateResponse>,
+    /// Updates are held back until then after a server side failure.
+    held_until: Option<Instant>,
+}
+
+/// Backend for update servers speaking the dyndns2 protocol.
+///
+/// The protocol can only point a hostname at an address, so the records listed are the ones
+/// updated since the daemon started, and every record shows up as a create in the first plan.
+/// The answers of the server are honoured: a refused account stops every update, a refused
+/// hostname stops the updates of that hostname, and a server failure holds the updates back.
+pub struct Dyndns2Backend {
+    client: Client,
+    state: Mutex<State>,
+}
+
+impl Dyndns2Backend {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Whether the server still accepts an update of the record.
+    fn check(&self, record: &DnsRecord) -> Result<(), Error> {
+        let state = self.state.lock().unwrap();
+
+        if let Some(response) = &state.blocked {
+            return Err(Error::new(format!("Not updating {}, the server answered {} earlier, fix the account and restart", record.name, response)));
+        }
+        if let Some(response) = state.failed_hosts.get(&record.name) {
+            return Err(Error::new(format!("Not updating {}, the server answered {} earlier, fix the hostname and restart", record.name, response)));
+        }
+        if let Some(held_until) = state.held_until.filter(|held_until| *held_until > Instant::now()) {
+            let minutes = held_until.saturating_duration_since(Instant::now()).as_secs().div_ceil(60);
+            return Err(Error::new(format!("Not updating {}, the server failed earlier, retrying in {} minutes", record.name, minutes)));
+        }
+
+        Ok(())
+    }
+
+    async fn update(&self, record: &DnsRecord) -> Result<(), Error> {
+        self.check(record)?;
+
+        let response = self.client.update(record.name.as_str(), &record.content.to_string()).await.map_err(|error| {
+            Error::new(format!("Failed to update {}: {}", record.name, error.message))
+        })?;
+
+        let mut state = self.state.lock().unwrap();
+
+        match response {
+            UpdateResponse::Good | UpdateResponse::NoChange => {
+                state.records.insert((record.name.clone(), record.record_type()), record.clone());
+                Ok(())
+            },
+            UpdateResponse::BadAuth | UpdateResponse::NotDonator | UpdateResponse::Abuse | UpdateResponse::BadAgent => {
+                let error = Error::new(format!("Server answered {} to the update of {}, stopping every update until restart", response, record.name));
+                state.blocked = Some(response);
+                Err(error)
+            },
+            UpdateResponse::NotFqdn | UpdateResponse::NoHost | UpdateResponse::NumHost => {
+                let error = Error::new(format!("Server answered {} to the update of {}, stopping its updates until restart", response, record.name));
+                state.failed_hosts.insert(record.name.clone(), response);
+                Err(error)
+            },
+            UpdateResponse::DnsError | UpdateResponse::ServerError => {
+                state.held_until = Some(Instant::now() + SERVER_ERROR_HOLD);
+                Err(Error::new(format!("Server answered {} to the update of {}, holding updates for {} minutes", response, record.name, SERVER_ERROR_HOLD.as_secs() / 60)))
+            },
+            UpdateResponse::Unknown(_) => {
+                Err(Error::new(format!("Server answered {} to the update of {}", response, record.name)))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DnsProviderBackend for Dyndns2Backend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    /// The protocol only sets the address, the TTL and everything else is up to the server.
+    fn desired_settings(&self, _settings: &RecordSettings) -> RecordSettings {
+        RecordSettings {
+            ttl: None,
+            proxied: None,
+            comment: None,
+            tags: vec![],
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<DnsRecord>, Error> {
+        Ok(self.state.lock().unwrap().records.values().cloned().collect())
+    }
+
+    async fn create_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        self.update(record).await
+    }
+
+    async fn update_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        self.update(record).await
+    }
+
+    async fn delete_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        Err(Error::new(format!("The dyndns2 protocol cannot delete {}", record.name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn backend(server: &MockServer) -> Dyndns2Backend {
+        Dyndns2Backend::new(Client::new(format!("{}/nic/update", server.uri()), "user".to_string(), "pass".to_string()))
+    }
+
+    fn record(name: &str, content: &str) -> DnsRecord {
+        DnsRecord {
+            id: None,
+            name: name.parse().unwrap(),
+            content: content.parse().unwrap(),
+            ttl: None,
+            proxied: None,
+            comment: None,
+            tags: vec![],
+            owner: None,
+        }
+    }
+
+    async fn mock_answer(server: &MockServer, hostname: &str, body: &str, expected_requests: u64) {
+        Mock::given(method("GET"))
+            .and(path("/nic/update"))
+            .and(query_param("hostname", hostname))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(expected_requests)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_updated_records_are_listed() {
+        let server = MockServer::start().await;
+        mock_answer(&server, "home.example.com", "good 192.0.2.1", 1).await;
+        mock_answer(&server, "nas.example.com", "nochg 192.0.2.1", 1).await;
+
+        let backend = backend(&server);
+        assert!(backend.list_records().await.unwrap().is_empty());
+
+        backend.create_record(&record("home.example.com", "192.0.2.1")).await.unwrap();
+        backend.create_record(&record("nas.example.com", "192.0.2.1")).await.unwrap();
+
+        let names: Vec<String> = backend.list_records().await.unwrap().iter().map(|record| record.name.to_string()).collect();
+        assert_eq!(names, vec!["home.example.com", "nas.example.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_badauth_stops_every_update() {
+        let server = MockServer::start().await;
+        mock_answer(&server, "home.example.com", "badauth", 1).await;
+        mock_answer(&server, "nas.example.com", "good 192.0.2.1", 0).await;
+
+        let backend = backend(&server);
+
+        assert!(backend.create_record(&record("home.example.com", "192.0.2.1")).await.is_err());
+        assert!(backend.create_record(&record("home.example.com", "192.0.2.1")).await.is_err());
+        assert!(backend.create_record(&record("nas.example.com", "192.0.2.1")).await.is_err());
+        assert!(backend.list_records().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nohost_stops_the_updates_of_the_host() {
+        let server = MockServer::start().await;
+        mock_answer(&server, "home.example.com", "nohost", 1).await;
+        mock_answer(&server, "nas.example.com", "good 192.0.2.1", 1).await;
+
+        let backend = backend(&server);
+
+        assert!(backend.create_record(&record("home.example.com", "192.0.2.1")).await.is_err());
+        assert!(backend.create_record(&record("home.example.com", "192.0.2.1")).await.is_err());
+        assert!(backend.create_record(&record("nas.example.com", "192.0.2.1")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_server_failure_holds_updates() {
+        let server = MockServer::start().await;
+        mock_answer(&server, "home.example.com", "911", 1).await;
+        mock_answer(&server, "nas.example.com", "good 192.0.2.1", 1).await;
+
+        let backend = backend(&server);
+
+        assert!(backend.create_record(&record("home.example.com", "192.0.2.1")).await.is_err());
+        let error = backend.create_record(&record("nas.example.com", "192.0.2.1")).await.unwrap_err();
+        assert_eq!(error.message, "Not updating nas.example.com, the server failed earlier, retrying in 30 minutes");
+
+        backend.state.lock().unwrap().held_until = Some(Instant::now());
+        assert!(backend.create_record(&record("nas.example.com", "192.0.2.1")).await.is_ok());
+    }
+}
@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use clients::hetzner::Client;
+use clients::hetzner::types::{Record, RecordRequest};
+use config::sync_entry::RecordSettings;
+use crate::dns_record::{DnsRecord, DomainName};
+use crate::error::Error;
+use crate::provider::{Capabilities, DnsProviderBackend};
+
+pub(crate) const NAME: &str = "hetzner";
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    proxied: false,
+    delete: true,
+    ownership: false,
+};
+
+/// The request body for the record, named relative to the zone.
+fn request(zone_id: &str, zone: &DomainName, record: &DnsRecord) -> RecordRequest {
+    RecordRequest {
+        zone_id: zone_id.to_string(),
+        type_field: record.record_type().to_string(),
+        name: record.name.relative_to(zone),
+        value: record.content.to_string(),
+        ttl: record.ttl,
+    }
+}
+
+fn from_record(zone: &DomainName, record: Record) -> Result<DnsRecord, Error> {
+    let name = DomainName::resolve(&record.name, zone)?;
+
+    let content = record.value.parse().map_err(|_| {
+        Error::new(format!("Record {} has invalid address {}", name, record.value))
+    })?;
+
+    Ok(DnsRecord {
+        id: Some(record.id),
+        name,
+        content,
+        ttl: record.ttl,
+        proxied: None,
+        comment: None,
+        tags: vec![],
+        owner: None,
+    })
+}
+
+#[async_trait]
+impl DnsProviderBackend for Client {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    /// Hetzner records have neither comments nor tags.
+    fn desired_settings(&self, settings: &RecordSettings) -> RecordSettings {
+        RecordSettings {
+            ttl: settings.ttl,
+            proxied: None,
+            comment: None,
+            tags: vec![],
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<DnsRecord>, Error> {
+        let zone = DomainName::parse(&self.zone().name)?;
+
+        let records = self.get_zone_records().await.map_err(|error| {
+            tracing::error!("Failed to fetch domain records: {:?}", error);
+            Error::new("Failed to fetch domain records".to_string())
+        })?;
+
+        records.into_iter().map(|record| from_record(&zone, record)).collect()
+    }
+
+    async fn create_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        let zone = DomainName::parse(&self.zone().name)?;
+
+        self.create_zone_record(&request(&self.zone().id, &zone, record)).await.map(|_| ()).map_err(|error| {
+            tracing::error!("Failed to create record: {:?}", error);
+            Error::new("Failed to create record".to_string())
+        })
+    }
+
+    async fn update_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        let zone = DomainName::parse(&self.zone().name)?;
+        let id = record.id.as_ref().ok_or_else(|| {
+            Error::new(format!("Cannot update record {} without an id", record.name))
+        })?;
+
+        self.update_zone_record(id, &request(&self.zone().id, &zone, record)).await.map(|_| ()).map_err(|error| {
+            tracing::error!("Failed to update record: {:?}", error);
+            Error::new("Failed to update record".to_string())
+        })
+    }
+
+    async fn delete_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        let id = record.id.as_ref().ok_or_else(|| {
+            Error::new(format!("Cannot delete record {} without an id", record.name))
+        })?;
+
+        self.delete_zone_record(id).await.map_err(|error| {
+            tracing::error!("Failed to delete record: {:?}", error);
+            Error::new("Failed to delete record".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_named_relative_to_the_zone() {
+        let zone: DomainName = "example.com".parse().unwrap();
+
+        let record = from_record(&zone, Record {
+            id: "7c1e3a5b9d2f4e6a8c0b1d3f5a7e9c2b".to_string(),
+            type_field: "A".to_string(),
+            name: "www".to_string(),
+            value: "192.0.2.1".to_string(),
+            ttl: None,
+            zone_id: "HBhuAj8UXBkCPbfwk7E7Zn".to_string(),
+        }).unwrap();
+
+        assert_eq!((record.name.as_str(), record.ttl), ("www.example.com", None));
+        assert_eq!(request("HBhuAj8UXBkCPbfwk7E7Zn", &zone, &record), RecordRequest {
+            zone_id: "HBhuAj8UXBkCPbfwk7E7Zn".to_string(),
+            type_field: "A".to_string(),
+            name: "www".to_string(),
+            value: "192.0.2.1".to_string(),
+            ttl: None,
+        });
+    }
+}
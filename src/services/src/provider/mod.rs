@@ -0,0 +1,297 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use clients::http::HttpClient;
+use config::dns_providers::{AwsCredentials, DnsProvider, DnsProviderSelection, TsigAlgorithm};
+use config::sync_entry::RecordSettings;
+use crate::dns_record::DnsRecord;
+use crate::error::Error;
+
+pub mod cloudflare;
+pub mod digitalocean;
+pub mod dyndns2;
+pub mod hetzner;
+pub mod powerdns;
+mod record_sets;
+pub mod rfc2136;
+pub mod route53;
+
+/// Optional features a DNS provider may or may not support, used by the sync logic to decide
+/// which parts of a record it is allowed to manage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The provider can proxy traffic for a record (e.g. Cloudflare's orange cloud).
+    pub proxied: bool,
+    /// The provider can delete individual records.
+    pub delete: bool,
+    /// The provider can mark records with the owner id, required to prune records.
+    pub ownership: bool,
+}
+
+/// A record change of a plan, as handed to the provider backend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordChange<'a> {
+    /// Create the record, or update it when it carries the provider id.
+    Upsert(&'a DnsRecord),
+    /// Delete the record as it currently is.
+    Delete(&'a DnsRecord),
+}
+
+impl RecordChange<'_> {
+    /// The record the change is about.
+    pub fn record(&self) -> &DnsRecord {
+        match self {
+            RecordChange::Upsert(record) | RecordChange::Delete(record) => record,
+        }
+    }
+}
+
+/// Abstraction over a DNS provider API, the services and the sync workflow only ever talk to a
+/// provider through this trait so new providers can be added without touching the sync logic.
+#[async_trait]
+pub trait DnsProviderBackend: Send + Sync {
+    /// Human readable name of the provider, used for logging.
+    fn name(&self) -> &'static str;
+
+    /// The optional features supported by this provider.
+    fn capabilities(&self) -> Capabilities;
+
+    /// The settings the records of an entry should converge to on this provider. Settings the
+    /// provider does not support are dropped so they never show up as drift, providers can
+    /// override this to fill in their own defaults.
+    fn desired_settings(&self, settings: &RecordSettings) -> RecordSettings {
+        RecordSettings {
+            proxied: settings.proxied.filter(|_| self.capabilities().proxied),
+            ..settings.clone()
+        }
+    }
+
+    /// List the address records currently present in the zone.
+    async fn list_records(&self) -> Result<Vec<DnsRecord>, Error>;
+
+    /// Create a new record, the record id is ignored.
+    async fn create_record(&self, record: &DnsRecord) -> Result<(), Error>;
+
+    /// Update an existing record, the record must carry the provider id.
+    async fn update_record(&self, record: &DnsRecord) -> Result<(), Error>;
+
+    /// Delete an existing record, the record must carry the provider id.
+    async fn delete_record(&self, record: &DnsRecord) -> Result<(), Error>;
+
+    /// Apply the changes of a plan. By default every change is applied on its own, providers
+    /// with a batch API can override this to send the changes together.
+    ///
+    /// # Returns
+    /// The number of changes that failed.
+    async fn apply_changes(&self, changes: &[RecordChange<'_>]) -> usize {
+        let mut failures: usize = 0;
+
+        for change in changes {
+            let result = match change {
+                RecordChange::Upsert(record) => {
+                    tracing::info!("Updating {} record: {}", record.record_type(), record.name);
+                    let result = match &record.id {
+                        Some(_) => self.update_record(record).await,
+                        None => self.create_record(record).await,
+                    };
+                    result.map(|_| tracing::info!("{} record updated to IP {}", record.name, record.content))
+                },
+                RecordChange::Delete(record) => {
+                    tracing::info!("Deleting {} record: {}", record.record_type(), record.name);
+                    self.delete_record(record).await
+                        .map(|_| tracing::info!("{} record deleted", record.name))
+                },
+            };
+
+            if let Err(error) = result {
+                tracing::error!("Failed to change record {}: {:?}", change.record().name, error);
+                failures += 1;
+            }
+        }
+
+        failures
+    }
+}
+
+/// The name and capabilities of the backend [`build_backend`] builds for the DNS provider,
+/// known without contacting the provider.
+pub fn backend_capabilities(dns_provider: &DnsProvider) -> (&'static str, Capabilities) {
+    match &dns_provider.config {
+        DnsProviderSelection::Cloudflare(_) => (cloudflare::NAME, cloudflare::CAPABILITIES),
+        DnsProviderSelection::Route53(_) => (route53::NAME, route53::CAPABILITIES),
+        DnsProviderSelection::Rfc2136(_) => (rfc2136::NAME, rfc2136::CAPABILITIES),
+        DnsProviderSelection::Dyndns2(_) => (dyndns2::NAME, dyndns2::CAPABILITIES),
+        DnsProviderSelection::DigitalOcean(_) => (digitalocean::NAME, digitalocean::CAPABILITIES),
+        DnsProviderSelection::Hetzner(_) => (hetzner::NAME, hetzner::CAPABILITIES),
+        DnsProviderSelection::PowerDns(_) => (powerdns::NAME, powerdns::CAPABILITIES),
+    }
+}
+
+/// Build the provider backend for the configured DNS provider. Settings the provider can
+/// discover by itself, like the Cloudflare zone id, are looked up once here.
+///
+/// # Arguments
+/// * `dns_provider` - The DNS provider configuration.
+/// * `domain` - The domain of the zone the backend manages.
+/// * `http` - The shared HTTP client used for every provider request.
+///
+/// # Returns
+/// The provider backend to run the sync against.
+pub async fn build_backend(
+    dns_provider: &DnsProvider,
+    domain: &str,
+    http: HttpClient
+) -> Result<Box<dyn DnsProviderBackend>, Error> {
+    match &dns_provider.config {
+        DnsProviderSelection::Cloudflare(provider) => {
+            let client = clients::cloudflare::Client::new(
+                provider.api_url.clone(),
+                provider.api_key.clone(),
+                provider.zone_id.clone().unwrap_or_default(),
+                provider.proxy_enabled
+            ).with_http_client(http);
+
+            if provider.zone_id.is_some() {
+                return Ok(Box::new(client));
+            }
+
+            let zone_id = client.find_zone_id(domain).await.map_err(|error| {
+                Error::new(format!("Failed to find the Cloudflare zone of {}: {}", domain, error.message))
+            })?;
+
+            tracing::info!("Found Cloudflare zone {} for {}", zone_id, domain);
+
+            Ok(Box::new(client.with_zone_id(zone_id)))
+        },
+        DnsProviderSelection::Route53(provider) => {
+            let credentials = match &provider.credentials {
+                AwsCredentials::Keys { access_key_id, secret_access_key, session_token } => {
+                    clients::route53::credentials::Credentials::new(access_key_id.clone(), secret_access_key.clone(), session_token.clone())
+                },
+                AwsCredentials::Profile { name, path } => {
+                    clients::route53::credentials::Credentials::from_profile(path, name).map_err(|error| {
+                        Error::new(format!("Failed to load AWS credentials: {}", error.message))
+                    })?
+                }
+            };
+
+            let client = clients::route53::Client::new(
+                provider.api_url.clone(),
+                provider.hosted_zone_id.clone().unwrap_or_default(),
+                credentials,
+                provider.ttl
+            ).with_http_client(http);
+
+            if provider.hosted_zone_id.is_some() {
+                return Ok(Box::new(client));
+            }
+
+            let hosted_zone_id = client.find_hosted_zone_id(domain).await.map_err(|error| {
+                Error::new(format!("Failed to find the Route 53 hosted zone of {}: {}", domain, error.message))
+            })?;
+
+            tracing::info!("Found Route 53 hosted zone {} for {}", hosted_zone_id, domain);
+
+            Ok(Box::new(client.with_hosted_zone_id(hosted_zone_id)))
+        },
+        DnsProviderSelection::Rfc2136(provider) => {
+            let tsig_key = provider.tsig_key.as_ref().map(|key| {
+                let algorithm = match key.algorithm {
+                    TsigAlgorithm::HmacSha256 => clients::rfc2136::types::TsigAlgorithm::HmacSha256,
+                    TsigAlgorithm::HmacSha512 => clients::rfc2136::types::TsigAlgorithm::HmacSha512,
+                };
+
+                clients::rfc2136::types::TsigKey::new(key.name.clone(), algorithm, key.secret.clone())
+            });
+
+            let client = clients::rfc2136::Client::new(
+                provider.server.clone(),
+                provider.zone.clone().unwrap_or_default(),
+                tsig_key,
+                provider.ttl,
+                Duration::from_secs(provider.timeout_seconds)
+            );
+
+            if provider.zone.is_some() {
+                return Ok(Box::new(rfc2136::Rfc2136Backend::new(client)));
+            }
+
+            let zone = client.find_zone(domain).await.map_err(|error| {
+                Error::new(format!("Failed to find the zone of {}: {}", domain, error.message))
+            })?;
+
+            tracing::info!("Found zone {} for {} on {}", zone, domain, provider.server);
+
+            Ok(Box::new(rfc2136::Rfc2136Backend::new(client.with_zone(zone))))
+        },
+        DnsProviderSelection::Dyndns2(provider) => {
+            let client = clients::dyndns2::Client::new(
+                provider.url.clone(),
+                provider.username.clone(),
+                provider.password.clone()
+            ).with_http_client(http);
+
+            Ok(Box::new(dyndns2::Dyndns2Backend::new(client)))
+        },
+        DnsProviderSelection::DigitalOcean(provider) => {
+            let client = clients::digitalocean::Client::new(
+                provider.api_url.clone(),
+                provider.api_token.clone(),
+                provider.zone.clone().unwrap_or_default()
+            ).with_http_client(http);
+
+            if provider.zone.is_some() {
+                return Ok(Box::new(client));
+            }
+
+            let zone = client.find_zone(domain).await.map_err(|error| {
+                Error::new(format!("Failed to find the DigitalOcean domain of {}: {}", domain, error.message))
+            })?;
+
+            tracing::info!("Found DigitalOcean domain {} for {}", zone, domain);
+
+            Ok(Box::new(client.with_zone(zone)))
+        },
+        DnsProviderSelection::Hetzner(provider) => {
+            let client = clients::hetzner::Client::new(
+                provider.api_url.clone(),
+                provider.api_token.clone(),
+                clients::hetzner::types::Zone::default()
+            ).with_http_client(http);
+
+            // Record names are relative to the zone, so its name is needed even when the id is set.
+            let zone = match &provider.zone_id {
+                Some(zone_id) => client.get_zone(zone_id).await.map_err(|error| {
+                    Error::new(format!("Failed to fetch the Hetzner zone {}: {}", zone_id, error.message))
+                })?,
+                None => client.find_zone(domain).await.map_err(|error| {
+                    Error::new(format!("Failed to find the Hetzner zone of {}: {}", domain, error.message))
+                })?,
+            };
+
+            tracing::info!("Found Hetzner zone {} ({}) for {}", zone.name, zone.id, domain);
+
+            Ok(Box::new(client.with_zone(zone)))
+        },
+        DnsProviderSelection::PowerDns(provider) => {
+            let client = clients::powerdns::Client::new(
+                provider.api_url.clone(),
+                provider.api_key.clone(),
+                provider.server_id.clone(),
+                provider.zone.as_ref().map(|zone| format!("{}.", zone.trim_end_matches('.'))).unwrap_or_default(),
+                provider.ttl
+            ).with_http_client(http);
+
+            if provider.zone.is_some() {
+                return Ok(Box::new(powerdns::PowerDnsBackend::new(client)));
+            }
+
+            let zone_id = client.find_zone_id(domain).await.map_err(|error| {
+                Error::new(format!("Failed to find the PowerDNS zone of {}: {}", domain, error.message))
+            })?;
+
+            tracing::info!("Found PowerDNS zone {} for {}", zone_id, domain);
+
+            Ok(Box::new(powerdns::PowerDnsBackend::new(client.with_zone_id(zone_id))))
+        }
+    }
+}
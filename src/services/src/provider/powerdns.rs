@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use clients::powerdns::Client;
+use clients::powerdns::types::{RRset, RRsetChange};
+use config::sync_entry::{RecordSettings, RecordType};
+use crate::dns_record::{DnsRecord, DomainName};
+use crate::error::Error;
+use crate::provider::{Capabilities, DnsProviderBackend, RecordChange};
+use crate::provider::record_sets::{apply_change, record_set_updates, record_sets, RecordSets};
+
+pub(crate) const NAME: &str = "powerdns";
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    proxied: false,
+    delete: true,
+    ownership: false,
+};
+
+/// Backend for the PowerDNS Authoritative HTTP API.
+///
+/// PowerDNS has no record ids and only replaces whole record sets, so the record sets are kept
+/// from the last listing to work out the values of a replaced set. The id of a listed record is
+/// its address. Disabled records are not listed but kept aside, and sent back with every
+/// replacement of their record set.
+pub struct PowerDnsBackend {
+    client: Client,
+    snapshot: Mutex<RecordSets>,
+    disabled: Mutex<HashMap<(DomainName, RecordType), Vec<String>>>,
+}
+
+impl PowerDnsBackend {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            snapshot: Mutex::new(HashMap::new()),
+            disabled: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// One record per enabled value of the record set.
+fn from_rrset(rrset: RRset) -> Result<Vec<DnsRecord>, Error> {
+    let name: DomainName = rrset.name.parse()?;
+
+    rrset.records.iter()
+        .filter(|record| !record.disabled)
+        .map(|record| {
+            let content = record.content.parse().map_err(|_| {
+                Error::new(format!("Record {} has invalid address {}", rrset.name, record.content))
+            })?;
+
+            Ok(DnsRecord {
+                id: Some(record.content.clone()),
+                name: name.clone(),
+                content,
+                ttl: Some(rrset.ttl),
+                proxied: None,
+                comment: None,
+                tags: vec![],
+                owner: None,
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl DnsProviderBackend for PowerDnsBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    /// Records have neither comments nor tags, and always a TTL. Records without their own TTL
+    /// use `POWERDNS_TTL`.
+    fn desired_settings(&self, settings: &RecordSettings) -> RecordSettings {
+        RecordSettings {
+            ttl: Some(settings.ttl.unwrap_or(self.client.default_ttl())),
+            proxied: None,
+            comment: None,
+            tags: vec![],
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<DnsRecord>, Error> {
+        let rrsets = self.client.get_rrsets().await.map_err(|error| {
+            tracing::error!("Failed to fetch domain records: {:?}", error);
+            Error::new("Failed to fetch domain records".to_string())
+        })?;
+
+        let mut records: Vec<DnsRecord> = vec![];
+        let mut disabled: HashMap<(DomainName, RecordType), Vec<String>> = HashMap::new();
+        for rrset in rrsets {
+            let disabled_values: Vec<String> = rrset.records.iter()
+                .filter(|record| record.disabled)
+                .map(|record| record.content.clone())
+                .collect();
+            if !disabled_values.is_empty() {
+                let record_type: RecordType = rrset.type_field.parse().map_err(Error::new)?;
+                disabled.insert((rrset.name.parse()?, record_type), disabled_values);
+            }
+
+            records.extend(from_rrset(rrset)?);
+        }
+
+        *self.snapshot.lock().unwrap() = record_sets(&records);
+        *self.disabled.lock().unwrap() = disabled;
+
+        Ok(records)
+    }
+
+    async fn create_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Upsert(record)).await
+    }
+
+    async fn update_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Upsert(record)).await
+    }
+
+    async fn delete_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Delete(record)).await
+    }
+
+    /// Every change of a name and type is merged into one record set, and the record sets are
+    /// sent in a single PATCH. PowerDNS applies it as a whole, so a failing request fails every
+    /// plan change.
+    async fn apply_changes(&self, changes: &[RecordChange<'_>]) -> usize {
+        let record_set_updates = record_set_updates(&self.snapshot.lock().unwrap(), changes, self.client.default_ttl());
+        if record_set_updates.is_empty() {
+            return 0;
+        }
+
+        let rrset_changes: Vec<RRsetChange> = {
+            let disabled = self.disabled.lock().unwrap();
+
+            record_set_updates.iter()
+                .map(|update| {
+                    let disabled_values = disabled.get(&(update.name.clone(), update.record_type)).map(Vec::as_slice).unwrap_or_default();
+                    RRsetChange::new(format!("{}.", update.name), update.record_type.to_string(), update.ttl, &update.desired, disabled_values)
+                })
+                .collect()
+        };
+
+        for change in &rrset_changes {
+            let values: Vec<String> = change.records.iter()
+                .map(|record| if record.disabled { format!("{} (disabled)", record.content) } else { record.content.clone() })
+                .collect();
+            tracing::info!("{} {} record set {}: {}", change.changetype.as_str(), change.type_field, change.name, values.join(", "));
+        }
+
+        match self.client.patch_rrsets(&rrset_changes).await {
+            Ok(_) => {
+                let mut snapshot = self.snapshot.lock().unwrap();
+                for update in record_set_updates {
+                    snapshot.insert((update.name, update.record_type), (update.ttl, update.desired));
+                }
+
+                0
+            },
+            Err(error) => {
+                tracing::error!("Failed to change record sets: {:?}", error);
+                record_set_updates.iter().map(|update| update.plan_changes).sum()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn backend(server: &MockServer) -> PowerDnsBackend {
+        PowerDnsBackend::new(Client::new(server.uri(), "key".to_string(), "localhost".to_string(), "example.com.".to_string(), 300))
+    }
+
+    #[tokio::test]
+    async fn test_apply_changes_replaces_listed_record_sets() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/servers/localhost/zones/example.com."))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "example.com.",
+                "name": "example.com.",
+                "rrsets": [
+                    { "name": "pool.example.com.", "type": "A", "ttl": 60, "records": [
+                        { "content": "192.0.2.1", "disabled": false },
+                        { "content": "192.0.2.2", "disabled": false },
+                    ] },
+                    { "name": "old.example.com.", "type": "AAAA", "ttl": 60, "records": [
+                        { "content": "2001:db8::1", "disabled": false },
+                    ] },
+                ]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/servers/localhost/zones/example.com."))
+            .and(body_json(json!({ "rrsets": [
+                { "name": "old.example.com.", "type": "AAAA", "changetype": "DELETE" },
+                { "name": "pool.example.com.", "type": "A", "changetype": "REPLACE", "ttl": 60, "records": [
+                    { "content": "192.0.2.2", "disabled": false },
+                    { "content": "192.0.2.9", "disabled": false },
+                ] },
+            ] })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let backend = backend(&server);
+        let records = backend.list_records().await.unwrap();
+        assert_eq!(records.iter().map(|record| record.id.as_deref()).collect::<Vec<_>>(), vec![Some("192.0.2.1"), Some("192.0.2.2"), Some("2001:db8::1")]);
+
+        let pool = DnsRecord {
+            content: "192.0.2.9".parse().unwrap(),
+            ..records[0].clone()
+        };
+        let changes = vec![RecordChange::Upsert(&pool), RecordChange::Delete(&records[2])];
+
+        assert_eq!(backend.apply_changes(&changes).await, 0);
+        assert_eq!(
+            backend.snapshot.lock().unwrap().get(&("pool.example.com".parse().unwrap(), pool.record_type())),
+            Some(&(60, vec!["192.0.2.2".to_string(), "192.0.2.9".to_string()]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_changes_keeps_disabled_records() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/servers/localhost/zones/example.com."))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "example.com.",
+                "name": "example.com.",
+                "rrsets": [
+                    { "name": "www.example.com.", "type": "A", "ttl": 60, "records": [
+                        { "content": "192.0.2.1", "disabled": false },
+                        { "content": "192.0.2.2", "disabled": true },
+                    ] },
+                    { "name": "old.example.com.", "type": "A", "ttl": 60, "records": [
+                        { "content": "192.0.2.1", "disabled": false },
+                        { "content": "192.0.2.3", "disabled": true },
+                    ] },
+                    { "name": "off.example.com.", "type": "A", "ttl": 60, "records": [
+                        { "content": "192.0.2.4", "disabled": true },
+                    ] },
+                ]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/servers/localhost/zones/example.com."))
+            .and(body_json(json!({ "rrsets": [
+                { "name": "off.example.com.", "type": "A", "changetype": "REPLACE", "ttl": 300, "records": [
+                    { "content": "192.0.2.9", "disabled": false },
+                    { "content": "192.0.2.4", "disabled": true },
+                ] },
+                { "name": "old.example.com.", "type": "A", "changetype": "REPLACE", "ttl": 60, "records": [
+                    { "content": "192.0.2.3", "disabled": true },
+                ] },
+                { "name": "www.example.com.", "type": "A", "changetype": "REPLACE", "ttl": 60, "records": [
+                    { "content": "192.0.2.9", "disabled": false },
+                    { "content": "192.0.2.2", "disabled": true },
+                ] },
+            ] })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let backend = backend(&server);
+        let records = backend.list_records().await.unwrap();
+        assert_eq!(records.iter().map(|record| record.name.as_str()).collect::<Vec<_>>(), vec!["www.example.com", "old.example.com"]);
+
+        let www = DnsRecord {
+            content: "192.0.2.9".parse().unwrap(),
+            ..records[0].clone()
+        };
+        let off = DnsRecord {
+            id: None,
+            name: "off.example.com".parse().unwrap(),
+            ttl: None,
+            ..www.clone()
+        };
+        let changes = vec![RecordChange::Upsert(&www), RecordChange::Delete(&records[1]), RecordChange::Upsert(&off)];
+
+        assert_eq!(backend.apply_changes(&changes).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_apply_changes_failure_fails_every_change() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/servers/localhost/zones/example.com."))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({ "error": "RRset www.example.com. IN A: Conflicts with pre-existing RRset" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let www = DnsRecord {
+            id: None,
+            name: "www.example.com".parse().unwrap(),
+            content: "192.0.2.9".parse().unwrap(),
+            ttl: None,
+            proxied: None,
+            comment: None,
+            tags: vec![],
+            owner: None,
+        };
+        let api = DnsRecord {
+            name: "api.example.com".parse().unwrap(),
+            ..www.clone()
+        };
+
+        assert_eq!(backend(&server).apply_changes(&[RecordChange::Upsert(&www), RecordChange::Upsert(&api)]).await, 2);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use config::sync_entry::RecordType;
+use crate::dns_record::{DnsRecord, DomainName};
+use crate::error::Error;
+use crate::provider::{DnsProviderBackend, RecordChange};
+
+/// The TTL and values of the record sets as last listed or fetched, used by the providers that
+/// replace whole record sets and identify a record of a set by its address.
+pub(crate) type RecordSets = HashMap<(DomainName, RecordType), (u64, Vec<String>)>;
+
+/// The replacement of a record set, carrying out one or more plan changes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RecordSetUpdate {
+    pub name: DomainName,
+    pub record_type: RecordType,
+    pub ttl: u64,
+    /// The values as last listed, empty when the record set is new.
+    pub current: Vec<String>,
+    /// The values after the update, empty when the record set is deleted.
+    pub desired: Vec<String>,
+    /// The number of plan changes the update carries out.
+    pub plan_changes: usize,
+}
+
+/// Apply a single change through [`DnsProviderBackend::apply_changes`], for the providers that
+/// only change whole record sets.
+pub(crate) async fn apply_change(backend: &dyn DnsProviderBackend, change: RecordChange<'_>) -> Result<(), Error> {
+    match backend.apply_changes(&[change]).await {
+        0 => Ok(()),
+        _ => Err(Error::new(format!("Failed to change record {}", change.record().name))),
+    }
+}
+
+/// Whether the values are the same address, however they are spelled. Providers may hand back
+/// an address the way it was entered, such as `2001:0db8:0:0::1`, while records carry it in its
+/// canonical form.
+fn same_address(value: &str, other: &str) -> bool {
+    match (value.parse::<IpAddr>(), other.parse::<IpAddr>()) {
+        (Ok(address), Ok(other_address)) => address == other_address,
+        _ => value == other,
+    }
+}
+
+/// The record sets of the listed records.
+pub(crate) fn record_sets(records: &[DnsRecord]) -> RecordSets {
+    let mut record_sets: RecordSets = HashMap::new();
+
+    for record in records {
+        let (_, values) = record_sets.entry((record.name.clone(), record.record_type()))
+            .or_insert_with(|| (record.ttl.unwrap_or_default(), vec![]));
+        values.push(record.content.to_string());
+    }
+
+    record_sets
+}
+
+/// The record set updates for the plan changes, one per name and type. The desired record set
+/// is the listed one without the deleted and replaced addresses, plus the upserted ones.
+pub(crate) fn record_set_updates(record_sets: &RecordSets, changes: &[RecordChange<'_>], default_ttl: u64) -> Vec<RecordSetUpdate> {
+    let mut groups: BTreeMap<(DomainName, RecordType), Vec<&RecordChange>> = BTreeMap::new();
+    for change in changes {
+        let record = change.record();
+        groups.entry((record.name.clone(), record.record_type())).or_default().push(change);
+    }
+
+    groups.into_iter()
+        .map(|((name, record_type), changes)| {
+            let mut removed: Vec<String> = vec![];
+            let mut added: Vec<String> = vec![];
+            let mut ttl: Option<u64> = None;
+
+            for change in &changes {
+                match change {
+                    RecordChange::Upsert(record) => {
+                        removed.extend(record.id.clone());
+                        added.push(record.content.to_string());
+                        ttl = Some(record.ttl.unwrap_or(default_ttl));
+                    },
+                    RecordChange::Delete(record) => removed.push(record.content.to_string()),
+                }
+            }
+
+            let (current_ttl, current) = record_sets.get(&(name.clone(), record_type))
+                .cloned()
+                .unwrap_or_else(|| (default_ttl, removed.clone()));
+
+            let mut desired: Vec<String> = current.iter()
+                .filter(|value| !removed.iter().any(|removed_value| same_address(removed_value, value)))
+                .cloned()
+                .collect();
+            for value in added {
+                if !desired.iter().any(|desired_value| same_address(desired_value, &value)) {
+                    desired.push(value);
+                }
+            }
+
+            RecordSetUpdate {
+                name,
+                record_type,
+                ttl: ttl.unwrap_or(current_ttl),
+                current,
+                desired,
+                plan_changes: changes.len(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, id: Option<&str>, content: &str) -> DnsRecord {
+        DnsRecord {
+            id: id.map(str::to_string),
+            name: name.parse().unwrap(),
+            content: content.parse().unwrap(),
+            ttl: Some(60),
+            proxied: None,
+            comment: None,
+            tags: vec![],
+            owner: None,
+        }
+    }
+
+    fn values(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_updates_replace_record_sets() {
+        let record_sets: RecordSets = HashMap::from([
+            (("www.example.com".parse().unwrap(), RecordType::A), (300, values(&["192.0.2.1"]))),
+            (("pool.example.com".parse().unwrap(), RecordType::A), (300, values(&["192.0.2.1", "192.0.2.2", "192.0.2.3"]))),
+        ]);
+
+        let www = record("www.example.com", Some("192.0.2.1"), "192.0.2.9");
+        let new = record("new.example.com", None, "192.0.2.9");
+        let pool_kept = record("pool.example.com", Some("192.0.2.1"), "192.0.2.9");
+        let pool_deleted = record("pool.example.com", Some("192.0.2.2"), "192.0.2.2");
+
+        let changes = vec![
+            RecordChange::Upsert(&www),
+            RecordChange::Upsert(&new),
+            RecordChange::Upsert(&pool_kept),
+            RecordChange::Delete(&pool_deleted),
+        ];
+
+        assert_eq!(record_set_updates(&record_sets, &changes, 300), vec![
+            RecordSetUpdate {
+                name: "new.example.com".parse().unwrap(),
+                record_type: RecordType::A,
+                ttl: 60,
+                current: vec![],
+                desired: values(&["192.0.2.9"]),
+                plan_changes: 1,
+            },
+            RecordSetUpdate {
+                name: "pool.example.com".parse().unwrap(),
+                record_type: RecordType::A,
+                ttl: 60,
+                current: values(&["192.0.2.1", "192.0.2.2", "192.0.2.3"]),
+                desired: values(&["192.0.2.3", "192.0.2.9"]),
+                plan_changes: 2,
+            },
+            RecordSetUpdate {
+                name: "www.example.com".parse().unwrap(),
+                record_type: RecordType::A,
+                ttl: 60,
+                current: values(&["192.0.2.1"]),
+                desired: values(&["192.0.2.9"]),
+                plan_changes: 1,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_updates_match_addresses_however_spelled() {
+        let record_sets: RecordSets = HashMap::from([
+            (("pool.example.com".parse().unwrap(), RecordType::AAAA), (300, values(&["2001:0db8:0:0::1", "2001:DB8::2", "2001:db8::3"]))),
+        ]);
+
+        let deleted = record("pool.example.com", None, "2001:db8::1");
+        let replaced = record("pool.example.com", Some("2001:db8:0::2"), "2001:db8::3");
+
+        let updates = record_set_updates(&record_sets, &[RecordChange::Delete(&deleted), RecordChange::Upsert(&replaced)], 300);
+
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].current, values(&["2001:0db8:0:0::1", "2001:DB8::2", "2001:db8::3"]));
+        assert_eq!(updates[0].desired, values(&["2001:db8::3"]));
+    }
+
+    #[test]
+    fn test_record_sets_of_listed_records() {
+        let records = vec![
+            record("pool.example.com", Some("192.0.2.1"), "192.0.2.1"),
+            record("pool.example.com", Some("192.0.2.2"), "192.0.2.2"),
+            record("pool.example.com", Some("2001:db8::1"), "2001:db8::1"),
+        ];
+
+        let record_sets = record_sets(&records);
+
+        assert_eq!(record_sets.get(&("pool.example.com".parse().unwrap(), RecordType::A)), Some(&(60, values(&["192.0.2.1", "192.0.2.2"]))));
+        assert_eq!(record_sets.get(&("pool.example.com".parse().unwrap(), RecordType::AAAA)), Some(&(60, values(&["2001:db8::1"]))));
+    }
+}
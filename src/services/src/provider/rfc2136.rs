@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use clients::rfc2136::Client;
+use clients::rfc2136::types::{Record, Update};
+use config::sync_entry::RecordSettings;
+use crate::dns_record::DnsRecord;
+use crate::error::Error;
+use crate::provider::{Capabilities, DnsProviderBackend, RecordChange};
+use crate::provider::record_sets::{apply_change, record_set_updates, record_sets, RecordSetUpdate, RecordSets};
+
+pub(crate) const NAME: &str = "rfc2136";
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    proxied: false,
+    delete: true,
+    ownership: false,
+};
+
+/// Backend for a name server accepting RFC 2136 dynamic updates.
+///
+/// Updates replace a whole record set and carry the record set as it was last read as
+/// prerequisite, so the records are listed before changing them and the listing is kept until
+/// the next one. Records have no id, the id of a listed record is its address.
+pub struct Rfc2136Backend {
+    client: Client,
+    snapshot: Mutex<RecordSets>,
+}
+
+impl Rfc2136Backend {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            snapshot: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn from_record(record: Record) -> Result<DnsRecord, Error> {
+    let content = record.value.parse().map_err(|_| {
+        Error::new(format!("Record {} has invalid address {}", record.name, record.value))
+    })?;
+
+    Ok(DnsRecord {
+        id: Some(record.value),
+        name: record.name.parse()?,
+        content,
+        ttl: Some(record.ttl as u64),
+        proxied: None,
+        comment: None,
+        tags: vec![],
+        owner: None,
+    })
+}
+
+fn update(record_set_update: &RecordSetUpdate) -> Update {
+    Update {
+        name: record_set_update.name.to_string(),
+        type_field: record_set_update.record_type.to_string(),
+        ttl: record_set_update.ttl as u32,
+        current: record_set_update.current.clone(),
+        desired: record_set_update.desired.clone(),
+    }
+}
+
+#[async_trait]
+impl DnsProviderBackend for Rfc2136Backend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    /// DNS records have neither comments nor tags, and always a TTL. Records without their own
+    /// TTL use `RFC2136_TTL`.
+    fn desired_settings(&self, settings: &RecordSettings) -> RecordSettings {
+        RecordSettings {
+            ttl: Some(settings.ttl.unwrap_or(self.client.default_ttl() as u64)),
+            proxied: None,
+            comment: None,
+            tags: vec![],
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<DnsRecord>, Error> {
+        let records = self.client.transfer_zone().await.map_err(|error| {
+            tracing::error!("Failed to fetch domain records: {:?}", error);
+            Error::new("Failed to fetch domain records".to_string())
+        })?;
+
+        let records: Vec<DnsRecord> = records.into_iter().map(from_record).collect::<Result<_, _>>()?;
+
+        *self.snapshot.lock().unwrap() = record_sets(&records);
+
+        Ok(records)
+    }
+
+    async fn create_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Upsert(record)).await
+    }
+
+    async fn update_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Upsert(record)).await
+    }
+
+    async fn delete_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Delete(record)).await
+    }
+
+    /// Every change of a name and type is merged into one UPDATE message of the record set.
+    async fn apply_changes(&self, changes: &[RecordChange<'_>]) -> usize {
+        let record_set_updates = record_set_updates(&self.snapshot.lock().unwrap(), changes, self.client.default_ttl() as u64);
+        let mut failures: usize = 0;
+
+        for record_set_update in record_set_updates {
+            tracing::info!("Updating {} record set {}: {}", record_set_update.record_type, record_set_update.name, record_set_update.desired.join(", "));
+
+            match self.client.update(&update(&record_set_update)).await {
+                Ok(_) => {
+                    let key = (record_set_update.name, record_set_update.record_type);
+                    self.snapshot.lock().unwrap().insert(key, (record_set_update.ttl, record_set_update.desired));
+                },
+                Err(error) => {
+                    tracing::error!("Failed to update {} record set {}: {:?}", record_set_update.record_type, record_set_update.name, error);
+                    failures += record_set_update.plan_changes;
+                }
+            }
+        }
+
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listed_record_id_is_its_address() {
+        let record = from_record(Record {
+            name: "www.example.com".to_string(),
+            type_field: "A".to_string(),
+            ttl: 300,
+            value: "192.0.2.1".to_string(),
+        }).unwrap();
+
+        assert_eq!((record.id.as_deref(), record.ttl), (Some("192.0.2.1"), Some(300)));
+    }
+}
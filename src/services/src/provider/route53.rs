@@ -0,0 +1,334 @@
+use std::collections::{BTreeSet, HashMap};
+use async_trait::async_trait;
+use clients::route53::{Client, MAX_CHANGES_PER_BATCH};
+use clients::route53::types::{Change, ChangeAction, RecordSet};
+use config::sync_entry::{RecordSettings, RecordType};
+use crate::dns_record::{DnsRecord, DomainName};
+use crate::error::Error;
+use crate::provider::{Capabilities, DnsProviderBackend, RecordChange};
+use crate::provider::record_sets::{apply_change, record_set_updates, RecordSets};
+
+pub(crate) const NAME: &str = "route53";
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    proxied: false,
+    delete: true,
+    ownership: false,
+};
+
+/// Route 53 keeps all the values of a name and type in one record set, which is listed as one
+/// record per value. Records have no id of their own, the id of a listed record is its address.
+fn from_record_set(record_set: RecordSet) -> Result<Vec<DnsRecord>, Error> {
+    let name: DomainName = record_set.name.parse()?;
+
+    record_set.values.iter()
+        .map(|value| {
+            let content = value.parse().map_err(|_| {
+                Error::new(format!("Record {} has invalid address {}", record_set.name, value))
+            })?;
+
+            Ok(DnsRecord {
+                id: Some(value.clone()),
+                name: name.clone(),
+                content,
+                ttl: Some(record_set.ttl),
+                proxied: None,
+                comment: None,
+                tags: vec![],
+                owner: None,
+            })
+        })
+        .collect()
+}
+
+/// A Route 53 change together with the number of plan changes it carries out.
+struct BatchedChange {
+    change: Change,
+    plan_changes: usize,
+}
+
+/// Turn the plan changes into record set changes, one per name and type. The current record
+/// sets are fetched first, so values of a record set the plan does not touch are kept: the
+/// change sends the current values without the deleted and replaced ones, plus the upserted
+/// ones, and deletes the record set once no value is left.
+///
+/// # Returns
+/// The record set changes, and the number of plan changes that failed on the way.
+async fn record_set_changes(client: &Client, changes: &[RecordChange<'_>]) -> (Vec<BatchedChange>, usize) {
+    let key = |change: &RecordChange| (change.record().name.clone(), change.record().record_type());
+    let keys: BTreeSet<(DomainName, RecordType)> = changes.iter().map(key).collect();
+
+    let mut current: RecordSets = HashMap::new();
+    let mut unknown: BTreeSet<(DomainName, RecordType)> = BTreeSet::new();
+
+    for (name, record_type) in keys {
+        match client.get_record_set(name.as_str(), &record_type.to_string()).await {
+            Ok(Some(record_set)) => {
+                current.insert((name, record_type), (record_set.ttl, record_set.values));
+            },
+            Ok(None) => {},
+            Err(error) => {
+                tracing::error!("Failed to fetch {} record set {}: {:?}", record_type, name, error);
+                unknown.insert((name, record_type));
+            }
+        }
+    }
+
+    let known: Vec<RecordChange> = changes.iter().filter(|change| !unknown.contains(&key(change))).copied().collect();
+    let failures = changes.len() - known.len();
+
+    let batched_changes = record_set_updates(&current, &known, client.default_ttl()).into_iter()
+        .filter_map(|update| {
+            let exists = current.contains_key(&(update.name.clone(), update.record_type));
+
+            let (action, values) = match (update.desired.is_empty(), exists) {
+                (false, _) => (ChangeAction::Upsert, update.desired),
+                (true, true) => (ChangeAction::Delete, update.current),
+                (true, false) => {
+                    tracing::info!("{} record {} is already deleted", update.record_type, update.name);
+                    return None;
+                },
+            };
+
+            Some(BatchedChange {
+                change: Change {
+                    action,
+                    record_set: RecordSet {
+                        name: update.name.to_string(),
+                        type_field: update.record_type.to_string(),
+                        ttl: update.ttl,
+                        values,
+                    },
+                },
+                plan_changes: update.plan_changes,
+            })
+        })
+        .collect();
+
+    (batched_changes, failures)
+}
+
+#[async_trait]
+impl DnsProviderBackend for Client {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        CAPABILITIES
+    }
+
+    /// Route 53 records have neither comments nor tags, and always a TTL. Records without
+    /// their own TTL use `ROUTE53_TTL`.
+    fn desired_settings(&self, settings: &RecordSettings) -> RecordSettings {
+        RecordSettings {
+            ttl: Some(settings.ttl.unwrap_or(self.default_ttl())),
+            proxied: None,
+            comment: None,
+            tags: vec![],
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<DnsRecord>, Error> {
+        let record_sets = self.get_record_sets().await.map_err(|error| {
+            tracing::error!("Failed to fetch domain records: {:?}", error);
+            Error::new("Failed to fetch domain records".to_string())
+        })?;
+
+        let mut records: Vec<DnsRecord> = vec![];
+        for record_set in record_sets {
+            records.extend(from_record_set(record_set)?);
+        }
+
+        Ok(records)
+    }
+
+    async fn create_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Upsert(record)).await
+    }
+
+    async fn update_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Upsert(record)).await
+    }
+
+    async fn delete_record(&self, record: &DnsRecord) -> Result<(), Error> {
+        apply_change(self, RecordChange::Delete(record)).await
+    }
+
+    /// Every change of a name and type is merged into one record set change, and the record
+    /// set changes are sent in batches. Route 53 applies a batch as a whole, so a failing batch
+    /// fails every plan change in it.
+    async fn apply_changes(&self, changes: &[RecordChange<'_>]) -> usize {
+        let (batched_changes, mut failures) = record_set_changes(self, changes).await;
+
+        for batch in batched_changes.chunks(MAX_CHANGES_PER_BATCH) {
+            let route53_changes: Vec<Change> = batch.iter().map(|batched| batched.change.clone()).collect();
+
+            for change in &route53_changes {
+                tracing::info!("{} {} record set {}: {}", change.action.as_str(), change.record_set.type_field, change.record_set.name, change.record_set.values.join(", "));
+            }
+
+            match self.change_record_sets(&route53_changes).await {
+                Ok(_) => tracing::info!("Applied {} record set changes", route53_changes.len()),
+                Err(error) => {
+                    tracing::error!("Failed to apply record set changes: {:?}", error);
+                    failures += batch.iter().map(|batched| batched.plan_changes).sum::<usize>();
+                }
+            }
+        }
+
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clients::route53::credentials::Credentials;
+    use wiremock::matchers::{body_string_contains, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> Client {
+        let credentials = Credentials::new("AKIDEXAMPLE".to_string(), "secret".to_string(), None);
+
+        Client::new(server.uri(), "Z1".to_string(), credentials, 300)
+    }
+
+    fn record(name: &str, content: &str) -> DnsRecord {
+        DnsRecord {
+            id: None,
+            name: name.parse().unwrap(),
+            content: content.parse().unwrap(),
+            ttl: Some(300),
+            proxied: None,
+            comment: None,
+            tags: vec![],
+            owner: None,
+        }
+    }
+
+    fn change_body(action: &str, name: &str, values: &[&str]) -> String {
+        let values: String = values.iter().map(|value| format!("<ResourceRecord><Value>{}</Value></ResourceRecord>", value)).collect();
+
+        format!("<Change><Action>{}</Action><ResourceRecordSet><Name>{}.</Name><Type>A</Type><TTL>300</TTL><ResourceRecords>{}</ResourceRecords></ResourceRecordSet></Change>", action, name, values)
+    }
+
+    async fn mount_empty_record_sets(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "<ListResourceRecordSetsResponse><ResourceRecordSets></ResourceRecordSets><IsTruncated>false</IsTruncated></ListResourceRecordSetsResponse>"
+            ))
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn test_record_set_is_listed_per_value() {
+        let records = from_record_set(RecordSet {
+            name: "www.example.com".to_string(),
+            type_field: "A".to_string(),
+            ttl: 60,
+            values: vec!["192.0.2.1".to_string(), "192.0.2.2".to_string()],
+        }).unwrap();
+
+        assert_eq!(records.iter().map(|record| record.content.to_string()).collect::<Vec<_>>(), vec!["192.0.2.1", "192.0.2.2"]);
+        assert!(records.iter().all(|record| record.ttl == Some(60) && record.id == Some(record.content.to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_apply_changes_merges_record_sets() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .and(query_param("name", "pool.example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "<ListResourceRecordSetsResponse><ResourceRecordSets><ResourceRecordSet><Name>pool.example.com.</Name><Type>A</Type><TTL>300</TTL><ResourceRecords><ResourceRecord><Value>192.0.2.1</Value></ResourceRecord><ResourceRecord><Value>192.0.2.2</Value></ResourceRecord></ResourceRecords></ResourceRecordSet></ResourceRecordSets><IsTruncated>false</IsTruncated></ListResourceRecordSetsResponse>"
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mount_empty_record_sets(&server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .and(body_string_contains(format!(
+                "<Changes>{}{}</Changes>",
+                change_body("UPSERT", "pool.example.com", &["192.0.2.2"]),
+                change_body("UPSERT", "www.example.com", &["192.0.2.9"])
+            )))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let www = record("www.example.com", "192.0.2.9");
+        let pool = record("pool.example.com", "192.0.2.1");
+        let changes = vec![RecordChange::Upsert(&www), RecordChange::Upsert(&www), RecordChange::Delete(&pool)];
+
+        assert_eq!(client(&server).apply_changes(&changes).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_apply_changes_keeps_values_of_kept_duplicates() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .and(query_param("name", "www.example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "<ListResourceRecordSetsResponse><ResourceRecordSets><ResourceRecordSet><Name>www.example.com.</Name><Type>A</Type><TTL>300</TTL><ResourceRecords><ResourceRecord><Value>192.0.2.1</Value></ResourceRecord><ResourceRecord><Value>192.0.2.2</Value></ResourceRecord></ResourceRecords></ResourceRecordSet></ResourceRecordSets><IsTruncated>false</IsTruncated></ListResourceRecordSetsResponse>"
+            ))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .and(body_string_contains(format!("<Changes>{}</Changes>", change_body("UPSERT", "www.example.com", &["192.0.2.2", "192.0.2.9"]))))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Under the keep-one policy only the kept value is updated, the duplicate is left alone
+        // when it is not ours to delete.
+        let records = from_record_set(RecordSet {
+            name: "www.example.com".to_string(),
+            type_field: "A".to_string(),
+            ttl: 300,
+            values: vec!["192.0.2.1".to_string(), "192.0.2.2".to_string()],
+        }).unwrap();
+        let kept = DnsRecord::build_record(&records[0].name, "192.0.2.9".parse().unwrap(), &RecordSettings::default(), Some(&records[0]));
+
+        assert_eq!(client(&server).apply_changes(&[RecordChange::Upsert(&kept)]).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_apply_changes_sends_batches() {
+        let server = MockServer::start().await;
+        mount_empty_record_sets(&server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/2013-04-01/hostedzone/Z1/rrset"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let records: Vec<DnsRecord> = (0..MAX_CHANGES_PER_BATCH + 5)
+            .map(|index| record(&format!("host{}.example.com", index), "192.0.2.1"))
+            .collect();
+        let changes: Vec<RecordChange> = records.iter().map(RecordChange::Upsert).collect();
+
+        assert_eq!(client(&server).apply_changes(&changes).await, 5);
+    }
+}
@@ -0,0 +1,680 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::net::IpAddr;
+use config::{DuplicatePolicy, OwnershipSettings};
+use config::sync_entry::{RecordType, SyncEntry};
+use serde::Serialize;
+use crate::dns_record::{DnsRecord, DomainName};
+use crate::error::Error;
+use crate::ip_detection::IpDetector;
+use crate::provider::{DnsProviderBackend, RecordChange};
+
+/// The public addresses resolved for the record types in use, together with the record types
+/// whose lookup failed.
+#[derive(Debug, Clone, Default)]
+pub struct PublicIps {
+    pub addresses: HashMap<RecordType, IpAddr>,
+    pub failed: Vec<RecordType>,
+}
+
+/// Resolve the public address of every record type used by the sync entries. Only the address
+/// families that are actually configured are resolved, a failing family is reported in
+/// `failed` so its records can be skipped while the other family still syncs.
+pub async fn resolve_public_ips(detector: &IpDetector, entries: &[SyncEntry]) -> PublicIps {
+    let mut record_types: Vec<RecordType> = entries.iter()
+        .flat_map(|entry| entry.record_types.clone())
+        .collect();
+    record_types.sort();
+    record_types.dedup();
+
+    let mut public_ips = PublicIps::default();
+
+    for record_type in record_types {
+        match crate::resolve_public_ip(detector, crate::ip_version_for(record_type)).await {
+            Ok(ip) => {
+                public_ips.addresses.insert(record_type, ip);
+            },
+            Err(error) => {
+                tracing::error!("Failed to fetch public IP address for {} records: {:?}", record_type, error);
+                public_ips.failed.push(record_type);
+            }
+        }
+    }
+
+    public_ips
+}
+
+/// What the sync will do with a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Create,
+    Update,
+    Delete,
+    NoOp,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Action::Create => write!(f, "create"),
+            Action::Update => write!(f, "update"),
+            Action::Delete => write!(f, "delete"),
+            Action::NoOp => write!(f, "no-op"),
+        }
+    }
+}
+
+/// A single record field that differs between the zone and the desired state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+/// The planned change for one record, either of a sync entry or an owned record that is no
+/// longer configured.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub action: Action,
+    pub name: DomainName,
+    pub record_type: RecordType,
+    pub current: Option<DnsRecord>,
+    /// The record after the change, `None` when it is deleted.
+    pub desired: Option<DnsRecord>,
+    pub fields: Vec<FieldChange>,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let symbol = match self.action {
+            Action::Create => "+",
+            Action::Update => "~",
+            Action::Delete => "-",
+            Action::NoOp => "=",
+        };
+
+        write!(f, "{} {:<6} {:<4} {}", symbol, self.action, self.record_type, self.name)?;
+
+        for field in &self.fields {
+            match &field.old {
+                Some(old) => write!(f, "\n    {}: {} -> {}", field.field, old, field.new)?,
+                None => write!(f, "\n    {}: {}", field.field, field.new)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Records sharing a name and type that the duplicate policy refused to sync.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub name: DomainName,
+    pub record_type: RecordType,
+    pub records: Vec<DnsRecord>,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "! {:<6} {:<4} {}, {} records", "refuse", self.record_type, self.name, self.records.len())
+    }
+}
+
+/// The changes needed to bring the zone to the desired state, including the records that are
+/// already up to date, and the conflicts that keep records from being synced.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Plan {
+    pub changes: Vec<Change>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Plan {
+    /// The changes that actually modify the zone.
+    pub fn pending(&self) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(|change| change.action != Action::NoOp)
+    }
+
+    /// Whether the zone differs from the desired state.
+    pub fn has_drift(&self) -> bool {
+        self.pending().next().is_some()
+    }
+
+    fn count(&self, action: Action) -> usize {
+        self.changes.iter().filter(|change| change.action == action).count()
+    }
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+
+        for conflict in &self.conflicts {
+            writeln!(f, "{}", conflict)?;
+        }
+
+        write!(
+            f,
+            "Plan: {} to create, {} to update, {} to delete, {} unchanged",
+            self.count(Action::Create),
+            self.count(Action::Update),
+            self.count(Action::Delete),
+            self.count(Action::NoOp)
+        )
+    }
+}
+
+/// The plan of one zone.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ZonePlan {
+    pub zone: String,
+    #[serde(flatten)]
+    pub plan: Plan,
+}
+
+impl Display for ZonePlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Zone {}", self.zone)?;
+        write!(f, "{}", self.plan)
+    }
+}
+
+/// A field change when the desired value is set and differs from the current one.
+fn field_change<T: PartialEq + Display>(field: &str, current: Option<&T>, desired: Option<&T>) -> Option<FieldChange> {
+    let desired = desired?;
+
+    if current == Some(desired) {
+        return None;
+    }
+
+    Some(FieldChange {
+        field: field.to_string(),
+        old: current.map(|value| value.to_string()),
+        new: desired.to_string(),
+    })
+}
+
+/// The fields of the desired record that differ from the current one, every set field of the
+/// desired record when it does not exist yet. Settings the desired record leaves unset are
+/// never a change.
+fn field_changes(current: Option<&DnsRecord>, desired: &DnsRecord) -> Vec<FieldChange> {
+    let tags = |record: &DnsRecord| Some(record.tags.join(",")).filter(|tags| !tags.is_empty());
+
+    [
+        field_change("content", current.map(|record| &record.content), Some(&desired.content)),
+        field_change("ttl", current.and_then(|record| record.ttl.as_ref()), desired.ttl.as_ref()),
+        field_change("proxied", current.and_then(|record| record.proxied.as_ref()), desired.proxied.as_ref()),
+        field_change("comment", current.and_then(|record| record.comment.as_ref()), desired.comment.as_ref()),
+        field_change("tags", current.and_then(tags).as_ref(), tags(desired).as_ref()),
+        field_change("owner", current.and_then(|record| record.owner.as_ref()), desired.owner.as_ref()),
+    ].into_iter().flatten().collect()
+}
+
+/// The entries with their settings resolved for the provider, see
+/// [`DnsProviderBackend::desired_settings`].
+pub fn provider_entries(backend: &dyn DnsProviderBackend, entries: &[SyncEntry]) -> Vec<SyncEntry> {
+    entries.iter()
+        .map(|entry| SyncEntry {
+            settings: backend.desired_settings(&entry.settings),
+            ..entry.clone()
+        })
+        .collect()
+}
+
+/// The change bringing one record, or the missing record when `current` is `None`, to the
+/// desired address and settings. Only created records and records already marked with our owner
+/// id carry the owner id, records created by someone else are never taken over.
+fn upsert_change(
+    name: &DomainName,
+    public_ip: IpAddr,
+    entry: &SyncEntry,
+    current: Option<&DnsRecord>,
+    ownership: &OwnershipSettings
+) -> Change {
+    let mut desired = DnsRecord::build_record(name, public_ip, &entry.settings, current);
+    if ownership.owner_id.is_some() && current.is_none_or(|record| is_owned(record, ownership)) {
+        desired.owner = ownership.owner_id.clone();
+    }
+    let fields = field_changes(current, &desired);
+
+    let action = match (current, fields.is_empty()) {
+        (None, _) => Action::Create,
+        (Some(_), false) => Action::Update,
+        (Some(_), true) => Action::NoOp,
+    };
+
+    tracing::debug!("Planned {} of {} record {}", action, desired.record_type(), name);
+
+    Change {
+        action,
+        name: name.clone(),
+        record_type: desired.record_type(),
+        current: current.cloned(),
+        desired: Some(desired),
+        fields,
+    }
+}
+
+fn delete_change(record: &DnsRecord) -> Change {
+    tracing::debug!("Planned delete of {} record {}", record.record_type(), record.name);
+
+    Change {
+        action: Action::Delete,
+        name: record.name.clone(),
+        record_type: record.record_type(),
+        current: Some(record.clone()),
+        desired: None,
+        fields: vec![],
+    }
+}
+
+/// Whether the record carries the marker of our owner id.
+fn is_owned(record: &DnsRecord, ownership: &OwnershipSettings) -> bool {
+    record.owner.is_some() && record.owner == ownership.owner_id
+}
+
+/// Compare the sync entries against the existing records and plan the changes.
+///
+/// Check each subdomain to see if it exists in the map. If it exists, ensure the IP is correct.
+/// If it is correct, do nothing. If it is incorrect, update the record.
+/// If it is missing then create the record. Other records are only touched when pruning, which
+/// deletes the records marked with our owner id that no longer belong to any entry.
+///
+/// A name with several records of the same type is handled by the duplicate policy, a refused
+/// name is left untouched and reported as a conflict of the plan.
+///
+/// # Arguments
+/// * `domain` - The domain the entry names are relative to.
+/// * `entries` - The sync entries, with the settings resolved by [`provider_entries`].
+/// * `public_ips` - The resolved public address per record type, record types without an
+///   address are skipped.
+/// * `existing_records` - The records currently in the zone grouped by name and record type.
+/// * `ownership` - The owner id to mark the records with and whether to prune.
+/// * `duplicate_policy` - What to do with names that have several records of a type.
+///
+/// # Errors
+/// When the name of an entry is not a valid domain name.
+pub fn build_plan(
+    domain: &DomainName,
+    entries: &[SyncEntry],
+    public_ips: &HashMap<RecordType, IpAddr>,
+    existing_records: &HashMap<(DomainName, RecordType), Vec<DnsRecord>>,
+    ownership: &OwnershipSettings,
+    duplicate_policy: DuplicatePolicy
+) -> Result<Plan, Error> {
+    let mut plan = Plan::default();
+    // Every configured record, including those skipped for lack of an address, so they are
+    // never pruned.
+    let mut configured: HashSet<(DomainName, RecordType)> = HashSet::new();
+
+    for entry in entries {
+        tracing::debug!("Checking entry: {}", entry.name);
+        let fqdn = DomainName::resolve(&entry.name, domain)?;
+
+        for record_type in &entry.record_types {
+            let key = (fqdn.clone(), *record_type);
+            if !configured.insert(key.clone()) {
+                tracing::warn!("Skipping {} record for {}, it is already managed by another entry", record_type, entry.name);
+                continue;
+            }
+
+            let Some(public_ip) = public_ips.get(record_type) else {
+                tracing::warn!("Skipping {} record for {}, no public address available", record_type, entry.name);
+                continue;
+            };
+
+            let current: &[DnsRecord] = existing_records.get(&key).map(Vec::as_slice).unwrap_or_default();
+
+            match (current, duplicate_policy) {
+                ([], _) => plan.changes.push(upsert_change(&fqdn, *public_ip, entry, None, ownership)),
+                ([record], _) => plan.changes.push(upsert_change(&fqdn, *public_ip, entry, Some(record), ownership)),
+                (records, DuplicatePolicy::UpdateAll) => {
+                    tracing::warn!("Found {} {} records for {}, updating all of them", records.len(), record_type, fqdn);
+                    for record in records {
+                        plan.changes.push(upsert_change(&fqdn, *public_ip, entry, Some(record), ownership));
+                    }
+                },
+                (records, DuplicatePolicy::KeepOne) => {
+                    // Keep the record that already has the address, so a sync does not flip
+                    // between duplicates, then the one with the lowest id.
+                    let mut records: Vec<&DnsRecord> = records.iter().collect();
+                    records.sort_by_key(|record| (record.content != *public_ip, record.id.clone()));
+
+                    plan.changes.push(upsert_change(&fqdn, *public_ip, entry, Some(records[0]), ownership));
+
+                    for record in &records[1..] {
+                        if is_owned(record, ownership) {
+                            plan.changes.push(delete_change(record));
+                        } else {
+                            tracing::warn!("Leaving duplicate {} record {} of {}, it is not owned by us", record_type, record.id.as_deref().unwrap_or_default(), fqdn);
+                        }
+                    }
+                },
+                (records, DuplicatePolicy::Refuse) => {
+                    tracing::error!("Found {} {} records for {}, refusing to sync them", records.len(), record_type, fqdn);
+                    plan.conflicts.push(Conflict {
+                        name: fqdn.clone(),
+                        record_type: *record_type,
+                        records: records.to_vec(),
+                    });
+                },
+            }
+        }
+    }
+
+    if ownership.prune {
+        let mut stale: Vec<&DnsRecord> = existing_records.iter()
+            .filter(|(key, _)| !configured.contains(*key))
+            .flat_map(|(_, records)| records)
+            .filter(|record| is_owned(record, ownership))
+            .collect();
+        stale.sort_by(|a, b| (&a.name, a.record_type(), &a.id).cmp(&(&b.name, b.record_type(), &b.id)));
+
+        plan.changes.extend(stale.into_iter().map(delete_change));
+    }
+
+    Ok(plan)
+}
+
+/// Apply the pending changes of the plan, a failing change is logged and does not stop the
+/// remaining ones.
+///
+/// # Returns
+/// The number of changes that failed.
+pub async fn apply_plan(backend: &dyn DnsProviderBackend, plan: &Plan) -> usize {
+    let changes: Vec<RecordChange> = plan.pending()
+        .filter_map(|change| match (&change.desired, &change.current) {
+            (Some(record), _) => Some(RecordChange::Upsert(record)),
+            (None, Some(record)) => Some(RecordChange::Delete(record)),
+            (None, None) => None,
+        })
+        .collect();
+
+    backend.apply_changes(&changes).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::sync_entry::RecordSettings;
+
+    fn entry(name: &str, record_types: Vec<RecordType>) -> SyncEntry {
+        SyncEntry {
+            name: name.to_string(),
+            record_types,
+            settings: RecordSettings::default(),
+        }
+    }
+
+    fn name(raw_input: &str) -> DomainName {
+        raw_input.parse().unwrap()
+    }
+
+    fn record(id: &str, raw_name: &str, content: &str) -> DnsRecord {
+        DnsRecord {
+            id: Some(id.to_string()),
+            name: name(raw_name),
+            content: content.parse().unwrap(),
+            ttl: Some(1),
+            proxied: Some(false),
+            comment: None,
+            tags: vec![],
+            owner: None,
+        }
+    }
+
+    fn existing(records: Vec<DnsRecord>) -> HashMap<(DomainName, RecordType), Vec<DnsRecord>> {
+        let mut existing_records: HashMap<(DomainName, RecordType), Vec<DnsRecord>> = HashMap::new();
+        for record in records {
+            existing_records.entry((record.name.clone(), record.record_type())).or_default().push(record);
+        }
+        existing_records
+    }
+
+    #[test]
+    fn test_build_plan() {
+        let entries = vec![
+            entry("www", vec![RecordType::A, RecordType::AAAA]),
+            entry("api", vec![RecordType::A]),
+        ];
+
+        let public_ips = HashMap::from([
+            (RecordType::A, "1.1.1.1".parse().unwrap()),
+            (RecordType::AAAA, "2606:4700::1".parse().unwrap()),
+        ]);
+
+        let existing_records = existing(vec![
+            record("1", "www.example.com", "1.1.1.1"),
+            record("2", "api.example.com", "8.8.8.8"),
+        ]);
+
+        let plan = build_plan(&name("example.com"), &entries, &public_ips, &existing_records, &OwnershipSettings::default(), DuplicatePolicy::Refuse).unwrap();
+        let actions: Vec<(Action, &str, RecordType)> = plan.changes.iter()
+            .map(|change| (change.action, change.name.as_str(), change.record_type))
+            .collect();
+
+        assert_eq!(actions, vec![
+            (Action::NoOp, "www.example.com", RecordType::A),
+            (Action::Create, "www.example.com", RecordType::AAAA),
+            (Action::Update, "api.example.com", RecordType::A),
+        ]);
+        assert!(plan.has_drift());
+        assert_eq!(plan.changes[2].fields, vec![FieldChange {
+            field: "content".to_string(),
+            old: Some("8.8.8.8".to_string()),
+            new: "1.1.1.1".to_string(),
+        }]);
+        assert!(plan.to_string().ends_with("Plan: 1 to create, 1 to update, 0 to delete, 1 unchanged"));
+    }
+
+    #[test]
+    fn test_build_plan_converges_settings() {
+        let entries = vec![SyncEntry {
+            settings: RecordSettings {
+                ttl: Some(300),
+                comment: Some("home".to_string()),
+                ..Default::default()
+            },
+            ..entry("www", vec![RecordType::A])
+        }];
+        let public_ips = HashMap::from([(RecordType::A, "1.1.1.1".parse().unwrap())]);
+        let existing_records = existing(vec![
+            record("1", "www.example.com", "1.1.1.1"),
+        ]);
+
+        let plan = build_plan(&name("example.com"), &entries, &public_ips, &existing_records, &OwnershipSettings::default(), DuplicatePolicy::Refuse).unwrap();
+        let fields: Vec<&str> = plan.changes[0].fields.iter().map(|field| field.field.as_str()).collect();
+
+        assert_eq!(plan.changes[0].action, Action::Update);
+        assert_eq!(fields, vec!["ttl", "comment"]);
+        assert_eq!(plan.changes[0].desired.as_ref().unwrap().proxied, Some(false));
+    }
+
+    #[test]
+    fn test_build_plan_reverts_proxied_drift() {
+        let entries = vec![SyncEntry {
+            settings: RecordSettings {
+                proxied: Some(false),
+                ..Default::default()
+            },
+            ..entry("www", vec![RecordType::A])
+        }];
+        let public_ips = HashMap::from([(RecordType::A, "1.1.1.1".parse().unwrap())]);
+        let existing_records = existing(vec![
+            DnsRecord {
+                proxied: Some(true),
+                ..record("1", "www.example.com", "1.1.1.1")
+            },
+        ]);
+
+        let plan = build_plan(&name("example.com"), &entries, &public_ips, &existing_records, &OwnershipSettings::default(), DuplicatePolicy::Refuse).unwrap();
+
+        assert_eq!(plan.changes[0].fields, vec![FieldChange {
+            field: "proxied".to_string(),
+            old: Some("true".to_string()),
+            new: "false".to_string(),
+        }]);
+        assert!(plan.to_string().contains("proxied: true -> false"));
+    }
+
+    #[test]
+    fn test_build_plan_names() {
+        let entries = vec![
+            entry("@", vec![RecordType::A]),
+            entry("*.dev", vec![RecordType::A]),
+            entry("api.eu", vec![RecordType::A]),
+            entry("vpn.example.com.", vec![RecordType::A]),
+        ];
+        let public_ips = HashMap::from([(RecordType::A, "1.1.1.1".parse().unwrap())]);
+        let existing_records = existing(vec![
+            record("1", "example.com", "1.1.1.1"),
+            record("2", "*.dev.example.com", "8.8.8.8"),
+        ]);
+
+        let plan = build_plan(&name("example.com."), &entries, &public_ips, &existing_records, &OwnershipSettings::default(), DuplicatePolicy::Refuse).unwrap();
+        let actions: Vec<(Action, &str, &str)> = plan.changes.iter()
+            .map(|change| (change.action, change.name.as_str(), change.desired.as_ref().unwrap().name.as_str()))
+            .collect();
+
+        assert_eq!(actions, vec![
+            (Action::NoOp, "example.com", "example.com"),
+            (Action::Update, "*.dev.example.com", "*.dev.example.com"),
+            (Action::Create, "api.eu.example.com", "api.eu.example.com"),
+            (Action::Create, "vpn.example.com", "vpn.example.com"),
+        ]);
+    }
+
+    #[test]
+    fn test_build_plan_matches_names_regardless_of_spelling() {
+        let entries = vec![entry("Dev", vec![RecordType::A]), entry("www.EXAMPLE.com.", vec![RecordType::A])];
+        let public_ips = HashMap::from([(RecordType::A, "1.1.1.1".parse().unwrap())]);
+        let existing_records = existing(vec![
+            record("1", "Dev.Example.com", "1.1.1.1"),
+            record("2", "www.example.com.", "1.1.1.1"),
+        ]);
+
+        let plan = build_plan(&name("example.com"), &entries, &public_ips, &existing_records, &OwnershipSettings::default(), DuplicatePolicy::Refuse).unwrap();
+
+        assert!(!plan.has_drift());
+        assert!(build_plan(&name("example.com"), &[entry("a..b", vec![RecordType::A])], &public_ips, &existing_records, &OwnershipSettings::default(), DuplicatePolicy::Refuse).is_err());
+    }
+
+    #[test]
+    fn test_build_plan_prunes_owned_records() {
+        let owned = |id: &str, name: &str, content: &str, owner: &str| DnsRecord {
+            owner: Some(owner.to_string()),
+            ..record(id, name, content)
+        };
+        let entries = vec![entry("www", vec![RecordType::A, RecordType::AAAA]), entry("api", vec![RecordType::A])];
+        let public_ips = HashMap::from([(RecordType::A, "1.1.1.1".parse().unwrap())]);
+        let existing_records = existing(vec![
+            record("1", "www.example.com", "1.1.1.1"),
+            owned("2", "www.example.com", "2001:db8::1", "home"),
+            owned("3", "old.example.com", "1.1.1.1", "home"),
+            owned("4", "lab.example.com", "1.1.1.1", "lab"),
+            record("5", "mail.example.com", "1.1.1.1"),
+        ]);
+        let ownership = OwnershipSettings {
+            owner_id: Some("home".to_string()),
+            prune: true,
+        };
+
+        let plan = build_plan(&name("example.com"), &entries, &public_ips, &existing_records, &ownership, DuplicatePolicy::Refuse).unwrap();
+        let actions: Vec<(Action, &str)> = plan.changes.iter()
+            .map(|change| (change.action, change.name.as_str()))
+            .collect();
+
+        assert_eq!(actions, vec![
+            (Action::NoOp, "www.example.com"),
+            (Action::Create, "api.example.com"),
+            (Action::Delete, "old.example.com"),
+        ]);
+        assert_eq!(plan.changes[1].desired.as_ref().unwrap().owner.as_deref(), Some("home"));
+        assert!(plan.to_string().contains("- delete A old.example.com"));
+    }
+
+    #[test]
+    fn test_build_plan_does_not_take_over_records() {
+        let public_ips = HashMap::from([(RecordType::A, "1.1.1.1".parse().unwrap())]);
+        let ownership = OwnershipSettings {
+            owner_id: Some("home".to_string()),
+            prune: true,
+        };
+
+        let existing_records = existing(vec![record("1", "www.example.com", "2.2.2.2")]);
+        let plan = build_plan(&name("example.com"), &[entry("www", vec![RecordType::A])], &public_ips, &existing_records, &ownership, DuplicatePolicy::Refuse).unwrap();
+        let desired = plan.changes[0].desired.clone().unwrap();
+        assert_eq!(plan.changes[0].action, Action::Update);
+        assert_eq!(desired.owner, None);
+        assert!(plan.changes[0].fields.iter().all(|field| field.field != "owner"));
+
+        let existing_records = existing(vec![desired]);
+        let plan = build_plan(&name("example.com"), &[entry("api", vec![RecordType::A])], &public_ips, &existing_records, &ownership, DuplicatePolicy::Refuse).unwrap();
+        let actions: Vec<(Action, &str)> = plan.changes.iter()
+            .map(|change| (change.action, change.name.as_str()))
+            .collect();
+        assert_eq!(actions, vec![(Action::Create, "api.example.com")]);
+    }
+
+    #[test]
+    fn test_build_plan_duplicate_policies() {
+        let entries = vec![entry("www", vec![RecordType::A])];
+        let public_ips = HashMap::from([(RecordType::A, "1.1.1.1".parse().unwrap())]);
+        let existing_records = existing(vec![
+            DnsRecord {
+                owner: Some("home".to_string()),
+                ..record("1", "www.example.com", "8.8.8.8")
+            },
+            record("2", "www.example.com", "1.1.1.1"),
+            record("3", "www.example.com", "9.9.9.9"),
+        ]);
+        let ownership = OwnershipSettings {
+            owner_id: Some("home".to_string()),
+            prune: false,
+        };
+        let plan = |policy: DuplicatePolicy| {
+            build_plan(&name("example.com"), &entries, &public_ips, &existing_records, &ownership, policy).unwrap()
+        };
+        let actions = |plan: &Plan| -> Vec<(Action, Option<String>)> {
+            plan.changes.iter()
+                .map(|change| (change.action, change.current.as_ref().and_then(|record| record.id.clone())))
+                .collect()
+        };
+
+        let update_all = plan(DuplicatePolicy::UpdateAll);
+        assert_eq!(actions(&update_all), vec![
+            (Action::Update, Some("1".to_string())),
+            (Action::NoOp, Some("2".to_string())),
+            (Action::Update, Some("3".to_string())),
+        ]);
+
+        let keep_one = plan(DuplicatePolicy::KeepOne);
+        assert_eq!(actions(&keep_one), vec![
+            (Action::NoOp, Some("2".to_string())),
+            (Action::Delete, Some("1".to_string())),
+        ]);
+
+        let refuse = plan(DuplicatePolicy::Refuse);
+        assert!(refuse.changes.is_empty());
+        assert_eq!(refuse.conflicts.len(), 1);
+        assert_eq!(refuse.conflicts[0].records.len(), 3);
+        assert!(refuse.to_string().contains("! refuse A www.example.com, 3 records"));
+    }
+
+    #[test]
+    fn test_build_plan_skips_missing_family() {
+        let entries = vec![entry("www", vec![RecordType::A, RecordType::AAAA])];
+        let public_ips = HashMap::from([(RecordType::A, "1.1.1.1".parse().unwrap())]);
+        let existing_records = existing(vec![
+            record("1", "www.example.com", "1.1.1.1"),
+        ]);
+
+        let plan = build_plan(&name("example.com"), &entries, &public_ips, &existing_records, &OwnershipSettings::default(), DuplicatePolicy::Refuse).unwrap();
+
+        assert_eq!(plan.changes.len(), 1);
+        assert!(!plan.has_drift());
+    }
+}